@@ -5,7 +5,10 @@ use tracy_client::frame_mark;
 
 use crate::{
     rendering::renderer::Renderer,
-    simulation::simulation::{Simulation, SimulationRunner},
+    simulation::{
+        sim_events::AppCommand,
+        simulation::{Simulation, SimulationRunner},
+    },
     utils::timer::Timer,
 };
 
@@ -21,6 +24,21 @@ pub struct AppData {
     pub stop: bool,
 }
 
+impl AppData {
+    // Requests of the simulation events
+    pub fn apply_commands(&mut self) {
+        for command in self.sim.take_commands() {
+            info!("Event command: {:?}", command);
+            match command {
+                AppCommand::Pause => self.running = false,
+                AppCommand::Resume => self.running = true,
+                AppCommand::Stop => self.stop = true,
+                AppCommand::SetStepsPerFrame(steps) => self.steps_per_frame = steps,
+            }
+        }
+    }
+}
+
 pub struct AppMain {
     data: AppData,
     renderer: Box<dyn Renderer>,
@@ -75,6 +93,12 @@ impl AppMain {
                     let _span = tracy_client::span!("Simulation step");
 
                     self.data.sim_runner.step(&mut self.data.sim);
+
+                    // Events may pause or stop the app in the middle of a frame
+                    self.data.apply_commands();
+                    if !self.data.running || self.data.stop {
+                        break;
+                    }
                 }
             }
             sim_elapsed += prof_timer.lap();
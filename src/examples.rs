@@ -20,30 +20,39 @@ use crate::{
         safe_sfml::{ViewData, WindowData},
     },
     simulation::{
-        areas::{Disk, Point, Rect},
+        areas::{Complement, Disk, Point, Rect},
+        bonds::{DistanceConstraint, DistanceConstraints, SoftBodyBuilder, Springs},
+        colliders::Colliders,
         color::Color,
+        distributions::MaxwellBoltzmannVelocityGenerator,
+        emitters::{Burst, DutyCycle, Emitter},
+        fields::{CurlNoiseField, FieldDrag},
         forces::{Drag, Gravity, Repulsion, UniformDrag, UniformGravity},
         generators::{
             ConstantGenerator, HSVAGenerator, PointGenerator, RandomDiskPointGenerator,
             RandomRectPointGenerator, UniformDiskPointsGenerator, UniformGenerator,
-            Vector2PolarGenerator,
+            UniformRectPointsGenerator, Vector2PolarGenerator,
         },
+        initial_conditions::{DiskGalaxy, GalaxyCollision},
         integrator::GaussianIntegrator,
+        pair_potentials::{LennardJones, PairPotentialForce},
         particles::{GeneratorFactory, ParticleFactory, Particles},
         quadtree::{QuadTree, QuadtreeForces},
         random::RngGenerator,
         sim_events::{DefaultSimEventsHandler, SimEvent},
         simulation::{ConstantSimulationRunner, Simulation, SimulationRunner},
+        sources::{FieldSources, RadialSource, SharedSources, VortexSource},
+        sph::{IdealGas, Sph},
+        system_list::SystemList,
         systems::{
-            ColorWheel, ConstantConsumer, ConstantEmitter, Physics, System, VelocityIntegrator,
-            Wall,
+            ColorWheel, ConstantConsumer, ConstantEmitter, Loop, Physics, System,
+            VelocityIntegrator, Void, Wall,
         },
         types::Scalar,
     },
-    utils::sorted_vec::SortedVec,
 };
 
-fn get_default_input_callback() -> InputCallback {
+pub fn get_default_input_callback() -> InputCallback {
     let mut keys_state = KeysState::new();
     let mut single_step = false;
 
@@ -205,7 +214,9 @@ pub fn benchmark_base() -> AppMain {
     let mut particles = Particles::new_empty();
     factory.create(1_000_000, &mut particles);
 
-    let sim = Simulation::new(particles, vec![limit_cond, velocity_integrator], None);
+    let systems: Vec<Box<dyn System>> = vec![limit_cond, velocity_integrator];
+
+    let sim = Simulation::new(particles, systems, None);
 
     let sim_runner = Box::new(ConstantSimulationRunner::new(1.));
 
@@ -269,11 +280,9 @@ pub fn benchmark_forces() -> AppMain {
         100.,
     ));
 
-    let sim = Simulation::new(
-        particles,
-        vec![limit_cond, consumer, physics, velocity_integrator],
-        None,
-    );
+    let systems: Vec<Box<dyn System>> = vec![limit_cond, consumer, physics, velocity_integrator];
+
+    let sim = Simulation::new(particles, systems, None);
 
     let sim_runner = Box::new(ConstantSimulationRunner::new(1.));
 
@@ -303,18 +312,20 @@ pub fn fireworks(width: u32, height: u32) -> AppMain {
     let gravity = Box::new(UniformGravity::new(Vector2::new(0., -0.001)));
     let drag = Box::new(UniformDrag::new(0.0005, Vector2::new(-4., 0.)));
 
+    // Sources dropped with the mouse
+    let sources: SharedSources = Arc::new(RwLock::new(Vec::new()));
+    let field_sources = Box::new(FieldSources::new(sources.clone()));
+
     let physics = Box::new(Physics::new(
-        vec![gravity, drag],
+        vec![gravity, drag, field_sources],
         Box::new(GaussianIntegrator),
     ));
 
     let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
 
-    let sim = Simulation::new(
-        Particles::new_empty(),
-        vec![limit_cond, physics, velocity_integrator],
-        None,
-    );
+    let systems: Vec<Box<dyn System>> = vec![limit_cond, physics, velocity_integrator];
+
+    let sim = Simulation::new(Particles::new_empty(), systems, None);
 
     let sim_runner = Box::new(ConstantSimulationRunner::new(1.));
 
@@ -351,6 +362,33 @@ pub fn fireworks(width: u32, height: u32) -> AppMain {
 
                         firework_factory.create(1_000, &mut data.sim.particles);
                     }
+                    SfmlEvent::MouseButtonPressed {
+                        button: sfml::window::mouse::Button::Right,
+                        ..
+                    } => {
+                        sources.write().unwrap().push(Box::new(RadialSource::new(
+                            event.position.unwrap(),
+                            0.5,
+                            1,
+                            10.,
+                            None,
+                        )));
+                    }
+                    SfmlEvent::MouseButtonPressed {
+                        button: sfml::window::mouse::Button::Middle,
+                        ..
+                    } => {
+                        sources.write().unwrap().push(Box::new(VortexSource::new(
+                            event.position.unwrap(),
+                            0.5,
+                            1,
+                            10.,
+                            Some(200.),
+                        )));
+                    }
+                    SfmlEvent::KeyPressed { code: Key::C, .. } => {
+                        sources.write().unwrap().clear();
+                    }
                     _ => {}
                 }
             }
@@ -418,33 +456,44 @@ pub fn flow(width: u32, height: u32) -> AppMain {
         Box::new(GaussianIntegrator),
     ));
 
+    // Weakly compressible fluid (pressure & viscosity)
+    let fluid = Box::new(Sph::new_default(
+        Rect::new(
+            Vector2::new(0., 0.),
+            Vector2::new(width as Scalar, height as Scalar),
+        ),
+        6.,
+        0.02,
+        Box::new(IdealGas::new(0.3, 0.2)),
+    ));
+
     let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
 
     let color_wheel = Box::new(ColorWheel { speed: 0.2 });
 
-    let systems: Vec<Box<dyn System>> = vec![
-        emitter,
-        consumer,
-        limit_cond,
-        physics,
-        velocity_integrator,
-        color_wheel,
-    ];
+    let mut systems = SystemList::new();
+    let tap = systems.add_named("tap", emitter);
+    systems.add(consumer);
+    systems.add(limit_cond);
+    systems.add(fluid);
+    systems.add(physics);
+    systems.add(velocity_integrator);
+    systems.add(color_wheel);
 
-    let mut events = SortedVec::new();
-    events.add(SimEvent::new(
+    let events = vec![SimEvent::new(
         5000.,
-        Box::new(|particles, systems| {
+        Box::new(move |context| {
             // Slow down particles
-            particles
+            context
+                .particles
                 .velocities
                 .par_iter_mut()
                 .for_each(|vel| *vel *= 0.5);
 
-            // Remove emitter
-            systems.remove(0);
+            // Close the tap for good
+            context.systems.remove(tap);
         }),
-    ));
+    )];
 
     let events_handler = Box::new(DefaultSimEventsHandler::new(events, 0.));
 
@@ -452,6 +501,25 @@ pub fn flow(width: u32, height: u32) -> AppMain {
 
     let sim_runner = Box::new(ConstantSimulationRunner::new(4.));
 
+    // T opens or closes the tap until it is removed
+    let mut default_input_callback = get_default_input_callback();
+    let input_callback = Box::new(
+        move |data: &mut AppData,
+              render_data: &mut RenderData,
+              dt: Scalar,
+              events: &Vec<WindowEvent>| {
+            default_input_callback(data, render_data, dt, events);
+
+            for event in events {
+                if let SfmlEvent::KeyPressed { code: Key::T, .. } = event.original {
+                    if let Some(enabled) = data.sim.systems.is_enabled(tap) {
+                        data.sim.systems.set_enabled(tap, !enabled);
+                    }
+                }
+            }
+        },
+    );
+
     base_iridium_app(
         width,
         height,
@@ -459,6 +527,370 @@ pub fn flow(width: u32, height: u32) -> AppMain {
         sim_runner,
         "Flow",
         max_fps(60),
+        input_callback,
+        None,
+    )
+}
+
+pub fn lennard_jones(width: u32, height: u32) -> AppMain {
+    let mut rng_gen = RngGenerator::new(0);
+
+    let sim_space = Rect::new(
+        Vector2::new(0., 0.),
+        Vector2::new(width as Scalar, height as Scalar),
+    );
+
+    // Start from a lattice in the lower half with thermal velocities
+    let mut particles = Particles::new_empty();
+    GeneratorFactory::new(
+        Box::new(UniformRectPointsGenerator::new(Rect::new(
+            Vector2::new(0., 0.),
+            Vector2::new(width as Scalar, height as Scalar / 2.),
+        ))),
+        Box::new(MaxwellBoltzmannVelocityGenerator::new(
+            rng_gen.next(),
+            0.002,
+            1.,
+        )),
+        Box::new(ConstantGenerator::new(1.)),
+        Box::new(ConstantGenerator::new(Color::CYAN)),
+    )
+    .create(2000, &mut particles);
+
+    let limit_cond = Box::new(Wall {
+        x_min: 0.,
+        y_min: 0.,
+        x_max: width as Scalar,
+        y_max: height as Scalar,
+        restitution: 1.,
+    });
+
+    let sigma = 6.;
+    let lennard_jones = Box::new(PairPotentialForce::new(
+        LennardJones::new(0.5, sigma, 2.5 * sigma),
+        sim_space,
+    ));
+    let gravity = Box::new(UniformGravity::new(Vector2::new(0., -0.001)));
+
+    let physics = Box::new(Physics::new(
+        vec![lennard_jones, gravity],
+        Box::new(GaussianIntegrator),
+    ));
+
+    let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
+
+    let systems: Vec<Box<dyn System>> = vec![limit_cond, physics, velocity_integrator];
+
+    let sim = Simulation::new(particles, systems, None);
+
+    let sim_runner = Box::new(ConstantSimulationRunner::new(0.05));
+
+    base_iridium_app(
+        width,
+        height,
+        sim,
+        sim_runner,
+        "Lennard-Jones",
+        max_fps(60),
+        get_default_input_callback(),
+        None,
+    )
+}
+
+pub fn periodic_gas(width: u32, height: u32) -> AppMain {
+    let mut rng_gen = RngGenerator::new(0);
+
+    let limit_cond = Loop {
+        x_min: 0.,
+        y_min: 0.,
+        x_max: width as Scalar,
+        y_max: height as Scalar,
+    };
+    let boundary = limit_cond.boundary();
+
+    let mut particles = Particles::new_empty();
+    GeneratorFactory::new(
+        Box::new(UniformRectPointsGenerator::new(boundary.rect.clone())),
+        Box::new(MaxwellBoltzmannVelocityGenerator::new(
+            rng_gen.next(),
+            0.002,
+            1.,
+        )),
+        Box::new(ConstantGenerator::new(1.)),
+        Box::new(ConstantGenerator::new(Color::CYAN)),
+    )
+    .create(2000, &mut particles);
+
+    // No walls: particles interact across the edges of the window
+    let sigma = 6.;
+    let lennard_jones = Box::new(
+        PairPotentialForce::new(
+            LennardJones::new(0.5, sigma, 2.5 * sigma),
+            boundary.rect.clone(),
+        )
+        .with_periodic(boundary),
+    );
+
+    let physics = Box::new(Physics::new(
+        vec![lennard_jones],
+        Box::new(GaussianIntegrator),
+    ));
+
+    let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
+
+    let systems: Vec<Box<dyn System>> = vec![Box::new(limit_cond), physics, velocity_integrator];
+
+    let sim = Simulation::new(particles, systems, None);
+
+    let sim_runner = Box::new(ConstantSimulationRunner::new(0.05));
+
+    base_iridium_app(
+        width,
+        height,
+        sim,
+        sim_runner,
+        "Periodic gas",
+        max_fps(60),
+        get_default_input_callback(),
+        None,
+    )
+}
+
+pub fn soft_bodies(width: u32, height: u32) -> AppMain {
+    let width_s = width as Scalar;
+    let height_s = height as Scalar;
+
+    let mut particles = Particles::new_empty();
+
+    // Spring based bodies
+    let jelly = SoftBodyBuilder::new(Vector2::new(0.3, 0.), 1., Color::GREEN, 0.05, 0.02);
+    let mut springs = jelly.soft_disk(
+        Disk::new(Vector2::new(width_s / 4., height_s * 0.7), width_s / 10.),
+        300,
+        &mut particles,
+    );
+
+    let fabric = SoftBodyBuilder::new(Vector2::new(-0.2, 0.), 1., Color::MAGENTA, 0.1, 0.05);
+    springs.append(&mut fabric.cloth(
+        Rect::new(
+            Vector2::new(width_s * 0.55, height_s * 0.55),
+            Vector2::new(width_s / 3., height_s / 4.),
+        ),
+        400,
+        &mut particles,
+    ));
+
+    // Rigid rope
+    let cord = SoftBodyBuilder::new(Vector2::zeros(), 1., Color::YELLOW, 0., 0.);
+    let rope = cord.rope(
+        Vector2::new(width_s * 0.2, height_s * 0.4),
+        Vector2::new(width_s * 0.8, height_s * 0.45),
+        100,
+        &mut particles,
+    );
+
+    let limit_cond = Box::new(Wall {
+        x_min: 0.,
+        y_min: 0.,
+        x_max: width_s,
+        y_max: height_s,
+        restitution: 0.5,
+    });
+
+    let gravity = Box::new(UniformGravity::new(Vector2::new(0., -0.002)));
+    let springs = Box::new(Springs::new(springs));
+
+    let physics = Box::new(Physics::new(
+        vec![gravity, springs],
+        Box::new(GaussianIntegrator),
+    ));
+
+    let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
+
+    let constraints = Box::new(DistanceConstraints::new(
+        DistanceConstraint::from_springs(&rope),
+        10,
+        1.,
+    ));
+
+    let systems: Vec<Box<dyn System>> = vec![limit_cond, physics, velocity_integrator, constraints];
+
+    let sim = Simulation::new(particles, systems, None);
+
+    let sim_runner = Box::new(ConstantSimulationRunner::new(1.));
+
+    base_iridium_app(
+        width,
+        height,
+        sim,
+        sim_runner,
+        "Soft Bodies",
+        max_fps(60),
+        get_default_input_callback(),
+        None,
+    )
+}
+
+pub fn wind(width: u32, height: u32) -> AppMain {
+    let mut rng_gen = RngGenerator::new(0);
+
+    let emitter = Box::new(ConstantEmitter::new(
+        Box::new(GeneratorFactory::new(
+            Box::new(RandomRectPointGenerator::new(
+                Rect::new(
+                    Vector2::new(0., 0.),
+                    Vector2::new(width as Scalar / 20., height as Scalar),
+                ),
+                rng_gen.next(),
+            )),
+            Box::new(ConstantGenerator::new(Vector2::new(0.5, 0.))),
+            Box::new(ConstantGenerator::new(1.)),
+            Box::new(HSVAGenerator::new(
+                Box::new(UniformGenerator::new(rng_gen.next(), 180., 240.)),
+                Box::new(ConstantGenerator::new(1.)),
+                Box::new(ConstantGenerator::new(1.)),
+                Box::new(ConstantGenerator::new(1.)),
+            )),
+        )),
+        100.,
+    ));
+
+    // Particles leaving the screen are removed
+    let consumer = Box::new(ConstantConsumer::new(
+        Box::new(Rect::new(
+            Vector2::new(width as Scalar, -(height as Scalar)),
+            Vector2::new(width as Scalar, 3. * height as Scalar),
+        )),
+        1E6,
+    ));
+
+    let turbulence = Box::new(FieldDrag::new(
+        Box::new(CurlNoiseField::new(rng_gen.next(), 150., 1., 0.001)),
+        0.02,
+    ));
+    let drag = Box::new(UniformDrag::new(0.005, Vector2::new(0.5, 0.)));
+
+    let physics = Box::new(Physics::new(
+        vec![turbulence, drag],
+        Box::new(GaussianIntegrator),
+    ));
+
+    let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
+
+    let systems: Vec<Box<dyn System>> = vec![emitter, consumer, physics, velocity_integrator];
+
+    let sim = Simulation::new(Particles::new_empty(), systems, None);
+
+    let sim_runner = Box::new(ConstantSimulationRunner::new(1.));
+
+    base_iridium_app(
+        width,
+        height,
+        sim,
+        sim_runner,
+        "Wind",
+        max_fps(60),
+        get_default_input_callback(),
+        None,
+    )
+}
+
+pub fn obstacles(width: u32, height: u32) -> AppMain {
+    let mut rng_gen = RngGenerator::new(0);
+    let width_s = width as Scalar;
+    let height_s = height as Scalar;
+
+    let emitter = Box::new(ConstantEmitter::new(
+        Box::new(GeneratorFactory::new(
+            Box::new(RandomRectPointGenerator::new(
+                Rect::new(
+                    Vector2::new(width_s * 0.1, height_s * 0.95),
+                    Vector2::new(width_s * 0.8, height_s * 0.04),
+                ),
+                rng_gen.next(),
+            )),
+            Box::new(ConstantGenerator::new(Vector2::new(0., -0.2))),
+            Box::new(ConstantGenerator::new(1.)),
+            Box::new(HSVAGenerator::new(
+                Box::new(UniformGenerator::new(rng_gen.next(), 20., 60.)),
+                Box::new(ConstantGenerator::new(1.)),
+                Box::new(ConstantGenerator::new(1.)),
+                Box::new(ConstantGenerator::new(1.)),
+            )),
+        )),
+        20.,
+    ));
+
+    // Remove particles falling out of the bottom
+    let consumer = Box::new(ConstantConsumer::new(
+        Box::new(Rect::new(
+            Vector2::new(0., 0.),
+            Vector2::new(width_s, height_s * 0.02),
+        )),
+        1E6,
+    ));
+
+    let mut colliders = Colliders::new(0.5, 0.05, 4, 20.);
+
+    // Funnel
+    colliders.add_polyline(&[
+        Vector2::new(width_s * 0.05, height_s * 0.85),
+        Vector2::new(width_s * 0.45, height_s * 0.6),
+        Vector2::new(width_s * 0.45, height_s * 0.5),
+    ]);
+    colliders.add_polyline(&[
+        Vector2::new(width_s * 0.95, height_s * 0.85),
+        Vector2::new(width_s * 0.55, height_s * 0.6),
+        Vector2::new(width_s * 0.55, height_s * 0.5),
+    ]);
+
+    // Pegs
+    for i in 0..7 {
+        for j in 0..3 {
+            let offset = if j % 2 == 0 { 0. } else { 0.07 };
+            colliders.add_circle(
+                Vector2::new(
+                    width_s * (0.08 + offset + i as Scalar * 0.14),
+                    height_s * (0.35 - j as Scalar * 0.1),
+                ),
+                width_s * 0.015,
+            );
+        }
+    }
+
+    // Tilted concave cup
+    colliders.add_polyline(&[
+        Vector2::new(width_s * 0.6, height_s * 0.1),
+        Vector2::new(width_s * 0.65, height_s * 0.05),
+        Vector2::new(width_s * 0.8, height_s * 0.05),
+        Vector2::new(width_s * 0.9, height_s * 0.12),
+    ]);
+
+    let gravity = Box::new(UniformGravity::new(Vector2::new(0., -0.002)));
+
+    let physics = Box::new(Physics::new(vec![gravity], Box::new(GaussianIntegrator)));
+
+    let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
+
+    let systems: Vec<Box<dyn System>> = vec![
+        emitter,
+        consumer,
+        physics,
+        velocity_integrator,
+        Box::new(colliders),
+    ];
+
+    let sim = Simulation::new(Particles::new_empty(), systems, None);
+
+    let sim_runner = Box::new(ConstantSimulationRunner::new(2.));
+
+    base_iridium_app(
+        width,
+        height,
+        sim,
+        sim_runner,
+        "Obstacles",
+        max_fps(60),
         get_default_input_callback(),
         None,
     )
@@ -472,6 +904,164 @@ impl System for SimReset {
     }
 }
 
+pub fn emitters(width: u32, height: u32) -> AppMain {
+    let mut rng_gen = RngGenerator::new(0);
+    let width_s = width as Scalar;
+    let height_s = height as Scalar;
+
+    let spark_factory = |rng_gen: &mut RngGenerator, speed: Scalar, spread: Scalar, hue: Scalar| {
+        Box::new(GeneratorFactory::new(
+            Box::new(PointGenerator::new(Point::new(Vector2::zeros()))),
+            Box::new(Vector2PolarGenerator::new(
+                Box::new(UniformGenerator::new(rng_gen.next(), speed * 0.8, speed)),
+                Box::new(UniformGenerator::new(rng_gen.next(), -spread, spread)),
+            )),
+            Box::new(ConstantGenerator::new(1.)),
+            Box::new(HSVAGenerator::new(
+                Box::new(UniformGenerator::new(rng_gen.next(), hue, hue + 40.)),
+                Box::new(ConstantGenerator::new(1.)),
+                Box::new(ConstantGenerator::new(1.)),
+                Box::new(ConstantGenerator::new(1.)),
+            )),
+        ))
+    };
+
+    // Sprinkler: sweeping nozzle spraying in pulses
+    let sprinkler = Box::new(
+        Emitter::new(spark_factory(&mut rng_gen, 1.2, 0.03, 180.), 40.)
+            .with_position(Vector2::new(width_s / 2., 0.))
+            .with_rotation(Box::new(|t| PI / 2. + 0.7 * (t * 0.01).sin()))
+            .with_duty_cycle(DutyCycle::new(60., 40., 0.)),
+    );
+
+    // Rocket: figure-eight path leaving a trail, exploding regularly
+    let center = Vector2::new(width_s / 2., height_s * 0.6);
+    let rocket = Box::new(
+        Emitter::new(spark_factory(&mut rng_gen, 0.3, PI, 0.), 30.)
+            .with_rate_curve(Box::new(|t| 30. + 20. * (t * 0.05).sin()))
+            .with_path(
+                Box::new(move |t| {
+                    center
+                        + Vector2::new(
+                            width_s * 0.35 * (t * 0.004).sin(),
+                            height_s * 0.2 * (t * 0.008).sin(),
+                        )
+                }),
+                0.5,
+            )
+            .with_bursts(Burst::periodic(200., 400., 500, 50)),
+    );
+
+    // Particles leaving the window are removed
+    let void = Box::new(Void {
+        area: Box::new(Complement::new(Box::new(Rect::new(
+            Vector2::new(0., 0.),
+            Vector2::new(width_s, height_s),
+        )))),
+    });
+
+    let gravity = Box::new(UniformGravity::new(Vector2::new(0., -0.002)));
+    let drag = Box::new(UniformDrag::new(0.002, Vector2::zeros()));
+
+    let physics = Box::new(Physics::new(
+        vec![gravity, drag],
+        Box::new(GaussianIntegrator),
+    ));
+
+    let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
+
+    let systems: Vec<Box<dyn System>> = vec![sprinkler, rocket, physics, velocity_integrator, void];
+
+    let sim = Simulation::new(Particles::new_empty(), systems, None);
+
+    let sim_runner = Box::new(ConstantSimulationRunner::new(1.));
+
+    base_iridium_app(
+        width,
+        height,
+        sim,
+        sim_runner,
+        "Emitters",
+        max_fps(60),
+        get_default_input_callback(),
+        None,
+    )
+}
+
+pub fn galaxies(width: u32, height: u32) -> AppMain {
+    let mut rng = RngGenerator::new(0).next();
+
+    let sim_space = Rect::new(
+        Vector2::new(0., 0.),
+        Vector2::new(width as Scalar, height as Scalar),
+    );
+    let center = sim_space.center();
+
+    let gravity = Gravity::new(0.03, 3.);
+
+    // Two disk galaxies with central black holes on a near parabolic encounter
+    let mut first = DiskGalaxy::new(center, 1500., 25.);
+    first.central_mass = 1500.;
+    first.color = Color::CYAN;
+    let mut second = DiskGalaxy::new(center, 800., 18.);
+    second.central_mass = 800.;
+    second.clockwise = true;
+    second.color = Color::YELLOW;
+
+    let mut particles = Particles::new_empty();
+    GalaxyCollision::new(first, second, center, width as Scalar / 2., 80.).build(
+        3000,
+        2000,
+        &gravity,
+        &mut rng,
+        &mut particles,
+    );
+
+    // Quadtree wraps simulation space
+    let qt_size = std::cmp::max(width, height) as Scalar * 4.;
+    let quadtree_rect = Rect::new(
+        center - Vector2::new(qt_size / 2., qt_size / 2.),
+        Vector2::new(qt_size, qt_size),
+    );
+
+    let quadtree = Arc::new(RwLock::new(QuadTree::new(
+        quadtree_rect,
+        10,
+        gravity,
+        Repulsion::new(0., 6, 1.),
+        Drag::new(0., 0.),
+        1.,
+        Some(50),
+        false,
+    )));
+
+    let quadtree_forces = Box::new(QuadtreeForces::new(quadtree.clone()));
+
+    let physics = Box::new(Physics::new(
+        vec![quadtree_forces],
+        Box::new(GaussianIntegrator),
+    ));
+
+    let velocity_integrator = Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)));
+
+    let systems: Vec<Box<dyn System>> = vec![physics, velocity_integrator];
+
+    let sim = Simulation::new(particles, systems, None);
+
+    let sim_runner = Box::new(ConstantSimulationRunner::new(0.5));
+
+    base_iridium_app(
+        width,
+        height,
+        sim,
+        sim_runner,
+        "Galaxies",
+        max_fps(60),
+        get_default_input_callback(),
+        Some(quadtree),
+    )
+}
+
 pub fn benchmark_generator() -> AppMain {
     let mut rng_gen = RngGenerator::new(0);
     let width = 500;
@@ -497,7 +1087,9 @@ pub fn benchmark_generator() -> AppMain {
 
     let sim_reseter = Box::new(SimReset);
 
-    let sim = Simulation::new(Particles::new_empty(), vec![sim_reseter, emitter], None);
+    let systems: Vec<Box<dyn System>> = vec![sim_reseter, emitter];
+
+    let sim = Simulation::new(Particles::new_empty(), systems, None);
 
     let sim_runner = Box::new(ConstantSimulationRunner::new(1.));
 
@@ -679,3 +1271,51 @@ pub fn benchmark_gravity() -> AppMain {
         Some(quadtree),
     )
 }
+
+// How a built-in example is created, the sized ones fill a window of the given size
+#[derive(Clone, Copy)]
+pub enum ExampleMain {
+    Fixed(fn() -> AppMain),
+    Sized(fn(u32, u32) -> AppMain),
+}
+
+impl ExampleMain {
+    pub fn build(self, width: u32, height: u32) -> AppMain {
+        match self {
+            Self::Fixed(main) => main(),
+            Self::Sized(main) => main(width, height),
+        }
+    }
+}
+
+// Examples written in Rust, run by name from the command line
+pub const EXAMPLES: &[(&str, ExampleMain)] = &[
+    ("benchmark_empty", ExampleMain::Fixed(benchmark_empty)),
+    ("benchmark_base", ExampleMain::Fixed(benchmark_base)),
+    ("benchmark_forces", ExampleMain::Fixed(benchmark_forces)),
+    (
+        "benchmark_generator",
+        ExampleMain::Fixed(benchmark_generator),
+    ),
+    ("benchmark_gravity", ExampleMain::Fixed(benchmark_gravity)),
+    (
+        "test_uniform_generators",
+        ExampleMain::Fixed(test_uniform_generators),
+    ),
+    ("fireworks", ExampleMain::Sized(fireworks)),
+    ("flow", ExampleMain::Sized(flow)),
+    ("lennard_jones", ExampleMain::Sized(lennard_jones)),
+    ("periodic_gas", ExampleMain::Sized(periodic_gas)),
+    ("soft_bodies", ExampleMain::Sized(soft_bodies)),
+    ("wind", ExampleMain::Sized(wind)),
+    ("obstacles", ExampleMain::Sized(obstacles)),
+    ("emitters", ExampleMain::Sized(emitters)),
+    ("galaxies", ExampleMain::Sized(galaxies)),
+];
+
+pub fn example(name: &str) -> Option<ExampleMain> {
+    EXAMPLES
+        .iter()
+        .find(|(example, _)| *example == name)
+        .map(|(_, main)| *main)
+}
@@ -0,0 +1,256 @@
+use log::{debug, info};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    simulation::{
+        diagnostics,
+        particles::Particles,
+        sim_events::AppCommand,
+        simulation::{Simulation, SimulationRunner},
+        types::Time,
+    },
+    utils::timer::Timer,
+};
+
+// When a headless run ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopCondition {
+    Steps(usize),
+    Time(Time), // Simulated time
+}
+
+// All the particles written every interval steps to directory/snapshot_<step>.csv
+pub struct SnapshotOutput {
+    pub directory: PathBuf,
+    pub interval: usize,
+}
+
+// One csv line of global quantities every interval steps
+pub struct StatsOutput {
+    pub path: PathBuf,
+    pub interval: usize,
+}
+
+// Runs a simulation without window as fast as possible, for batch runs & parameter sweeps
+pub struct HeadlessApp {
+    pub sim: Simulation,
+    pub sim_runner: Box<dyn SimulationRunner>,
+    pub dt: Time, // Used to know the simulated time
+    pub stop: StopCondition,
+    pub snapshots: Option<SnapshotOutput>,
+    pub stats: Option<StatsOutput>,
+    pub log_interval: Duration,
+}
+
+impl HeadlessApp {
+    pub fn new(
+        sim: Simulation,
+        sim_runner: Box<dyn SimulationRunner>,
+        dt: Time,
+        stop: StopCondition,
+    ) -> Self {
+        Self {
+            sim,
+            sim_runner,
+            dt,
+            stop,
+            snapshots: None,
+            stats: None,
+            log_interval: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_snapshots(mut self, directory: PathBuf, interval: usize) -> Self {
+        assert!(interval > 0, "Snapshot interval must be positive");
+        self.snapshots = Some(SnapshotOutput {
+            directory,
+            interval,
+        });
+        self
+    }
+
+    pub fn with_stats(mut self, path: PathBuf, interval: usize) -> Self {
+        assert!(interval > 0, "Stats interval must be positive");
+        self.stats = Some(StatsOutput { path, interval });
+        self
+    }
+
+    pub fn with_log_interval(mut self, log_interval: Duration) -> Self {
+        self.log_interval = log_interval;
+        self
+    }
+
+    fn finished(&self, step: usize, time: Time) -> bool {
+        match self.stop {
+            StopCondition::Steps(steps) => step >= steps,
+            StopCondition::Time(end) => time >= end,
+        }
+    }
+
+    // The initial state is written as step 0 and the final state is always written
+    pub fn run(&mut self) -> io::Result<()> {
+        if let Some(snapshots) = &self.snapshots {
+            fs::create_dir_all(&snapshots.directory)?;
+        }
+        let mut stats_writer = match &self.stats {
+            Some(stats) => {
+                let mut writer = BufWriter::new(File::create(&stats.path)?);
+                writeln!(writer, "{}", STATS_HEADER)?;
+                Some(writer)
+            }
+            None => None,
+        };
+
+        let mut step = 0;
+        let mut time = 0.;
+        self.write_outputs(step, time, stats_writer.as_mut(), |_| true)?;
+
+        let run_timer = Timer::new_now();
+        let mut log_timer = Timer::new_now();
+        let mut log_steps = 0;
+
+        while !self.finished(step, time) {
+            let _span = tracy_client::span!("Simulation step");
+
+            self.sim_runner.step(&mut self.sim);
+            step += 1;
+            log_steps += 1;
+            time = step as Time * self.dt;
+
+            self.write_outputs(step, time, stats_writer.as_mut(), |interval| {
+                step % interval == 0
+            })?;
+
+            // Events may end the run early
+            if self.apply_commands() {
+                break;
+            }
+
+            let log_elapsed = log_timer.elapsed();
+            if log_elapsed >= self.log_interval {
+                info!(
+                    "Step {} (t = {:.3}): {:.2e} particles, {:.2} ms/step",
+                    step,
+                    time,
+                    self.sim.particles.len(),
+                    log_elapsed.as_secs_f64() * 1000. / log_steps as f64
+                );
+                log_timer.reset();
+                log_steps = 0;
+            }
+        }
+
+        // Last step, if not already written
+        self.write_outputs(step, time, stats_writer.as_mut(), |interval| {
+            step % interval != 0
+        })?;
+        if let Some(writer) = &mut stats_writer {
+            writer.flush()?;
+        }
+
+        info!(
+            "Ran {} steps (t = {:.3}) in {:.3} s, {:.2e} particles",
+            step,
+            time,
+            run_timer.elapsed().as_secs_f64(),
+            self.sim.particles.len()
+        );
+        Ok(())
+    }
+
+    // Only stop means something without window, returns true when requested
+    fn apply_commands(&mut self) -> bool {
+        let mut stopped = false;
+        for command in self.sim.take_commands() {
+            match command {
+                AppCommand::Stop => {
+                    info!("Event command: {:?}", command);
+                    stopped = true;
+                }
+                _ => debug!("Event command ignored without window: {:?}", command),
+            }
+        }
+        stopped
+    }
+
+    // Outputs whose interval is due
+    fn write_outputs(
+        &self,
+        step: usize,
+        time: Time,
+        stats_writer: Option<&mut BufWriter<File>>,
+        due: impl Fn(usize) -> bool,
+    ) -> io::Result<()> {
+        if let Some(snapshots) = &self.snapshots {
+            if due(snapshots.interval) {
+                write_snapshot(&self.sim.particles, &snapshots.directory, step)?;
+            }
+        }
+        if let (Some(stats), Some(writer)) = (&self.stats, stats_writer) {
+            if due(stats.interval) {
+                write_stats(writer, &self.sim.particles, step, time)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const STATS_HEADER: &str = "step,time,particles,mass,kinetic_energy,temperature,momentum_x,\
+momentum_y,angular_momentum,center_of_mass_x,center_of_mass_y";
+
+fn write_stats(
+    writer: &mut impl Write,
+    particles: &Particles,
+    step: usize,
+    time: Time,
+) -> io::Result<()> {
+    let momentum = diagnostics::momentum(particles);
+    let center_of_mass = diagnostics::center_of_mass(particles);
+    writeln!(
+        writer,
+        "{},{},{},{},{},{},{},{},{},{},{}",
+        step,
+        time,
+        particles.len(),
+        diagnostics::total_mass(particles),
+        diagnostics::kinetic_energy(particles),
+        diagnostics::kinetic_temperature(particles),
+        momentum.x,
+        momentum.y,
+        diagnostics::angular_momentum(particles),
+        center_of_mass.x,
+        center_of_mass.y
+    )
+}
+
+fn write_snapshot(particles: &Particles, directory: &Path, step: usize) -> io::Result<()> {
+    let _span = tracy_client::span!("Snapshot");
+
+    let path = directory.join(format!("snapshot_{:08}.csv", step));
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "x,y,vx,vy,mass,r,g,b,a")?;
+    for i in 0..particles.len() {
+        let position = particles.positions[i];
+        let velocity = particles.velocities[i];
+        let color = particles.colors[i];
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{}",
+            position.x,
+            position.y,
+            velocity.x,
+            velocity.y,
+            particles.masses[i],
+            color.r,
+            color.g,
+            color.b,
+            color.a
+        )?;
+    }
+    writer.flush()
+}
@@ -1,5 +1,8 @@
 pub mod app;
 pub mod examples;
+pub mod headless;
 pub mod rendering;
+pub mod scene;
+pub mod scripting;
 pub mod simulation;
 pub mod utils;
@@ -1,7 +1,268 @@
-use std::time::Instant;
+use std::{
+    path::{self, PathBuf},
+    process,
+    time::Instant,
+};
 
-use iridium::examples::benchmark_gravity;
-use log::info;
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use iridium::{
+    examples::{self, EXAMPLES},
+    headless::{HeadlessApp, StopCondition},
+    scene::{registry::Registry, Scene, SceneSimulation, BUILTIN_SCENES},
+    simulation::{tunables::Tunables, types::Time},
+};
+use log::{error, info};
+
+fn cli() -> Command {
+    Command::new("iridium")
+        .about("An easy to use and fast 2D particle simulator")
+        .arg(
+            Arg::new("scene")
+                .value_name("SCENE")
+                .default_value("gravity")
+                .help("Scene file (.toml or .ron) or name of a built-in scene"),
+        )
+        .arg(
+            Arg::new("example")
+                .long("example")
+                .value_name("NAME")
+                .conflicts_with_all([
+                    "scene",
+                    "dt",
+                    "steps-per-frame",
+                    "max-fps",
+                    "seed",
+                    "tunables",
+                    "list-tunables",
+                    "headless",
+                ])
+                .help("Run a built-in example written in Rust instead of a scene"),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .action(ArgAction::SetTrue)
+                .help("List the built-in scenes & examples and exit"),
+        )
+        .arg(
+            Arg::new("types")
+                .long("types")
+                .action(ArgAction::SetTrue)
+                .help("List the types usable in scenes with their parameters and exit"),
+        )
+        .arg(
+            Arg::new("width")
+                .long("width")
+                .value_name("PIXELS")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Window width, also the default bounds of the scene"),
+        )
+        .arg(
+            Arg::new("height")
+                .long("height")
+                .value_name("PIXELS")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Window height, also the default bounds of the scene"),
+        )
+        .arg(
+            Arg::new("dt")
+                .long("dt")
+                .value_name("TIME")
+                .value_parser(parse_positive)
+                .help("Time step"),
+        )
+        .arg(
+            Arg::new("steps-per-frame")
+                .long("steps-per-frame")
+                .value_name("STEPS")
+                .value_parser(value_parser!(usize))
+                .help("Simulation steps between two frames"),
+        )
+        .arg(
+            Arg::new("max-fps")
+                .long("max-fps")
+                .value_name("FPS")
+                .value_parser(value_parser!(u64))
+                .help("Frame rate limit, 0 for unlimited"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .value_parser(value_parser!(u64))
+                .help("Seed of the random generators"),
+        )
+        .arg(
+            Arg::new("tunables")
+                .long("tunables")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help(
+                    "Parameters of the systems applied between steps, again when the file changes",
+                ),
+        )
+        .arg(
+            Arg::new("list-tunables")
+                .long("list-tunables")
+                .action(ArgAction::SetTrue)
+                .help("List the parameters of the scene usable in a tunables file and exit"),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .action(ArgAction::SetTrue)
+                .requires("stop")
+                .help("Run without window until --steps or --until"),
+        )
+        .arg(
+            Arg::new("steps")
+                .long("steps")
+                .value_name("STEPS")
+                .value_parser(value_parser!(usize))
+                .requires("headless")
+                .help("Number of steps of a headless run"),
+        )
+        .arg(
+            Arg::new("until")
+                .long("until")
+                .value_name("TIME")
+                .value_parser(parse_positive)
+                .requires("headless")
+                .help("Simulated time at which a headless run stops"),
+        )
+        .group(ArgGroup::new("stop").args(["steps", "until"]))
+        .arg(
+            Arg::new("snapshots")
+                .long("snapshots")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .requires("headless")
+                .help("Write the particles to DIR/snapshot_<step>.csv"),
+        )
+        .arg(
+            Arg::new("snapshot-interval")
+                .long("snapshot-interval")
+                .value_name("STEPS")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("100")
+                .help("Steps between two snapshots"),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .requires("headless")
+                .help("Write particle count, mass, kinetic energy & momentum to a csv file"),
+        )
+        .arg(
+            Arg::new("stats-interval")
+                .long("stats-interval")
+                .value_name("STEPS")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("10")
+                .help("Steps between two lines of stats"),
+        )
+}
+
+fn parse_positive(value: &str) -> Result<Time, String> {
+    match value.parse::<Time>() {
+        Ok(value) if value > 0. => Ok(value),
+        Ok(_) => Err("must be positive".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Sized examples get the window size of the command line, 1200x800 by default
+fn run_example(name: &str, args: &ArgMatches) {
+    let Some(example) = examples::example(name) else {
+        error!("Unknown example {}, --list shows them", name);
+        process::exit(1);
+    };
+    let width = args.get_one::<u32>("width").copied().unwrap_or(1200);
+    let height = args.get_one::<u32>("height").copied().unwrap_or(800);
+    example.build(width, height).run();
+}
+
+fn load_scene(name: &str) -> Scene {
+    let scene = Scene::builtin(name).unwrap_or_else(|| Scene::from_file(name));
+    scene.unwrap_or_else(|e| {
+        error!("{}", e);
+        process::exit(1);
+    })
+}
+
+// Command line values replace the ones of the scene file
+fn apply_overrides(scene: &mut Scene, args: &ArgMatches) {
+    let description = &mut scene.description;
+    if let Some(&width) = args.get_one::<u32>("width") {
+        description.window.width = width;
+    }
+    if let Some(&height) = args.get_one::<u32>("height") {
+        description.window.height = height;
+    }
+    if let Some(&dt) = args.get_one::<Time>("dt") {
+        description.runner.dt = dt;
+    }
+    if let Some(&steps_per_frame) = args.get_one::<usize>("steps-per-frame") {
+        description.runner.steps_per_frame = steps_per_frame;
+    }
+    if let Some(&max_fps) = args.get_one::<u64>("max-fps") {
+        description.window.max_fps = Some(max_fps);
+    }
+    if let Some(&seed) = args.get_one::<u64>("seed") {
+        description.seed = seed;
+    }
+    // Relative to the working directory, not the scene
+    if let Some(path) = args.get_one::<PathBuf>("tunables") {
+        description.runner.tunables = Some(path::absolute(path).unwrap_or_else(|e| {
+            error!("{}: {}", path.display(), e);
+            process::exit(1);
+        }));
+    }
+}
+
+fn list_tunables(scene: &Scene) {
+    let mut sim = build_simulation(scene).sim;
+    let mut tunables = Tunables::new_empty();
+    tunables.systems(&mut sim.systems);
+    for (name, value) in tunables.visited() {
+        println!("{} = {}", name, value);
+    }
+}
+
+fn build_simulation(scene: &Scene) -> SceneSimulation {
+    scene.build_simulation().unwrap_or_else(|e| {
+        error!("{}", e);
+        process::exit(1);
+    })
+}
+
+fn run_headless(scene: &Scene, args: &ArgMatches) {
+    let stop = match args.get_one::<usize>("steps") {
+        Some(&steps) => StopCondition::Steps(steps),
+        None => StopCondition::Time(*args.get_one::<Time>("until").unwrap()),
+    };
+
+    let SceneSimulation {
+        sim, sim_runner, ..
+    } = build_simulation(scene);
+
+    let mut app = HeadlessApp::new(sim, sim_runner, scene.description.runner.dt, stop);
+    if let Some(directory) = args.get_one::<PathBuf>("snapshots") {
+        let interval = *args.get_one::<u64>("snapshot-interval").unwrap();
+        app = app.with_snapshots(directory.clone(), interval as usize);
+    }
+    if let Some(path) = args.get_one::<PathBuf>("stats") {
+        let interval = *args.get_one::<u64>("stats-interval").unwrap();
+        app = app.with_stats(path.clone(), interval as usize);
+    }
+
+    if let Err(e) = app.run() {
+        error!("{}", e);
+        process::exit(1);
+    }
+}
 
 fn main() {
     let t = Instant::now();
@@ -17,12 +278,52 @@ fn main() {
         .format_level(true)
         .init();
 
+    let args = cli().get_matches();
+
+    if args.get_flag("list") {
+        println!("Scenes:");
+        for (name, path, ..) in BUILTIN_SCENES {
+            println!("  {:<10} {}", name, path);
+        }
+        println!("Examples (--example NAME):");
+        for (name, _) in EXAMPLES {
+            println!("  {}", name);
+        }
+        return;
+    }
+    if args.get_flag("types") {
+        print!("{}", Registry::default());
+        return;
+    }
+
+    if let Some(name) = args.get_one::<String>("example") {
+        let _span = tracy_client::span!("Example run");
+        run_example(name, &args);
+        return;
+    }
+
+    let mut scene = load_scene(args.get_one::<String>("scene").unwrap());
+    apply_overrides(&mut scene, &args);
+
+    if args.get_flag("list-tunables") {
+        list_tunables(&scene);
+        return;
+    }
+
+    if args.get_flag("headless") {
+        let _span = tracy_client::span!("Headless run");
+        run_headless(&scene, &args);
+        return;
+    }
+
     let mut app = {
         let _span = tracy_client::span!("App startup");
 
         // Create the app
-        // gravity1(500, 500)
-        benchmark_gravity()
+        scene.build().unwrap_or_else(|e| {
+            error!("{}", e);
+            process::exit(1);
+        })
     };
 
     info!("App startup took {} ms", t.elapsed().as_millis());
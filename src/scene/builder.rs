@@ -0,0 +1,1088 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    path::Path,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
+
+use log::warn;
+use nalgebra::Vector2;
+use rayon::prelude::*;
+
+use rand_pcg::Pcg64Mcg;
+
+use super::{
+    description::{
+        ActionDesc, AreaDesc, ColorGeneratorDesc, ConditionDesc, DiskGalaxyDesc,
+        EquationOfStateDesc, EventDesc, ForceDesc, ParticlesDesc, QuadTreeDesc, RectDesc,
+        ScalarGeneratorDesc, SceneDesc, SystemDesc, VectorDesc, VectorFieldDesc,
+        VectorGeneratorDesc,
+    },
+    registry::Registry,
+};
+use crate::{
+    scripting::{Script, ScriptSystem, SharedScript},
+    simulation::{
+        areas::{
+            Annulus, Area, Complement, Difference, Disk, Ellipse, Intersection, Point, Polygon,
+            Rect, Transformed, Union,
+        },
+        colliders::Colliders,
+        color::Color,
+        diagnostics::Diagnostics,
+        distributions::{
+            ExponentialGenerator, LogNormalGenerator, MaxwellBoltzmannSpeedGenerator,
+            MaxwellBoltzmannVelocityGenerator, NormalGenerator, PowerLawGenerator,
+            WeightedChoiceGenerator,
+        },
+        emitters::{Burst, DutyCycle, Emitter},
+        fields::{CurlNoiseField, FieldAcceleration, FieldDrag, SampledField, VectorField},
+        forces::{Drag, Force, Gravity, Repulsion, UniformDrag, UniformGravity},
+        generators::{
+            AreaPointGenerator, ConstantGenerator, Generator, HSVAGenerator, HexLatticeGenerator,
+            JitteredGridGenerator, PoissonDiskGenerator, RGBAGenerator, UniformDiskPointsGenerator,
+            UniformGenerator, UniformRectPointsGenerator, Vector2Generator, Vector2PolarGenerator,
+        },
+        initial_conditions::{DiskGalaxy, GalaxyCollision, KeplerSystem, PlummerSphere},
+        integrator::GaussianIntegrator,
+        pair_potentials::{LennardJones, Morse, PairPotential, PairPotentialForce, SoftSphere},
+        particles::{GeneratorFactory, Particles},
+        periodic::PeriodicBoundary,
+        quadtree::{QuadTree, QuadtreeForces},
+        random::RngGenerator,
+        sim_events::{
+            kinetic_energy_above, kinetic_energy_below, particle_count_above, particle_count_below,
+            particle_in_area, AppCommand, Condition, DefaultSimEventsHandler, SimEvent,
+            SimEventContext, SimEventQueue,
+        },
+        sph::{EquationOfState, IdealGas, Sph, Tait},
+        system_list::{Placement, SystemHandle, SystemList},
+        systems::{
+            ColorWheel, ConstantConsumer, Loop, Physics, System, VelocityIntegrator, Void, Wall,
+        },
+        thermostats::{Berendsen, Langevin, VelocityRescaling},
+        types::{Scalar, Time, Velocity},
+    },
+};
+
+pub type BuildResult<T> = Result<T, String>;
+
+// Prefix errors with where they happened in the scene (e.g. "systems[2].forces[0]")
+fn context<T>(result: BuildResult<T>, context: impl Display) -> BuildResult<T> {
+    result.map_err(|message| format!("{}: {}", context, message))
+}
+
+#[inline]
+fn vector(v: &VectorDesc) -> Vector2<Scalar> {
+    Vector2::new(v[0], v[1])
+}
+
+// Generators & areas share the scene rng stream, in the order of the file
+pub struct Builder<'a> {
+    scene: &'a SceneDesc,
+    rng_gen: RngGenerator,
+    window: Rect,
+    directory: &'a Path, // Relative paths in the scene are resolved from here
+    registry: &'a Registry,
+    quadtree: Option<Arc<RwLock<QuadTree>>>,
+    gravity: Option<Gravity>, // Gravity constant used for the initial conditions
+    scripts: Vec<SharedScript>,
+    script_events: SimEventQueue, // Given to the scripts before the event handler is built
+}
+
+impl<'a> Builder<'a> {
+    pub fn new(scene: &'a SceneDesc, directory: &'a Path, registry: &'a Registry) -> Self {
+        let window = Rect::new(
+            Vector2::zeros(),
+            Vector2::new(scene.window.width as Scalar, scene.window.height as Scalar),
+        );
+
+        // Gravity of the quadtree, or the first direct gravity force
+        let gravity = scene
+            .quadtree
+            .as_ref()
+            .map(|quadtree| Gravity::new(quadtree.gravity.coef, quadtree.gravity.epsilon))
+            .or_else(|| {
+                scene.systems.iter().find_map(|desc| match &desc.system {
+                    SystemDesc::Physics { forces } => forces.iter().find_map(|force| match force {
+                        ForceDesc::Gravity { coef, epsilon } => Some(Gravity::new(*coef, *epsilon)),
+                        _ => None,
+                    }),
+                    _ => None,
+                })
+            });
+
+        Self {
+            scene,
+            rng_gen: RngGenerator::new(scene.seed as u128),
+            window,
+            directory,
+            registry,
+            quadtree: None,
+            gravity,
+            scripts: Vec::new(),
+            script_events: SimEventQueue::new(),
+        }
+    }
+
+    pub fn quadtree(&mut self, desc: &QuadTreeDesc) -> Arc<RwLock<QuadTree>> {
+        let rect = match &desc.rect {
+            Some(rect) => self.rect(rect),
+            None => {
+                // Square wrapping the window
+                let size = self.window.size.x.max(self.window.size.y);
+                Rect::new(
+                    self.window.center() - Vector2::new(size / 2., size / 2.),
+                    Vector2::new(size, size),
+                )
+            }
+        };
+
+        let mut quadtree = QuadTree::new(
+            rect.clone(),
+            desc.max_particles,
+            Gravity::new(desc.gravity.coef, desc.gravity.epsilon),
+            Repulsion::new(
+                desc.repulsion.coef,
+                desc.repulsion.power,
+                desc.repulsion.epsilon,
+            ),
+            Drag::new(desc.drag.coef, desc.drag.distance),
+            desc.theta,
+            desc.max_depth,
+            desc.max_depth_panics,
+        );
+        if desc.periodic {
+            quadtree = quadtree.with_periodic(PeriodicBoundary::new(rect));
+        }
+
+        let quadtree = Arc::new(RwLock::new(quadtree));
+        self.quadtree = Some(quadtree.clone());
+        quadtree
+    }
+
+    // Next stream of the scene rng, for registered types
+    pub fn rng(&mut self) -> Pcg64Mcg {
+        self.rng_gen.next()
+    }
+
+    // Scripts of the systems built so far, for their input handlers
+    pub fn scripts(&self) -> &[SharedScript] {
+        &self.scripts
+    }
+
+    pub fn window(&self) -> &Rect {
+        &self.window
+    }
+
+    pub fn rect(&self, desc: &RectDesc) -> Rect {
+        Rect::new(vector(&desc.position), vector(&desc.size))
+    }
+
+    // The window if missing
+    pub fn bounds(&self, desc: &Option<RectDesc>) -> Rect {
+        match desc {
+            Some(rect) => self.rect(rect),
+            None => self.window.clone(),
+        }
+    }
+
+    fn gravity(&self) -> BuildResult<&Gravity> {
+        self.gravity.as_ref().ok_or_else(|| {
+            "needs a gravity constant: add a [quadtree] or a gravity force".to_string()
+        })
+    }
+
+    pub fn area(&mut self, desc: &AreaDesc) -> BuildResult<Box<dyn Area>> {
+        let area: Box<dyn Area> = match desc {
+            AreaDesc::Window => Box::new(self.window.clone()),
+            AreaDesc::Rect { position, size } => {
+                Box::new(Rect::new(vector(position), vector(size)))
+            }
+            AreaDesc::Disk { position, radius } => Box::new(Disk::new(vector(position), *radius)),
+            AreaDesc::Point { position } => Box::new(Point::new(vector(position))),
+            AreaDesc::Polygon { vertices } => {
+                Box::new(Polygon::new(vertices.iter().map(vector).collect()))
+            }
+            AreaDesc::RegularPolygon {
+                position,
+                radius,
+                sides,
+                angle,
+            } => Box::new(Polygon::regular(vector(position), *radius, *sides, *angle)),
+            AreaDesc::Annulus {
+                position,
+                inner_radius,
+                outer_radius,
+            } => Box::new(Annulus::new(vector(position), *inner_radius, *outer_radius)),
+            AreaDesc::Ellipse {
+                position,
+                radii,
+                angle,
+            } => Box::new(Ellipse::new(vector(position), vector(radii), *angle)),
+            AreaDesc::Union { areas } => Box::new(Union::new(self.areas(areas)?)),
+            AreaDesc::Intersection { areas } => Box::new(Intersection::new(self.areas(areas)?)),
+            AreaDesc::Difference { area, removed } => Box::new(Difference::new(
+                context(self.area(area), "area")?,
+                context(self.area(removed), "removed")?,
+            )),
+            AreaDesc::Complement { area } => {
+                Box::new(Complement::new(context(self.area(area), "area")?))
+            }
+            AreaDesc::Transformed {
+                area,
+                scale,
+                rotation,
+                translation,
+            } => Box::new(
+                Transformed::new(context(self.area(area), "area")?)
+                    .scale(vector(scale))
+                    .rotate(*rotation)
+                    .translate(vector(translation)),
+            ),
+            AreaDesc::Registered { name, params } => {
+                let registry = self.registry;
+                context(registry.areas.build(name, self, params), name)?
+            }
+        };
+        Ok(area)
+    }
+
+    fn areas(&mut self, descs: &[AreaDesc]) -> BuildResult<Vec<Box<dyn Area>>> {
+        descs
+            .iter()
+            .enumerate()
+            .map(|(i, area)| context(self.area(area), format!("areas[{}]", i)))
+            .collect()
+    }
+
+    // Areas particles are generated in
+    pub fn bounded_area(&mut self, desc: &AreaDesc) -> BuildResult<Box<dyn Area>> {
+        let area = self.area(desc)?;
+        let bounding_box = area.bounding_box();
+        if bounding_box.size.iter().all(|size| size.is_finite()) {
+            Ok(area)
+        } else {
+            Err("can't generate points in an unbounded area".to_string())
+        }
+    }
+
+    // Bounded with a surface, for the generators spacing points out
+    fn surface_area(&mut self, desc: &AreaDesc) -> BuildResult<Box<dyn Area>> {
+        let area = self.bounded_area(desc)?;
+        if area.area() > 0. {
+            Ok(area)
+        } else {
+            Err("can't space points out in an area without surface".to_string())
+        }
+    }
+
+    pub fn scalar_generator(
+        &mut self,
+        desc: &ScalarGeneratorDesc,
+    ) -> BuildResult<Box<dyn Generator<Scalar>>> {
+        let generator: Box<dyn Generator<Scalar>> = match desc {
+            ScalarGeneratorDesc::Constant { value } => Box::new(ConstantGenerator::new(*value)),
+            ScalarGeneratorDesc::Uniform { min, max } => {
+                Box::new(UniformGenerator::new(self.rng_gen.next(), *min, *max))
+            }
+            ScalarGeneratorDesc::Normal { mean, std_dev } => {
+                Box::new(NormalGenerator::new(self.rng_gen.next(), *mean, *std_dev))
+            }
+            ScalarGeneratorDesc::LogNormal { mean, std_dev } => Box::new(
+                LogNormalGenerator::from_mean_std_dev(self.rng_gen.next(), *mean, *std_dev),
+            ),
+            ScalarGeneratorDesc::Exponential { rate } => {
+                Box::new(ExponentialGenerator::new(self.rng_gen.next(), *rate))
+            }
+            ScalarGeneratorDesc::PowerLaw { exponent, min, max } => Box::new(
+                PowerLawGenerator::new(self.rng_gen.next(), *exponent, *min, *max),
+            ),
+            ScalarGeneratorDesc::MaxwellBoltzmannSpeed { temperature, mass } => Box::new(
+                MaxwellBoltzmannSpeedGenerator::new(self.rng_gen.next(), *temperature, *mass),
+            ),
+            ScalarGeneratorDesc::Choice { values, weights } => {
+                Box::new(self.choice(values.clone(), weights))
+            }
+            ScalarGeneratorDesc::Registered { name, params } => {
+                let registry = self.registry;
+                context(registry.scalar_generators.build(name, self, params), name)?
+            }
+        };
+        Ok(generator)
+    }
+
+    pub fn vector_generator(
+        &mut self,
+        desc: &VectorGeneratorDesc,
+    ) -> BuildResult<Box<dyn Generator<Vector2<Scalar>>>> {
+        let generator: Box<dyn Generator<Vector2<Scalar>>> = match desc {
+            VectorGeneratorDesc::Constant { value } => {
+                Box::new(ConstantGenerator::new(vector(value)))
+            }
+            VectorGeneratorDesc::Cartesian { x, y } => Box::new(Vector2Generator::new(
+                context(self.scalar_generator(x), "x")?,
+                context(self.scalar_generator(y), "y")?,
+            )),
+            VectorGeneratorDesc::Polar { length, angle } => Box::new(Vector2PolarGenerator::new(
+                context(self.scalar_generator(length), "length")?,
+                context(self.scalar_generator(angle), "angle")?,
+            )),
+            VectorGeneratorDesc::Random { area } => {
+                let area = context(self.bounded_area(area), "area")?;
+                Box::new(AreaPointGenerator::new(area, self.rng_gen.next()))
+            }
+            VectorGeneratorDesc::UniformRect { position, size } => Box::new(
+                UniformRectPointsGenerator::new(Rect::new(vector(position), vector(size))),
+            ),
+            VectorGeneratorDesc::UniformDisk { position, radius } => Box::new(
+                UniformDiskPointsGenerator::new(Disk::new(vector(position), *radius)),
+            ),
+            VectorGeneratorDesc::PoissonDisk { area, min_distance } => {
+                let area = context(self.surface_area(area), "area")?;
+                let generator = PoissonDiskGenerator::new(area, self.rng_gen.next());
+                match min_distance {
+                    Some(min_distance) => Box::new(generator.with_min_distance(*min_distance)),
+                    None => Box::new(generator),
+                }
+            }
+            VectorGeneratorDesc::HexLattice { area } => Box::new(HexLatticeGenerator::new(
+                context(self.surface_area(area), "area")?,
+            )),
+            VectorGeneratorDesc::JitteredGrid { area, jitter } => {
+                let area = context(self.surface_area(area), "area")?;
+                Box::new(JitteredGridGenerator::new(
+                    area,
+                    self.rng_gen.next(),
+                    *jitter,
+                ))
+            }
+            VectorGeneratorDesc::MaxwellBoltzmann { temperature, mass } => Box::new(
+                MaxwellBoltzmannVelocityGenerator::new(self.rng_gen.next(), *temperature, *mass),
+            ),
+            VectorGeneratorDesc::Choice { values, weights } => {
+                Box::new(self.choice(values.iter().map(vector).collect(), weights))
+            }
+            VectorGeneratorDesc::Registered { name, params } => {
+                let registry = self.registry;
+                context(registry.vector_generators.build(name, self, params), name)?
+            }
+        };
+        Ok(generator)
+    }
+
+    pub fn color_generator(
+        &mut self,
+        desc: &ColorGeneratorDesc,
+    ) -> BuildResult<Box<dyn Generator<Color>>> {
+        let generator: Box<dyn Generator<Color>> = match desc {
+            ColorGeneratorDesc::Constant { value } => Box::new(ConstantGenerator::new(value.0)),
+            ColorGeneratorDesc::Rgba { r, g, b, a } => Box::new(RGBAGenerator::new(
+                context(self.scalar_generator(r), "r")?,
+                context(self.scalar_generator(g), "g")?,
+                context(self.scalar_generator(b), "b")?,
+                context(self.scalar_generator(a), "a")?,
+            )),
+            ColorGeneratorDesc::Hsva { h, s, v, a } => Box::new(HSVAGenerator::new(
+                context(self.scalar_generator(h), "h")?,
+                context(self.scalar_generator(s), "s")?,
+                context(self.scalar_generator(v), "v")?,
+                context(self.scalar_generator(a), "a")?,
+            )),
+            ColorGeneratorDesc::Choice { values, weights } => {
+                Box::new(self.choice(values.iter().map(|color| color.0).collect(), weights))
+            }
+            ColorGeneratorDesc::Registered { name, params } => {
+                let registry = self.registry;
+                context(registry.color_generators.build(name, self, params), name)?
+            }
+        };
+        Ok(generator)
+    }
+
+    // Uniform choice if there are no weights
+    fn choice<T: Clone>(
+        &mut self,
+        values: Vec<T>,
+        weights: &Option<Vec<Scalar>>,
+    ) -> WeightedChoiceGenerator<T> {
+        let weights = match weights {
+            Some(weights) => weights.clone(),
+            None => vec![1.; values.len()],
+        };
+        WeightedChoiceGenerator::new(
+            self.rng_gen.next(),
+            values.into_iter().zip(weights).collect(),
+        )
+    }
+
+    pub fn factory(
+        &mut self,
+        position: &VectorGeneratorDesc,
+        velocity: &VectorGeneratorDesc,
+        mass: &ScalarGeneratorDesc,
+        color: &ColorGeneratorDesc,
+    ) -> BuildResult<GeneratorFactory> {
+        Ok(GeneratorFactory::new(
+            context(self.vector_generator(position), "position")?,
+            context(self.vector_generator(velocity), "velocity")?,
+            context(self.scalar_generator(mass), "mass")?,
+            context(self.color_generator(color), "color")?,
+        ))
+    }
+
+    fn disk_galaxy(&self, desc: &DiskGalaxyDesc) -> DiskGalaxy {
+        let mut galaxy = DiskGalaxy::new(vector(&desc.center), desc.mass, desc.scale_length);
+        galaxy.velocity = vector(&desc.velocity);
+        if let Some(max_radius) = desc.max_radius {
+            galaxy.max_radius = max_radius;
+        }
+        galaxy.central_mass = desc.central_mass;
+        if let Some(dispersion) = desc.dispersion {
+            galaxy.dispersion = dispersion;
+        }
+        galaxy.clockwise = desc.clockwise;
+        galaxy.color = desc.color.0;
+        galaxy
+    }
+
+    pub fn particles(
+        &mut self,
+        desc: &ParticlesDesc,
+        particles: &mut Particles,
+    ) -> BuildResult<()> {
+        match desc {
+            ParticlesDesc::Generators {
+                count,
+                position,
+                velocity,
+                mass,
+                color,
+            } => context(
+                self.factory(position, velocity, mass, color)?
+                    .try_create(*count, particles),
+                "position",
+            )?,
+            ParticlesDesc::Particle {
+                position,
+                velocity,
+                mass,
+                color,
+            } => particles.push(vector(position), vector(velocity), *mass, color.0),
+            ParticlesDesc::DiskGalaxy(galaxy) => {
+                let mut rng = self.rng_gen.next();
+                self.disk_galaxy(galaxy)
+                    .build(galaxy.count, self.gravity()?, &mut rng, particles);
+            }
+            ParticlesDesc::PlummerSphere {
+                count,
+                center,
+                velocity,
+                mass,
+                scale_radius,
+                max_radius,
+                color,
+            } => {
+                let mut sphere = PlummerSphere::new(vector(center), *mass, *scale_radius);
+                sphere.velocity = vector(velocity);
+                if let Some(max_radius) = max_radius {
+                    sphere.max_radius = *max_radius;
+                }
+                sphere.color = color.0;
+
+                let mut rng = self.rng_gen.next();
+                sphere.build(*count, self.gravity()?, &mut rng, particles);
+            }
+            ParticlesDesc::KeplerSystem {
+                center,
+                velocity,
+                star_mass,
+                star_color,
+                planets,
+                clockwise,
+            } => {
+                let mut system = KeplerSystem::new(vector(center), *star_mass, star_color.0);
+                system.velocity = vector(velocity);
+                system.clockwise = *clockwise;
+                for planet in planets {
+                    system.add_planet(
+                        planet.semi_major_axis,
+                        planet.eccentricity,
+                        planet.angle,
+                        planet.mass,
+                        planet.color.0,
+                    );
+                }
+                system.build(self.gravity()?, particles);
+            }
+            ParticlesDesc::GalaxyCollision {
+                first,
+                second,
+                center,
+                separation,
+                impact_parameter,
+                speed_factor,
+            } => {
+                let mut collision = GalaxyCollision::new(
+                    self.disk_galaxy(first),
+                    self.disk_galaxy(second),
+                    vector(center),
+                    *separation,
+                    *impact_parameter,
+                );
+                collision.speed_factor = *speed_factor;
+
+                let mut rng = self.rng_gen.next();
+                collision.build(
+                    first.count,
+                    second.count,
+                    self.gravity()?,
+                    &mut rng,
+                    particles,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn vector_field(&mut self, desc: &VectorFieldDesc) -> BuildResult<Box<dyn VectorField>> {
+        match desc {
+            VectorFieldDesc::CurlNoise {
+                scale,
+                strength,
+                speed,
+            } => Ok(Box::new(CurlNoiseField::new(
+                self.rng_gen.next(),
+                *scale,
+                *strength,
+                *speed,
+            ))),
+            VectorFieldDesc::Sampled { file, bounds } => {
+                let path = self.directory.join(file);
+                SampledField::from_file(&path, self.bounds(bounds))
+                    .map(|field| Box::new(field) as Box<dyn VectorField>)
+                    .map_err(|e| format!("{}: {}", path.display(), e))
+            }
+        }
+    }
+
+    fn pair_potential<P: PairPotential + 'static>(
+        &self,
+        potential: P,
+        bounds: &Option<RectDesc>,
+        periodic: bool,
+    ) -> Box<dyn Force> {
+        let rect = self.bounds(bounds);
+        let force = PairPotentialForce::new(potential, rect.clone());
+        if periodic {
+            Box::new(force.with_periodic(PeriodicBoundary::new(rect)))
+        } else {
+            Box::new(force)
+        }
+    }
+
+    pub fn force(&mut self, desc: &ForceDesc) -> BuildResult<Box<dyn Force>> {
+        let force: Box<dyn Force> = match desc {
+            ForceDesc::UniformGravity { acceleration } => {
+                Box::new(UniformGravity::new(vector(acceleration)))
+            }
+            ForceDesc::UniformDrag { coef, velocity } => {
+                Box::new(UniformDrag::new(*coef, vector(velocity)))
+            }
+            ForceDesc::Gravity { coef, epsilon } => Box::new(Gravity::new(*coef, *epsilon)),
+            ForceDesc::Repulsion {
+                coef,
+                power,
+                epsilon,
+            } => Box::new(Repulsion::new(*coef, *power, *epsilon)),
+            ForceDesc::Drag { coef, distance } => Box::new(Drag::new(*coef, *distance)),
+            ForceDesc::Quadtree => match &self.quadtree {
+                Some(quadtree) => Box::new(QuadtreeForces::new(quadtree.clone())),
+                None => return Err("quadtree forces need a [quadtree] section".to_string()),
+            },
+            ForceDesc::LennardJones {
+                epsilon,
+                sigma,
+                cutoff,
+                bounds,
+                periodic,
+            } => self.pair_potential(
+                LennardJones::new(*epsilon, *sigma, cutoff.unwrap_or(2.5 * sigma)),
+                bounds,
+                *periodic,
+            ),
+            ForceDesc::Morse {
+                depth,
+                width,
+                equilibrium,
+                cutoff,
+                bounds,
+                periodic,
+            } => self.pair_potential(
+                Morse::new(*depth, *width, *equilibrium, *cutoff),
+                bounds,
+                *periodic,
+            ),
+            ForceDesc::SoftSphere {
+                epsilon,
+                sigma,
+                power,
+                cutoff,
+                bounds,
+                periodic,
+            } => self.pair_potential(
+                SoftSphere::new(*epsilon, *sigma, *power, *cutoff),
+                bounds,
+                *periodic,
+            ),
+            ForceDesc::FieldAcceleration { field } => Box::new(FieldAcceleration::new(context(
+                self.vector_field(field),
+                "field",
+            )?)),
+            ForceDesc::FieldDrag { field, coef } => Box::new(FieldDrag::new(
+                context(self.vector_field(field), "field")?,
+                *coef,
+            )),
+            ForceDesc::Registered { name, params } => {
+                let registry = self.registry;
+                context(registry.forces.build(name, self, params), name)?
+            }
+        };
+        Ok(force)
+    }
+
+    pub fn system(&mut self, desc: &SystemDesc) -> BuildResult<Box<dyn System>> {
+        let system: Box<dyn System> = match desc {
+            SystemDesc::Wall {
+                bounds,
+                restitution,
+            } => {
+                let rect = self.bounds(bounds);
+                Box::new(Wall {
+                    x_min: rect.position.x,
+                    y_min: rect.position.y,
+                    x_max: rect.position.x + rect.size.x,
+                    y_max: rect.position.y + rect.size.y,
+                    restitution: *restitution,
+                })
+            }
+            SystemDesc::Loop { bounds } => {
+                let rect = self.bounds(bounds);
+                Box::new(Loop {
+                    x_min: rect.position.x,
+                    y_min: rect.position.y,
+                    x_max: rect.position.x + rect.size.x,
+                    y_max: rect.position.y + rect.size.y,
+                })
+            }
+            SystemDesc::Void { area } => Box::new(Void {
+                area: context(self.area(area), "area")?,
+            }),
+            SystemDesc::Consumer { area, rate } => Box::new(ConstantConsumer::new(
+                context(self.area(area), "area")?,
+                *rate,
+            )),
+            SystemDesc::Emitter {
+                rate,
+                position,
+                velocity,
+                mass,
+                color,
+                origin,
+                angle,
+                duty_cycle,
+                bursts,
+            } => {
+                let factory = self.factory(position, velocity, mass, color)?;
+                let mut emitter = Emitter::new(Box::new(factory), *rate).with_bursts(
+                    bursts
+                        .iter()
+                        .map(|burst| Burst::new(burst.time, burst.count))
+                        .collect(),
+                );
+                if let Some(cycle) = duty_cycle {
+                    emitter = emitter.with_duty_cycle(DutyCycle::new(
+                        cycle.period,
+                        cycle.on_time,
+                        cycle.offset,
+                    ));
+                }
+                if let Some(origin) = origin {
+                    emitter = emitter.with_position(vector(origin));
+                }
+                if let Some(angle) = *angle {
+                    emitter = emitter.with_rotation(Box::new(move |_| angle));
+                }
+                Box::new(emitter)
+            }
+            SystemDesc::Physics { forces } => {
+                let forces = forces
+                    .iter()
+                    .enumerate()
+                    .map(|(i, force)| context(self.force(force), format!("forces[{}]", i)))
+                    .collect::<BuildResult<Vec<_>>>()?;
+                Box::new(Physics::new(forces, Box::new(GaussianIntegrator)))
+            }
+            SystemDesc::VelocityIntegrator => {
+                Box::new(VelocityIntegrator::new(Box::new(GaussianIntegrator)))
+            }
+            SystemDesc::ColorWheel { speed } => Box::new(ColorWheel { speed: *speed }),
+            SystemDesc::Sph {
+                smoothing_length,
+                viscosity,
+                equation_of_state,
+                bounds,
+                periodic,
+            } => {
+                let equation_of_state: Box<dyn EquationOfState> = match equation_of_state {
+                    EquationOfStateDesc::IdealGas {
+                        stiffness,
+                        rest_density,
+                    } => Box::new(IdealGas::new(*stiffness, *rest_density)),
+                    EquationOfStateDesc::Tait {
+                        rest_density,
+                        speed_of_sound,
+                        gamma,
+                    } => Box::new(Tait::new(*rest_density, *speed_of_sound, *gamma)),
+                };
+                let rect = self.bounds(bounds);
+                let sph = Sph::new_default(
+                    rect.clone(),
+                    *smoothing_length,
+                    *viscosity,
+                    equation_of_state,
+                );
+                if *periodic {
+                    Box::new(sph.with_periodic(PeriodicBoundary::new(rect)))
+                } else {
+                    Box::new(sph)
+                }
+            }
+            SystemDesc::Colliders {
+                restitution,
+                friction,
+                max_bounces,
+                cell_size,
+                segments,
+                polylines,
+                polygons,
+                circles,
+                containers,
+            } => {
+                let mut colliders =
+                    Colliders::new(*restitution, *friction, *max_bounces, *cell_size);
+                for [start, end] in segments {
+                    colliders.add_segment(vector(start), vector(end));
+                }
+                for polyline in polylines {
+                    colliders.add_polyline(&polyline.iter().map(vector).collect::<Vec<_>>());
+                }
+                for polygon in polygons {
+                    colliders.add_polygon(&polygon.iter().map(vector).collect::<Vec<_>>());
+                }
+                for circle in circles {
+                    colliders.add_circle(vector(&circle.position), circle.radius);
+                }
+                for container in containers {
+                    colliders.add_circle_container(vector(&container.position), container.radius);
+                }
+                Box::new(colliders)
+            }
+            SystemDesc::VelocityRescaling { temperature } => {
+                Box::new(VelocityRescaling::new(*temperature))
+            }
+            SystemDesc::Berendsen { temperature, tau } => {
+                Box::new(Berendsen::new(*temperature, *tau))
+            }
+            SystemDesc::Langevin {
+                temperature,
+                gamma,
+                velocity,
+            } => Box::new(
+                Langevin::new(self.rng_gen.next(), *temperature, *gamma)
+                    .with_velocity(vector(velocity)),
+            ),
+            SystemDesc::Diagnostics {
+                potentials,
+                drift_threshold,
+                file,
+                log,
+            } => {
+                let potentials = match potentials {
+                    Some(descs) => descs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, desc)| context(self.force(desc), format!("potentials[{}]", i)))
+                        .collect::<BuildResult<Vec<_>>>()?,
+                    None => self.scene_potentials()?,
+                };
+
+                let mut diagnostics = Diagnostics::new(potentials);
+                if let Some(threshold) = drift_threshold {
+                    diagnostics = diagnostics.with_drift_threshold(*threshold);
+                }
+                if *log {
+                    diagnostics = diagnostics.with_log();
+                }
+                if let Some(file) = file {
+                    diagnostics = diagnostics
+                        .with_file(self.directory.join(file))
+                        .map_err(|e| format!("{}: {}", file.display(), e))?;
+                }
+                Box::new(diagnostics)
+            }
+            SystemDesc::Script { file } => {
+                let script = Script::from_file(self.directory.join(file), self.rng_gen.next())
+                    .map_err(|e| e.to_string())?
+                    .with_events(self.script_events.clone())
+                    .shared();
+                self.scripts.push(script.clone());
+                Box::new(ScriptSystem::new(script))
+            }
+            SystemDesc::Registered { name, params } => {
+                let registry = self.registry;
+                context(registry.systems.build(name, self, params), name)?
+            }
+        };
+        Ok(system)
+    }
+
+    // Forces of the physics systems deriving from a potential, built again for the diagnostics
+    fn scene_potentials(&mut self) -> BuildResult<Vec<Box<dyn Force>>> {
+        let scene = self.scene;
+        let mut potentials = Vec::new();
+        for desc in scene.systems.iter() {
+            let SystemDesc::Physics { forces } = &desc.system else {
+                continue;
+            };
+            for force in forces.iter() {
+                if matches!(
+                    force,
+                    ForceDesc::UniformGravity { .. }
+                        | ForceDesc::Gravity { .. }
+                        | ForceDesc::Repulsion { .. }
+                        | ForceDesc::Quadtree
+                        | ForceDesc::LennardJones { .. }
+                        | ForceDesc::Morse { .. }
+                        | ForceDesc::SoftSphere { .. }
+                ) {
+                    potentials.push(self.force(force)?);
+                }
+            }
+        }
+        Ok(potentials)
+    }
+
+    pub fn event(&mut self, desc: &EventDesc) -> BuildResult<SimEvent> {
+        let actions = self.actions(&desc.actions)?;
+
+        let callback = Box::new(move |context: &mut SimEventContext| {
+            for action in actions.iter() {
+                action.apply(context);
+            }
+        });
+
+        let mut event = match (desc.every, &desc.when) {
+            (Some(period), _) => SimEvent::every(desc.time, period, callback),
+            (None, Some(condition)) => {
+                let mut condition = context(self.condition(condition), "when")?;
+                let start = desc.time;
+                SimEvent::when(
+                    Box::new(move |particles, time| time >= start && condition(particles, time)),
+                    callback,
+                )
+            }
+            (None, None) => SimEvent::new(desc.time, callback),
+        };
+        if let Some(count) = desc.count {
+            event = event.with_count(count);
+        }
+        Ok(event)
+    }
+
+    fn condition(&mut self, desc: &ConditionDesc) -> BuildResult<Condition> {
+        let condition = match desc {
+            ConditionDesc::ParticlesAbove { count } => particle_count_above(*count),
+            ConditionDesc::ParticlesBelow { count } => particle_count_below(*count),
+            ConditionDesc::KineticEnergyAbove { energy } => kinetic_energy_above(*energy),
+            ConditionDesc::KineticEnergyBelow { energy } => kinetic_energy_below(*energy),
+            ConditionDesc::ParticleIn { area } => {
+                particle_in_area(context(self.area(area), "area")?)
+            }
+        };
+        Ok(condition)
+    }
+
+    fn actions(&mut self, descs: &[ActionDesc]) -> BuildResult<Vec<Action>> {
+        descs
+            .iter()
+            .enumerate()
+            .map(|(i, action)| context(self.action(action), format!("actions[{}]", i)))
+            .collect()
+    }
+
+    fn action(&mut self, desc: &ActionDesc) -> BuildResult<Action> {
+        let action = match desc {
+            ActionDesc::ScaleVelocities { factor } => Action::ScaleVelocities(*factor),
+            ActionDesc::AddVelocity { velocity } => Action::AddVelocity(vector(velocity)),
+            ActionDesc::ClearParticles => Action::ClearParticles,
+            ActionDesc::AddParticles { particles: desc } => {
+                let mut particles = Particles::new_empty();
+                context(self.particles(desc, &mut particles), "particles")?;
+                Action::AddParticles(particles)
+            }
+            ActionDesc::RemoveSystem { id } => Action::RemoveSystem(id.clone()),
+            ActionDesc::EnableSystem { id } => Action::SetSystemEnabled(id.clone(), true),
+            ActionDesc::DisableSystem { id } => Action::SetSystemEnabled(id.clone(), false),
+            ActionDesc::ReplaceSystem { id, system } => Action::ReplaceSystem(
+                id.clone(),
+                RefCell::new(Some(context(self.system(system), "system")?)),
+            ),
+            ActionDesc::InsertSystem {
+                id,
+                before,
+                after,
+                system,
+            } => {
+                let anchor = match (before, after) {
+                    (Some(before), _) => Anchor::Before(before.clone()),
+                    (None, Some(after)) => Anchor::After(after.clone()),
+                    (None, None) => Anchor::Last,
+                };
+                Action::InsertSystem(
+                    id.clone(),
+                    anchor,
+                    RefCell::new(Some(context(self.system(system), "system")?)),
+                )
+            }
+            ActionDesc::Pause => Action::Command(AppCommand::Pause),
+            ActionDesc::Resume => Action::Command(AppCommand::Resume),
+            ActionDesc::Stop => Action::Command(AppCommand::Stop),
+            ActionDesc::SetStepsPerFrame { steps } => {
+                Action::Command(AppCommand::SetStepsPerFrame(*steps))
+            }
+            ActionDesc::Schedule { delay, actions } => {
+                Action::Schedule(*delay, Rc::new(self.actions(actions)?))
+            }
+        };
+        Ok(action)
+    }
+
+    pub fn events(&mut self, descs: &[EventDesc]) -> BuildResult<DefaultSimEventsHandler> {
+        let events = descs
+            .iter()
+            .enumerate()
+            .map(|(i, desc)| context(self.event(desc), format!("events[{}]", i)))
+            .collect::<BuildResult<Vec<_>>>()?;
+        Ok(DefaultSimEventsHandler::new(events, 0.).with_queue(self.script_events.clone()))
+    }
+}
+
+// Event actions, built when the scene is loaded
+enum Action {
+    ScaleVelocities(Scalar),
+    AddVelocity(Velocity),
+    ClearParticles,
+    AddParticles(Particles), // Copied each time
+    RemoveSystem(String),
+    SetSystemEnabled(String, bool),
+    // Systems are taken when used
+    ReplaceSystem(String, RefCell<Option<Box<dyn System>>>),
+    InsertSystem(Option<String>, Anchor, RefCell<Option<Box<dyn System>>>),
+    Command(AppCommand),
+    Schedule(Time, Rc<Vec<Action>>),
+}
+
+enum Anchor {
+    Last,
+    Before(String),
+    After(String),
+}
+
+// Handle of a system by id, warns when missing
+fn system_handle(systems: &SystemList, id: &str) -> Option<SystemHandle> {
+    let handle = systems.handle(id);
+    if handle.is_none() {
+        warn!("Scene event: no system with id {}", id);
+    }
+    handle
+}
+
+// The system of an insert or replace action, warns when already used
+fn take_system(system: &RefCell<Option<Box<dyn System>>>) -> Option<Box<dyn System>> {
+    let system = system.borrow_mut().take();
+    if system.is_none() {
+        warn!("Scene event: system already used");
+    }
+    system
+}
+
+impl Action {
+    fn apply(&self, context: &mut SimEventContext) {
+        let particles = &mut *context.particles;
+        let systems = &mut *context.systems;
+        match self {
+            Action::ScaleVelocities(factor) => particles
+                .velocities
+                .par_iter_mut()
+                .for_each(|velocity| *velocity *= *factor),
+            Action::AddVelocity(offset) => particles
+                .velocities
+                .par_iter_mut()
+                .for_each(|velocity| *velocity += *offset),
+            Action::ClearParticles => particles.clear(),
+            Action::AddParticles(added) => particles.append(&mut added.clone()),
+            Action::RemoveSystem(id) => {
+                if let Some(handle) = system_handle(systems, id) {
+                    systems.remove(handle);
+                }
+            }
+            Action::SetSystemEnabled(id, enabled) => {
+                if let Some(handle) = system_handle(systems, id) {
+                    systems.set_enabled(handle, *enabled);
+                }
+            }
+            Action::ReplaceSystem(id, system) => {
+                let Some(handle) = system_handle(systems, id) else {
+                    return;
+                };
+                if let Some(system) = take_system(system) {
+                    systems.replace(handle, system);
+                }
+            }
+            Action::InsertSystem(id, anchor, system) => {
+                if let Some(id) = id.as_deref().filter(|id| systems.handle(id).is_some()) {
+                    warn!("Scene event: system id {} already used", id);
+                    return;
+                }
+                let placement = match anchor {
+                    Anchor::Last => Placement::Last,
+                    Anchor::Before(before) => match system_handle(systems, before) {
+                        Some(handle) => Placement::Before(handle),
+                        None => return,
+                    },
+                    Anchor::After(after) => match system_handle(systems, after) {
+                        Some(handle) => Placement::After(handle),
+                        None => return,
+                    },
+                };
+                if let Some(system) = take_system(system) {
+                    systems.insert(placement, id.as_deref(), system);
+                }
+            }
+            Action::Command(command) => context.command(*command),
+            Action::Schedule(delay, actions) => {
+                let actions = actions.clone();
+                context.schedule(SimEvent::new(
+                    context.time + delay,
+                    Box::new(move |context| {
+                        for action in actions.iter() {
+                            action.apply(context);
+                        }
+                    }),
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,1363 @@
+use std::{fmt, marker::PhantomData, path::PathBuf};
+
+use serde::{
+    de::{
+        self,
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    Deserialize, Deserializer,
+};
+
+use super::registry::Params;
+use crate::simulation::{
+    color::Color,
+    types::{Angle, Energy, Length, Mass, Scalar, Temperature, Time},
+};
+
+// [x, y] in TOML, (x, y) in RON
+pub type VectorDesc = [Scalar; 2];
+
+// Checks done while parsing, so errors point at the faulty value in TOML files
+pub trait Validate {
+    fn validate(&self) -> Result<(), String>;
+}
+
+fn check(condition: bool, message: &str) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(message.to_string())
+    }
+}
+
+// Deserialize through the remote = "Self" impl generated by serde, then validate
+macro_rules! validated {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl<'de> Deserialize<'de> for $type {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let value = Self::deserialize(deserializer)?;
+                    value.validate().map_err(de::Error::custom)?;
+                    Ok(value)
+                }
+            }
+        )*
+    };
+}
+
+// Generators can be written as a plain constant value or as a table with a type
+trait Shorthand: Sized + Validate {
+    type Value: DeserializeOwned;
+    const EXPECTING: &'static str;
+
+    fn constant(value: Self::Value) -> Self;
+    fn tagged<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+struct ShorthandVisitor<T>(PhantomData<T>);
+
+impl<T: Shorthand> ShorthandVisitor<T> {
+    fn constant<'de, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        T::Value::deserialize(deserializer).map(T::constant)
+    }
+}
+
+impl<'de, T: Shorthand> Visitor<'de> for ShorthandVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(T::EXPECTING)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        Self::constant(v.into_deserializer())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        Self::constant(v.into_deserializer())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<T, E> {
+        Self::constant(v.into_deserializer())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        Self::constant(v.into_deserializer())
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<T, A::Error> {
+        Self::constant(SeqAccessDeserializer::new(seq))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        let value = T::tagged(MapAccessDeserializer::new(map))?;
+        value.validate().map_err(de::Error::custom)?;
+        Ok(value)
+    }
+}
+
+macro_rules! shorthand {
+    ($type:ty, $value:ty, $expecting:literal) => {
+        impl Shorthand for $type {
+            type Value = $value;
+            const EXPECTING: &'static str = $expecting;
+
+            fn constant(value: $value) -> Self {
+                Self::Constant { value }
+            }
+
+            fn tagged<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Self::deserialize(deserializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(ShorthandVisitor::<Self>(PhantomData))
+            }
+        }
+    };
+}
+
+// Color name ("cyan"), hex string ("#ff8000" or "#ff800080") or [r, g, b(, a)] in [0, 1]
+#[derive(Debug, Clone, Copy)]
+pub struct ColorDesc(pub Color);
+
+impl ColorDesc {
+    fn from_name(name: &str) -> Option<Color> {
+        let color = match name {
+            "black" => Color::BLACK,
+            "white" => Color::WHITE,
+            "red" => Color::RED,
+            "green" => Color::GREEN,
+            "blue" => Color::BLUE,
+            "yellow" => Color::YELLOW,
+            "cyan" => Color::CYAN,
+            "magenta" => Color::MAGENTA,
+            _ => {
+                let hex = name.strip_prefix('#')?;
+                let value = u32::from_str_radix(hex, 16).ok()?;
+                match hex.len() {
+                    6 => Color::from_hex((value << 8) | 0xff),
+                    8 => Color::from_hex(value),
+                    _ => return None,
+                }
+            }
+        };
+        Some(color)
+    }
+}
+
+impl Default for ColorDesc {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = ColorDesc;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a color name, a hex string or [r, g, b(, a)]")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<ColorDesc, E> {
+        ColorDesc::from_name(v)
+            .map(ColorDesc)
+            .ok_or_else(|| E::custom(format!("unknown color `{}`", v)))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<ColorDesc, A::Error> {
+        let components = Vec::<Scalar>::deserialize(SeqAccessDeserializer::new(seq))?;
+        match components[..] {
+            [r, g, b] => Ok(ColorDesc(Color::from_rgba(r, g, b, 1.))),
+            [r, g, b, a] => Ok(ColorDesc(Color::from_rgba(r, g, b, a))),
+            _ => Err(de::Error::custom("expected 3 or 4 color components")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorDesc {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+fn default_one() -> Scalar {
+    1.
+}
+
+fn default_zero_vector() -> VectorDesc {
+    [0., 0.]
+}
+
+fn default_velocity() -> VectorGeneratorDesc {
+    VectorGeneratorDesc::Constant {
+        value: default_zero_vector(),
+    }
+}
+
+fn default_mass() -> ScalarGeneratorDesc {
+    ScalarGeneratorDesc::Constant { value: 1. }
+}
+
+fn default_color() -> ColorGeneratorDesc {
+    ColorGeneratorDesc::Constant {
+        value: ColorDesc::default(),
+    }
+}
+
+fn check_weights(values: usize, weights: &Option<Vec<Scalar>>) -> Result<(), String> {
+    check(values > 0, "choice needs at least one value")?;
+    if let Some(weights) = weights {
+        check(weights.len() == values, "expected one weight per value")?;
+        check(weights.iter().all(|w| *w >= 0.), "weights must be positive")?;
+        check(
+            weights.iter().any(|w| *w > 0.),
+            "choice needs at least one strictly positive weight",
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum ScalarGeneratorDesc {
+    Constant {
+        value: Scalar,
+    },
+    Uniform {
+        min: Scalar,
+        max: Scalar,
+    },
+    Normal {
+        mean: Scalar,
+        std_dev: Scalar,
+    },
+    LogNormal {
+        mean: Scalar,
+        std_dev: Scalar,
+    },
+    Exponential {
+        rate: Scalar,
+    },
+    PowerLaw {
+        exponent: Scalar,
+        min: Scalar,
+        max: Scalar,
+    },
+    MaxwellBoltzmannSpeed {
+        temperature: Temperature,
+        mass: Mass,
+    },
+    Choice {
+        values: Vec<Scalar>,
+        weights: Option<Vec<Scalar>>, // Uniform if missing
+    },
+    // Type added to the registry by name, built-in types can also be written this way
+    Registered {
+        name: String,
+        #[serde(default)]
+        params: Box<Params>,
+    },
+}
+
+shorthand!(ScalarGeneratorDesc, Scalar, "a number or a generator table");
+
+impl Validate for ScalarGeneratorDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Uniform { min, max } => check(min < max, "min must be less than max"),
+            Self::Normal { std_dev, .. } => check(*std_dev >= 0., "std_dev must be positive"),
+            Self::LogNormal { mean, std_dev } => {
+                check(*mean > 0., "mean must be strictly positive")?;
+                check(*std_dev >= 0., "std_dev must be positive")
+            }
+            Self::Exponential { rate } => check(*rate > 0., "rate must be strictly positive"),
+            Self::PowerLaw { min, max, .. } => {
+                check(*min > 0. && min < max, "power law needs 0 < min < max")
+            }
+            Self::MaxwellBoltzmannSpeed { temperature, mass } => check(
+                *temperature >= 0. && *mass > 0.,
+                "Maxwell-Boltzmann needs a positive temperature and mass",
+            ),
+            Self::Choice { values, weights } => check_weights(values.len(), weights),
+            Self::Constant { .. } | Self::Registered { .. } => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum VectorGeneratorDesc {
+    Constant {
+        value: VectorDesc,
+    },
+    Cartesian {
+        x: Box<ScalarGeneratorDesc>,
+        y: Box<ScalarGeneratorDesc>,
+    },
+    Polar {
+        length: Box<ScalarGeneratorDesc>,
+        angle: Box<ScalarGeneratorDesc>,
+    },
+    // Random points, uniformly distributed in the area
+    Random {
+        area: AreaDesc,
+    },
+    // Evenly spaced points
+    UniformRect {
+        position: VectorDesc,
+        size: VectorDesc,
+    },
+    UniformDisk {
+        position: VectorDesc,
+        radius: Length,
+    },
+    PoissonDisk {
+        area: AreaDesc,
+        min_distance: Option<Length>, // Fits the particle count if missing
+    },
+    HexLattice {
+        area: AreaDesc,
+    },
+    JitteredGrid {
+        area: AreaDesc,
+        jitter: Scalar,
+    },
+    MaxwellBoltzmann {
+        temperature: Temperature,
+        mass: Mass,
+    },
+    Choice {
+        values: Vec<VectorDesc>,
+        weights: Option<Vec<Scalar>>,
+    },
+    // Type added to the registry by name, built-in types can also be written this way
+    Registered {
+        name: String,
+        #[serde(default)]
+        params: Box<Params>,
+    },
+}
+
+shorthand!(
+    VectorGeneratorDesc,
+    VectorDesc,
+    "a vector or a generator table"
+);
+
+impl Validate for VectorGeneratorDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::UniformRect { size, .. } => {
+                check(size[0] >= 0. && size[1] >= 0., "size must be positive")
+            }
+            Self::UniformDisk { radius, .. } => check(*radius >= 0., "radius must be positive"),
+            Self::PoissonDisk {
+                min_distance: Some(min_distance),
+                ..
+            } => check(*min_distance > 0., "min_distance must be strictly positive"),
+            Self::JitteredGrid { jitter, .. } => {
+                check((0. ..=1.).contains(jitter), "jitter must be in [0, 1]")
+            }
+            Self::MaxwellBoltzmann { temperature, mass } => check(
+                *temperature >= 0. && *mass > 0.,
+                "Maxwell-Boltzmann needs a positive temperature and mass",
+            ),
+            Self::Choice { values, weights } => check_weights(values.len(), weights),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum ColorGeneratorDesc {
+    Constant {
+        value: ColorDesc,
+    },
+    // Components in [0, 1]
+    Rgba {
+        r: Box<ScalarGeneratorDesc>,
+        g: Box<ScalarGeneratorDesc>,
+        b: Box<ScalarGeneratorDesc>,
+        #[serde(default = "default_unit")]
+        a: Box<ScalarGeneratorDesc>,
+    },
+    // Hue in degrees, other components in [0, 1]
+    Hsva {
+        h: Box<ScalarGeneratorDesc>,
+        #[serde(default = "default_unit")]
+        s: Box<ScalarGeneratorDesc>,
+        #[serde(default = "default_unit")]
+        v: Box<ScalarGeneratorDesc>,
+        #[serde(default = "default_unit")]
+        a: Box<ScalarGeneratorDesc>,
+    },
+    Choice {
+        values: Vec<ColorDesc>,
+        weights: Option<Vec<Scalar>>,
+    },
+    // Type added to the registry by name, built-in types can also be written this way
+    Registered {
+        name: String,
+        #[serde(default)]
+        params: Box<Params>,
+    },
+}
+
+fn default_unit() -> Box<ScalarGeneratorDesc> {
+    Box::new(ScalarGeneratorDesc::Constant { value: 1. })
+}
+
+shorthand!(
+    ColorGeneratorDesc,
+    ColorDesc,
+    "a color or a generator table"
+);
+
+impl Validate for ColorGeneratorDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Choice { values, weights } => check_weights(values.len(), weights),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RectDesc {
+    pub position: VectorDesc,
+    pub size: VectorDesc,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum AreaDesc {
+    // The window rect
+    Window,
+    Rect {
+        position: VectorDesc,
+        size: VectorDesc,
+    },
+    Disk {
+        position: VectorDesc,
+        radius: Length,
+    },
+    Point {
+        position: VectorDesc,
+    },
+    Polygon {
+        vertices: Vec<VectorDesc>,
+    },
+    RegularPolygon {
+        position: VectorDesc,
+        radius: Length,
+        sides: usize,
+        #[serde(default)]
+        angle: Angle,
+    },
+    Annulus {
+        position: VectorDesc,
+        inner_radius: Length,
+        outer_radius: Length,
+    },
+    Ellipse {
+        position: VectorDesc,
+        radii: VectorDesc,
+        #[serde(default)]
+        angle: Angle,
+    },
+    Union {
+        areas: Vec<AreaDesc>,
+    },
+    Intersection {
+        areas: Vec<AreaDesc>,
+    },
+    Difference {
+        area: Box<AreaDesc>,
+        removed: Box<AreaDesc>,
+    },
+    Complement {
+        area: Box<AreaDesc>,
+    },
+    // Scaled, then rotated (around the origin), then translated
+    Transformed {
+        area: Box<AreaDesc>,
+        #[serde(default = "default_scale")]
+        scale: VectorDesc,
+        #[serde(default)]
+        rotation: Angle,
+        #[serde(default = "default_zero_vector")]
+        translation: VectorDesc,
+    },
+    // Type added to the registry by name, built-in types can also be written this way
+    Registered {
+        name: String,
+        #[serde(default)]
+        params: Box<Params>,
+    },
+}
+
+fn default_scale() -> VectorDesc {
+    [1., 1.]
+}
+
+impl Validate for AreaDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Rect { size, .. } => {
+                check(size[0] >= 0. && size[1] >= 0., "size must be positive")
+            }
+            Self::Disk { radius, .. } | Self::RegularPolygon { radius, .. } => {
+                check(*radius >= 0., "radius must be positive")?;
+                match self {
+                    Self::RegularPolygon { sides, .. } => {
+                        check(*sides >= 3, "polygon needs at least 3 sides")
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Self::Polygon { vertices } => {
+                check(vertices.len() >= 3, "polygon needs at least 3 vertices")
+            }
+            Self::Annulus {
+                inner_radius,
+                outer_radius,
+                ..
+            } => check(
+                0. <= *inner_radius && inner_radius <= outer_radius,
+                "annulus needs 0 <= inner_radius <= outer_radius",
+            ),
+            Self::Ellipse { radii, .. } => {
+                check(radii[0] >= 0. && radii[1] >= 0., "radii must be positive")
+            }
+            Self::Intersection { areas } => {
+                check(!areas.is_empty(), "intersection needs at least one area")
+            }
+            Self::Transformed { scale, .. } => {
+                check(scale[0] != 0. && scale[1] != 0., "scale must not be zero")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Particles created at startup
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum ParticlesDesc {
+    Generators {
+        count: usize,
+        position: VectorGeneratorDesc,
+        #[serde(default = "default_velocity")]
+        velocity: VectorGeneratorDesc,
+        #[serde(default = "default_mass")]
+        mass: ScalarGeneratorDesc,
+        #[serde(default = "default_color")]
+        color: ColorGeneratorDesc,
+    },
+    Particle {
+        position: VectorDesc,
+        #[serde(default = "default_zero_vector")]
+        velocity: VectorDesc,
+        #[serde(default = "default_one")]
+        mass: Mass,
+        #[serde(default)]
+        color: ColorDesc,
+    },
+    DiskGalaxy(DiskGalaxyDesc),
+    PlummerSphere {
+        count: usize,
+        center: VectorDesc,
+        #[serde(default = "default_zero_vector")]
+        velocity: VectorDesc,
+        mass: Mass,
+        scale_radius: Length,
+        max_radius: Option<Length>,
+        #[serde(default)]
+        color: ColorDesc,
+    },
+    KeplerSystem {
+        center: VectorDesc,
+        #[serde(default = "default_zero_vector")]
+        velocity: VectorDesc,
+        star_mass: Mass,
+        #[serde(default)]
+        star_color: ColorDesc,
+        #[serde(default)]
+        planets: Vec<PlanetDesc>,
+        #[serde(default)]
+        clockwise: bool,
+    },
+    // Centers and velocities of the galaxies are set by the collision
+    GalaxyCollision {
+        first: DiskGalaxyDesc,
+        second: DiskGalaxyDesc,
+        center: VectorDesc,
+        separation: Length,
+        impact_parameter: Length,
+        #[serde(default = "default_one")]
+        speed_factor: Scalar,
+    },
+}
+
+impl Validate for ParticlesDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Particle { mass, .. } => check(*mass > 0., "mass must be strictly positive"),
+            Self::PlummerSphere {
+                mass, scale_radius, ..
+            } => {
+                check(*mass > 0., "mass must be strictly positive")?;
+                check(*scale_radius > 0., "scale_radius must be strictly positive")
+            }
+            Self::KeplerSystem { star_mass, .. } => {
+                check(*star_mass > 0., "star_mass must be strictly positive")
+            }
+            Self::GalaxyCollision { separation, .. } => {
+                check(*separation > 0., "separation must be strictly positive")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, remote = "Self")]
+pub struct DiskGalaxyDesc {
+    pub count: usize,
+    #[serde(default = "default_zero_vector")]
+    pub center: VectorDesc,
+    #[serde(default = "default_zero_vector")]
+    pub velocity: VectorDesc,
+    pub mass: Mass,
+    pub scale_length: Length,
+    pub max_radius: Option<Length>,
+    #[serde(default)]
+    pub central_mass: Mass,
+    pub dispersion: Option<Scalar>,
+    #[serde(default)]
+    pub clockwise: bool,
+    #[serde(default)]
+    pub color: ColorDesc,
+}
+
+impl Validate for DiskGalaxyDesc {
+    fn validate(&self) -> Result<(), String> {
+        check(self.mass > 0., "mass must be strictly positive")?;
+        check(
+            self.scale_length > 0.,
+            "scale_length must be strictly positive",
+        )?;
+        check(self.central_mass >= 0., "central_mass must be positive")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, remote = "Self")]
+pub struct PlanetDesc {
+    pub semi_major_axis: Length,
+    #[serde(default)]
+    pub eccentricity: Scalar,
+    #[serde(default)]
+    pub angle: Angle,
+    pub mass: Mass,
+    #[serde(default)]
+    pub color: ColorDesc,
+}
+
+impl Validate for PlanetDesc {
+    fn validate(&self) -> Result<(), String> {
+        check(
+            (0. ..1.).contains(&self.eccentricity),
+            "orbit eccentricity must be in [0, 1)",
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GravityDesc {
+    pub coef: Scalar,
+    pub epsilon: Scalar,
+}
+
+impl Default for GravityDesc {
+    fn default() -> Self {
+        Self {
+            coef: 0.,
+            epsilon: 1.,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepulsionDesc {
+    pub coef: Scalar,
+    pub power: i32,
+    pub epsilon: Scalar,
+}
+
+impl Default for RepulsionDesc {
+    fn default() -> Self {
+        Self {
+            coef: 0.,
+            power: 6,
+            epsilon: 1.,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DragDesc {
+    pub coef: Scalar,
+    pub distance: Scalar,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum VectorFieldDesc {
+    CurlNoise {
+        scale: Scalar,
+        strength: Scalar,
+        speed: Scalar,
+    },
+    // Text file relative to the scene file, see SampledField::from_file
+    Sampled {
+        file: PathBuf,
+        bounds: Option<RectDesc>, // Window if missing
+    },
+}
+
+impl Validate for VectorFieldDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::CurlNoise { scale, .. } => check(*scale > 0., "scale must be strictly positive"),
+            Self::Sampled { .. } => Ok(()),
+        }
+    }
+}
+
+// Pair potentials and SPH use a neighbour grid over bounds (the window if missing)
+// periodic makes them interact across the edges of bounds, like a loop system
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum ForceDesc {
+    UniformGravity {
+        acceleration: VectorDesc,
+    },
+    UniformDrag {
+        coef: Scalar,
+        #[serde(default = "default_zero_vector")]
+        velocity: VectorDesc,
+    },
+    // Direct (O(n^2)) pairwise forces
+    Gravity {
+        coef: Scalar,
+        epsilon: Scalar,
+    },
+    Repulsion {
+        coef: Scalar,
+        power: i32,
+        epsilon: Scalar,
+    },
+    Drag {
+        coef: Scalar,
+        distance: Scalar,
+    },
+    // Barnes-Hut forces of the scene quadtree
+    Quadtree,
+    LennardJones {
+        epsilon: Scalar,
+        sigma: Length,
+        cutoff: Option<Length>, // 2.5 sigma if missing
+        bounds: Option<RectDesc>,
+        #[serde(default)]
+        periodic: bool,
+    },
+    Morse {
+        depth: Scalar,
+        width: Scalar,
+        equilibrium: Length,
+        cutoff: Length,
+        bounds: Option<RectDesc>,
+        #[serde(default)]
+        periodic: bool,
+    },
+    SoftSphere {
+        epsilon: Scalar,
+        sigma: Length,
+        power: i32,
+        cutoff: Length,
+        bounds: Option<RectDesc>,
+        #[serde(default)]
+        periodic: bool,
+    },
+    FieldAcceleration {
+        field: VectorFieldDesc,
+    },
+    FieldDrag {
+        field: VectorFieldDesc,
+        coef: Scalar,
+    },
+    // Type added to the registry by name, built-in types can also be written this way
+    Registered {
+        name: String,
+        #[serde(default)]
+        params: Box<Params>,
+    },
+}
+
+impl Validate for ForceDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::LennardJones { sigma, cutoff, .. } => {
+                check(*sigma > 0., "sigma must be strictly positive")?;
+                check(
+                    cutoff.is_none_or(|cutoff| cutoff > 0.),
+                    "cutoff must be strictly positive",
+                )
+            }
+            Self::Morse { cutoff, .. } | Self::SoftSphere { cutoff, .. } => {
+                check(*cutoff > 0., "cutoff must be strictly positive")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum EquationOfStateDesc {
+    IdealGas {
+        stiffness: Scalar,
+        rest_density: Scalar,
+    },
+    Tait {
+        rest_density: Scalar,
+        speed_of_sound: Scalar,
+        gamma: i32,
+    },
+}
+
+impl Validate for EquationOfStateDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::IdealGas { rest_density, .. } | Self::Tait { rest_density, .. } => {
+                check(*rest_density > 0., "rest_density must be strictly positive")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DutyCycleDesc {
+    pub period: Time,
+    pub on_time: Time,
+    #[serde(default)]
+    pub offset: Time,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BurstDesc {
+    pub time: Time,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircleDesc {
+    pub position: VectorDesc,
+    pub radius: Length,
+}
+
+// Systems run in the order of the file
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum SystemDesc {
+    // Bounds default to the window
+    Wall {
+        bounds: Option<RectDesc>,
+        restitution: Scalar,
+    },
+    Loop {
+        bounds: Option<RectDesc>,
+    },
+    // Removes particles in the area
+    Void {
+        area: AreaDesc,
+    },
+    Consumer {
+        area: AreaDesc,
+        rate: Scalar,
+    },
+    // The spawn shape (position generator) is rotated by angle then moved to origin when given
+    Emitter {
+        rate: Scalar,
+        position: VectorGeneratorDesc,
+        #[serde(default = "default_velocity")]
+        velocity: VectorGeneratorDesc,
+        #[serde(default = "default_mass")]
+        mass: ScalarGeneratorDesc,
+        #[serde(default = "default_color")]
+        color: ColorGeneratorDesc,
+        origin: Option<VectorDesc>,
+        angle: Option<Angle>,
+        duty_cycle: Option<DutyCycleDesc>,
+        #[serde(default)]
+        bursts: Vec<BurstDesc>,
+    },
+    Physics {
+        forces: Vec<ForceDesc>,
+    },
+    VelocityIntegrator,
+    ColorWheel {
+        speed: Scalar,
+    },
+    Sph {
+        smoothing_length: Length,
+        viscosity: Scalar,
+        equation_of_state: EquationOfStateDesc,
+        bounds: Option<RectDesc>,
+        #[serde(default)]
+        periodic: bool,
+    },
+    Colliders {
+        restitution: Scalar,
+        friction: Scalar,
+        #[serde(default = "default_max_bounces")]
+        max_bounces: usize,
+        cell_size: Length,
+        #[serde(default)]
+        segments: Vec<[VectorDesc; 2]>,
+        #[serde(default)]
+        polylines: Vec<Vec<VectorDesc>>,
+        #[serde(default)]
+        polygons: Vec<Vec<VectorDesc>>,
+        #[serde(default)]
+        circles: Vec<CircleDesc>,
+        // Particles are kept inside these circles
+        #[serde(default)]
+        containers: Vec<CircleDesc>,
+    },
+    // Thermostats holding the kinetic temperature at the target, see simulation::thermostats
+    VelocityRescaling {
+        temperature: Temperature,
+    },
+    Berendsen {
+        temperature: Temperature,
+        tau: Time,
+    },
+    // Friction rate gamma towards the bath velocity, with noise drawn from the scene seed
+    Langevin {
+        temperature: Temperature,
+        gamma: Scalar,
+        #[serde(default = "default_zero_vector")]
+        velocity: VectorDesc,
+    },
+    // Samples energies & momenta each update (give it a period), see simulation::diagnostics. The
+    // potentials default to the forces of the physics systems deriving from one.
+    Diagnostics {
+        potentials: Option<Vec<ForceDesc>>,
+        drift_threshold: Option<Scalar>,
+        file: Option<PathBuf>, // Csv relative to the scene
+        #[serde(default = "default_log")]
+        log: bool,
+    },
+    // Rhai script relative to the scene, its input handlers are also called by the window
+    Script {
+        file: PathBuf,
+    },
+    // Type added to the registry by name, built-in types can also be written this way
+    Registered {
+        name: String,
+        #[serde(default)]
+        params: Box<Params>,
+    },
+}
+
+fn default_max_bounces() -> usize {
+    4
+}
+
+fn default_log() -> bool {
+    true
+}
+
+// A system of the scene list, events refer to it by id. It is updated every step unless
+// every_steps or every (simulated time) is given, after the systems of run_after (by id).
+#[derive(Debug, Deserialize)]
+#[serde(remote = "Self")]
+pub struct SceneSystemDesc {
+    pub id: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub every_steps: Option<usize>,
+    pub every: Option<Time>,
+    #[serde(default)]
+    pub run_after: Vec<String>,
+    #[serde(flatten)]
+    pub system: SystemDesc,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Validate for SceneSystemDesc {
+    fn validate(&self) -> Result<(), String> {
+        check(
+            self.every_steps.is_none() || self.every.is_none(),
+            "every_steps and every can't be combined",
+        )?;
+        check(
+            self.every_steps != Some(0),
+            "every_steps must be at least 1",
+        )?;
+        check(
+            self.every.is_none_or(|every| every > 0.),
+            "every must be strictly positive",
+        )
+    }
+}
+
+impl Validate for SystemDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Emitter {
+                rate, duty_cycle, ..
+            } => {
+                check(*rate >= 0., "rate must be positive")?;
+                check(
+                    duty_cycle.as_ref().is_none_or(|cycle| cycle.period > 0.),
+                    "duty cycle period must be strictly positive",
+                )
+            }
+            Self::Sph {
+                smoothing_length, ..
+            } => check(
+                *smoothing_length > 0.,
+                "smoothing_length must be strictly positive",
+            ),
+            Self::Colliders { cell_size, .. } => {
+                check(*cell_size > 0., "cell_size must be strictly positive")
+            }
+            Self::VelocityRescaling { temperature } => {
+                check(*temperature >= 0., "temperature must be positive")
+            }
+            Self::Berendsen { temperature, tau } => {
+                check(*temperature >= 0., "temperature must be positive")?;
+                check(*tau > 0., "tau must be strictly positive")
+            }
+            Self::Langevin {
+                temperature, gamma, ..
+            } => {
+                check(*temperature >= 0., "temperature must be positive")?;
+                check(*gamma > 0., "gamma must be strictly positive")
+            }
+            Self::Diagnostics {
+                drift_threshold, ..
+            } => check(
+                drift_threshold.is_none_or(|threshold| threshold > 0.),
+                "drift_threshold must be strictly positive",
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Systems are given by id, actions on a missing one are skipped with a warning
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    deny_unknown_fields,
+    remote = "Self"
+)]
+pub enum ActionDesc {
+    ScaleVelocities {
+        factor: Scalar,
+    },
+    AddVelocity {
+        velocity: VectorDesc,
+    },
+    ClearParticles,
+    // Particles are created when the scene is loaded and added by the event
+    AddParticles {
+        particles: Box<ParticlesDesc>,
+    },
+    RemoveSystem {
+        id: String,
+    },
+    EnableSystem {
+        id: String,
+    },
+    DisableSystem {
+        id: String,
+    },
+    // Keeps the id & enabled flag
+    ReplaceSystem {
+        id: String,
+        system: Box<SystemDesc>,
+    },
+    // Appended when neither before nor after is given
+    InsertSystem {
+        id: Option<String>,
+        before: Option<String>,
+        after: Option<String>,
+        system: Box<SystemDesc>,
+    },
+    Pause,
+    Resume,
+    Stop,
+    SetStepsPerFrame {
+        steps: usize,
+    },
+    // Actions done delay after the event
+    Schedule {
+        delay: Time,
+        actions: Vec<ActionDesc>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ConditionDesc {
+    ParticlesAbove { count: usize },
+    ParticlesBelow { count: usize },
+    KineticEnergyAbove { energy: Energy },
+    KineticEnergyBelow { energy: Energy },
+    // Any particle in the area
+    ParticleIn { area: AreaDesc },
+}
+
+// Fires once at time, or every period from time, or each time the condition becomes true after
+// time. count limits the number of firings of the last two.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, remote = "Self")]
+pub struct EventDesc {
+    #[serde(default)]
+    pub time: Time,
+    pub every: Option<Time>,
+    pub when: Option<ConditionDesc>,
+    pub count: Option<usize>,
+    pub actions: Vec<ActionDesc>,
+}
+
+impl Validate for ActionDesc {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::InsertSystem { before, after, .. } => check(
+                before.is_none() || after.is_none(),
+                "before and after can't be combined",
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Validate for EventDesc {
+    fn validate(&self) -> Result<(), String> {
+        check(
+            self.every.is_none() || self.when.is_none(),
+            "every and when can't be combined",
+        )?;
+        check(
+            self.every.is_none_or(|period| period > 0.),
+            "every must be strictly positive",
+        )?;
+        check(
+            self.count.is_none() || self.every.is_some() || self.when.is_some(),
+            "count needs every or when",
+        )?;
+        check(self.count != Some(0), "count must be strictly positive")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WindowDesc {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_name")]
+    pub name: String,
+    pub max_fps: Option<u64>, // Unlimited if missing or 0
+}
+
+fn default_name() -> String {
+    "Scene".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewDesc {
+    pub center: Option<VectorDesc>, // Center of the window if missing
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    #[serde(default)]
+    pub rotation: f32, // Degrees
+}
+
+fn default_zoom() -> f32 {
+    1.
+}
+
+impl Default for ViewDesc {
+    fn default() -> Self {
+        Self {
+            center: None,
+            zoom: 1.,
+            rotation: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, remote = "Self")]
+pub struct RunnerDesc {
+    pub dt: Time,
+    #[serde(default = "default_steps_per_frame")]
+    pub steps_per_frame: usize,
+    // Parameters applied between steps, again when the file changes (see simulation::tunables)
+    pub tunables: Option<PathBuf>,
+    // Systems touching different particle columns are updated together (see
+    // simulation::system_list)
+    #[serde(default)]
+    pub concurrent_systems: bool,
+}
+
+fn default_steps_per_frame() -> usize {
+    4
+}
+
+impl Validate for RunnerDesc {
+    fn validate(&self) -> Result<(), String> {
+        check(self.dt > 0., "dt must be strictly positive")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, remote = "Self")]
+pub struct QuadTreeDesc {
+    pub rect: Option<RectDesc>, // Square around the window if missing
+    #[serde(default = "default_max_particles")]
+    pub max_particles: usize,
+    #[serde(default)]
+    pub gravity: GravityDesc,
+    #[serde(default)]
+    pub repulsion: RepulsionDesc,
+    #[serde(default)]
+    pub drag: DragDesc,
+    #[serde(default = "default_one")]
+    pub theta: Scalar,
+    #[serde(default = "default_max_depth")]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub max_depth_panics: bool,
+    #[serde(default)]
+    pub periodic: bool, // Periodic over rect
+}
+
+fn default_max_particles() -> usize {
+    10
+}
+
+fn default_max_depth() -> Option<usize> {
+    Some(50)
+}
+
+impl Validate for QuadTreeDesc {
+    fn validate(&self) -> Result<(), String> {
+        check(self.max_particles > 0, "max_particles must be at least 1")?;
+        check(self.theta >= 0., "theta must be positive")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDesc {
+    #[serde(default)]
+    pub seed: u64,
+    pub window: WindowDesc,
+    #[serde(default)]
+    pub view: ViewDesc,
+    pub runner: RunnerDesc,
+    pub quadtree: Option<QuadTreeDesc>,
+    #[serde(default)]
+    pub particles: Vec<ParticlesDesc>,
+    #[serde(default)]
+    pub systems: Vec<SceneSystemDesc>,
+    #[serde(default)]
+    pub events: Vec<EventDesc>,
+}
+
+validated!(
+    AreaDesc,
+    ParticlesDesc,
+    DiskGalaxyDesc,
+    PlanetDesc,
+    VectorFieldDesc,
+    ForceDesc,
+    EquationOfStateDesc,
+    SystemDesc,
+    SceneSystemDesc,
+    RunnerDesc,
+    QuadTreeDesc,
+    ActionDesc,
+    EventDesc,
+);
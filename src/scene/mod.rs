@@ -0,0 +1,316 @@
+// Scenes described in TOML or RON files (see scenes/ for examples): window & view, runner,
+// quadtree, initial particles, ordered systems with their forces & optional ids, events and scripts
+pub mod builder;
+pub mod description;
+pub mod registry;
+mod ron_position;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use sfml::system::Vector2f;
+
+use self::{
+    builder::{BuildResult, Builder},
+    description::SceneDesc,
+    registry::Registry,
+};
+use crate::{
+    app::{max_fps, AppMain},
+    examples::get_default_input_callback,
+    rendering::{
+        render_thread::RenderThread,
+        renderer::{BasicRenderer, RenderData},
+        safe_sfml::{ViewData, WindowData},
+    },
+    scripting::{script_input_callback, SharedScript},
+    simulation::{
+        particles::Particles,
+        quadtree::QuadTree,
+        simulation::{ConstantSimulationRunner, Simulation, SimulationRunner},
+        system_list::{Period, Placement, SystemList},
+        tunables::TunedSimulationRunner,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneFormat {
+    Toml,
+    Ron,
+}
+
+impl SceneFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "ron" => Some(Self::Ron),
+            _ => None,
+        }
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// 1-based line & column of a byte offset
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+// Scenes of scenes/ compiled in the binary: name, path shown in errors, format, source
+pub const BUILTIN_SCENES: &[(&str, &str, SceneFormat, &str)] = &[
+    (
+        "gravity",
+        "scenes/gravity.toml",
+        SceneFormat::Toml,
+        include_str!("../../scenes/gravity.toml"),
+    ),
+    (
+        "galaxies",
+        "scenes/galaxies.toml",
+        SceneFormat::Toml,
+        include_str!("../../scenes/galaxies.toml"),
+    ),
+    (
+        "flow",
+        "scenes/flow.ron",
+        SceneFormat::Ron,
+        include_str!("../../scenes/flow.ron"),
+    ),
+];
+
+// Simulation side of a scene, without window
+pub struct SceneSimulation {
+    pub sim: Simulation,
+    pub sim_runner: Box<dyn SimulationRunner>,
+    pub quadtree: Option<Arc<RwLock<QuadTree>>>,
+    pub scripts: Vec<SharedScript>, // Whose input handlers are called by the window
+}
+
+pub struct Scene {
+    pub description: SceneDesc,
+    pub path: PathBuf, // Shown in errors, relative paths in the scene are resolved from its directory
+}
+
+impl Scene {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let format = SceneFormat::from_path(path).ok_or_else(|| {
+            invalid_data(format!(
+                "{}: unknown scene format (expected .toml or .ron)",
+                path.display()
+            ))
+        })?;
+        let source = fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        Self::from_str(&source, format, path)
+    }
+
+    pub fn builtin(name: &str) -> Option<io::Result<Self>> {
+        BUILTIN_SCENES
+            .iter()
+            .find(|(builtin, ..)| *builtin == name)
+            .map(|(_, path, format, source)| Self::from_str(source, *format, path))
+    }
+
+    // Errors are reported as path:line:column when the position is known
+    pub fn from_str<P: AsRef<Path>>(
+        source: &str,
+        format: SceneFormat,
+        path: P,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let description = Self::parse(source, format).map_err(|(position, message)| {
+            invalid_data(match position {
+                Some((line, column)) => {
+                    format!("{}:{}:{}: {}", path.display(), line, column, message)
+                }
+                None => format!("{}: {}", path.display(), message),
+            })
+        })?;
+        Ok(Self { description, path })
+    }
+
+    fn parse(
+        source: &str,
+        format: SceneFormat,
+    ) -> Result<SceneDesc, (Option<(usize, usize)>, String)> {
+        match format {
+            SceneFormat::Toml => toml::from_str(source).map_err(|e| {
+                let position = line_column(source, e.span().map_or(0, |span| span.start));
+                (Some(position), e.message().to_string())
+            }),
+            // Tagged values are buffered by serde, which loses the bare identifiers of RON: going
+            // through a Value keeps the tags as strings. The Value has no positions, the path of
+            // the failing value is found again in the source.
+            SceneFormat::Ron => {
+                let value = ron::from_str::<ron::Value>(source).map_err(|e| {
+                    let position = (e.span.start.line, e.span.start.col);
+                    (Some(position), e.code.to_string())
+                })?;
+                serde_path_to_error::deserialize(value).map_err(|e| {
+                    let offset = ron_position::locate(source, e.path());
+                    (
+                        Some(line_column(source, offset)),
+                        e.into_inner().to_string(),
+                    )
+                })
+            }
+        }
+    }
+
+    fn directory(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    // Particles, systems and events, in the order of the file
+    fn build_parts(&self, builder: &mut Builder) -> BuildResult<SceneSimulation> {
+        let scene = &self.description;
+        let quadtree = scene
+            .quadtree
+            .as_ref()
+            .map(|quadtree| builder.quadtree(quadtree));
+
+        let mut particles = Particles::new_empty();
+        for (i, desc) in scene.particles.iter().enumerate() {
+            builder
+                .particles(desc, &mut particles)
+                .map_err(|e| format!("particles[{}]: {}", i, e))?;
+        }
+
+        let mut systems = SystemList::new();
+        for (i, desc) in scene.systems.iter().enumerate() {
+            let system = builder
+                .system(&desc.system)
+                .map_err(|e| format!("systems[{}]: {}", i, e))?;
+            if let Some(id) = desc.id.as_deref().filter(|id| systems.handle(id).is_some()) {
+                return Err(format!("systems[{}]: id {} already used", i, id));
+            }
+            let handle = systems
+                .insert(Placement::Last, desc.id.as_deref(), system)
+                .unwrap();
+            systems.set_enabled(handle, desc.enabled);
+            if let Some(steps) = desc.every_steps {
+                systems.set_period(handle, Period::Steps(steps));
+            }
+            if let Some(time) = desc.every {
+                systems.set_period(handle, Period::Time(time));
+            }
+        }
+
+        // Once all ids are known
+        let handles = systems.handles().collect::<Vec<_>>();
+        for (i, (desc, handle)) in scene.systems.iter().zip(handles).enumerate() {
+            for id in desc.run_after.iter() {
+                let dependency = systems
+                    .handle(id)
+                    .ok_or_else(|| format!("systems[{}]: no system with id {}", i, id))?;
+                if !systems.run_after(handle, dependency) {
+                    return Err(format!(
+                        "systems[{}]: running after {} makes a cycle",
+                        i, id
+                    ));
+                }
+            }
+        }
+        systems.concurrent = scene.runner.concurrent_systems;
+
+        // Also runs the events of the scripts
+        let event_handler = if scene.events.is_empty() && builder.scripts().is_empty() {
+            None
+        } else {
+            Some(Box::new(builder.events(&scene.events)?) as _)
+        };
+
+        let mut sim_runner: Box<dyn SimulationRunner> =
+            Box::new(ConstantSimulationRunner::new(scene.runner.dt));
+        if let Some(file) = &scene.runner.tunables {
+            sim_runner = Box::new(TunedSimulationRunner::new(
+                sim_runner,
+                self.directory().join(file),
+            ));
+        }
+
+        Ok(SceneSimulation {
+            sim: Simulation::new(particles, systems, event_handler),
+            sim_runner,
+            quadtree,
+            scripts: builder.scripts().to_vec(),
+        })
+    }
+
+    pub fn build_simulation(&self) -> io::Result<SceneSimulation> {
+        self.build_simulation_with(&Registry::default())
+    }
+
+    // With the types registered by the user
+    pub fn build_simulation_with(&self, registry: &Registry) -> io::Result<SceneSimulation> {
+        let mut builder = Builder::new(&self.description, self.directory(), registry);
+        self.build_parts(&mut builder)
+            .map_err(|e| invalid_data(format!("{}: {}", self.path.display(), e)))
+    }
+
+    pub fn build(&self) -> io::Result<AppMain> {
+        self.build_with(&Registry::default())
+    }
+
+    pub fn build_with(&self, registry: &Registry) -> io::Result<AppMain> {
+        let SceneSimulation {
+            sim,
+            sim_runner,
+            quadtree,
+            scripts,
+        } = self.build_simulation_with(registry)?;
+
+        let window = &self.description.window;
+        let view = &self.description.view;
+        let size = Vector2f::new(window.width as f32, window.height as f32);
+        let center = match view.center {
+            Some([x, y]) => Vector2f::new(x as f32, y as f32),
+            None => size / 2.,
+        };
+        let view_data = ViewData::new(
+            center,
+            size,
+            sfml::graphics::FloatRect::new(0., 0., 1., 1.),
+            view.rotation,
+            view.zoom,
+        );
+
+        let render_thread = RenderThread::new(WindowData::new(
+            (window.width, window.height),
+            format!("Iridium - {}", window.name),
+            sfml::window::Style::DEFAULT,
+            sfml::window::ContextSettings::default(),
+            false,
+        ));
+
+        Ok(AppMain::new(
+            sim,
+            Box::new(BasicRenderer::new(
+                quadtree,
+                render_thread,
+                script_input_callback(scripts, get_default_input_callback()),
+                window.max_fps.filter(|fps| *fps > 0).and_then(max_fps),
+                RenderData::new(view_data),
+            )),
+            sim_runner,
+            self.description.runner.steps_per_frame,
+            Duration::from_secs(1),
+        ))
+    }
+}
+
+pub fn load_scene<P: AsRef<Path>>(path: P) -> io::Result<AppMain> {
+    Scene::from_file(path)?.build()
+}
@@ -0,0 +1,867 @@
+// Named constructors of systems, forces, generators & areas with the schema of their
+// parameters, to build them from data (scene files, scripts, UIs). Registry::default() holds
+// the built-in types, user crates add theirs with register on the matching constructors:
+//
+//     let mut registry = Registry::default();
+//     registry.systems.register(
+//         "spin",
+//         Schema::new("Rotates the velocities").required("speed", ParamType::Scalar, ""),
+//         |_, params| Ok(Box::new(Spin::new(params.get("speed")?))),
+//     );
+//     let app = scene.build_with(&registry)?;
+//
+// then use them in scenes as { type = "registered", name = "spin", params = { speed = 0.1 } }
+use std::{collections::BTreeMap, fmt};
+
+use nalgebra::Vector2;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::{
+    builder::{BuildResult, Builder},
+    description::{
+        AreaDesc, ColorGeneratorDesc, ForceDesc, ScalarGeneratorDesc, SystemDesc,
+        VectorGeneratorDesc,
+    },
+};
+use crate::simulation::{
+    areas::Area, color::Color, forces::Force, generators::Generator, systems::System, types::Scalar,
+};
+
+// Parameters of a registered type, values are read with the same formats as scene files
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Params(pub toml::Table);
+
+impl Params {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<T: Into<toml::Value>>(mut self, name: &str, value: T) -> Self {
+        self.0.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> BuildResult<T> {
+        self.get_opt(name)?
+            .ok_or_else(|| format!("missing parameter `{}`", name))
+    }
+
+    pub fn get_or<T: DeserializeOwned>(&self, name: &str, default: T) -> BuildResult<T> {
+        Ok(self.get_opt(name)?.unwrap_or(default))
+    }
+
+    pub fn get_opt<T: DeserializeOwned>(&self, name: &str) -> BuildResult<Option<T>> {
+        self.0
+            .get(name)
+            .map(|value| {
+                T::deserialize(value.clone()).map_err(|e| format!("{}: {}", name, e.message()))
+            })
+            .transpose()
+    }
+
+    // Table with the type tag of the scene descriptions, to deserialize the built-in types
+    fn tagged<T: DeserializeOwned>(&self, name: &str) -> BuildResult<T> {
+        let mut table = self.0.clone();
+        table.insert("type".to_string(), name.into());
+        T::deserialize(toml::Value::Table(table)).map_err(|e| e.message().to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamType {
+    Bool,
+    Integer,
+    Scalar,
+    String,
+    Vector, // [x, y]
+    Color,
+    Rect, // { position, size }
+    Area,
+    ScalarGenerator,
+    VectorGenerator,
+    ColorGenerator,
+    VectorField,
+    EquationOfState,
+    Force,
+    Table,
+    List(&'static ParamType),
+}
+
+impl ParamType {
+    // Only checks the shape of simple values, the others are checked when they are read
+    fn matches(&self, value: &toml::Value) -> bool {
+        use toml::Value;
+        match (self, value) {
+            (Self::Bool, Value::Boolean(_)) => true,
+            (Self::Integer, Value::Integer(_)) => true,
+            (Self::Scalar, Value::Integer(_) | Value::Float(_)) => true,
+            (Self::String, Value::String(_)) => true,
+            (Self::Vector, Value::Array(values)) => {
+                values.len() == 2 && values.iter().all(|v| Self::Scalar.matches(v))
+            }
+            (Self::List(item), Value::Array(values)) => values.iter().all(|v| item.matches(v)),
+            (
+                Self::Rect
+                | Self::Area
+                | Self::VectorField
+                | Self::EquationOfState
+                | Self::Force
+                | Self::Table,
+                value,
+            ) => value.is_table(),
+            // Constants or tables
+            (
+                Self::Color | Self::ScalarGenerator | Self::VectorGenerator | Self::ColorGenerator,
+                _,
+            ) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bool => write!(f, "bool"),
+            Self::Integer => write!(f, "integer"),
+            Self::Scalar => write!(f, "scalar"),
+            Self::String => write!(f, "string"),
+            Self::Vector => write!(f, "vector"),
+            Self::Color => write!(f, "color"),
+            Self::Rect => write!(f, "rect"),
+            Self::Area => write!(f, "area"),
+            Self::ScalarGenerator => write!(f, "scalar generator"),
+            Self::VectorGenerator => write!(f, "vector generator"),
+            Self::ColorGenerator => write!(f, "color generator"),
+            Self::VectorField => write!(f, "vector field"),
+            Self::EquationOfState => write!(f, "equation of state"),
+            Self::Force => write!(f, "force"),
+            Self::Table => write!(f, "table"),
+            Self::List(item) => write!(f, "list of {}", item),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamSchema {
+    pub name: &'static str,
+    pub param_type: ParamType,
+    pub required: bool,
+    pub description: &'static str, // With the default value of optional parameters
+}
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub description: &'static str,
+    pub params: Vec<ParamSchema>,
+}
+
+impl Schema {
+    pub fn new(description: &'static str) -> Self {
+        Self {
+            description,
+            params: Vec::new(),
+        }
+    }
+
+    fn param(
+        mut self,
+        name: &'static str,
+        param_type: ParamType,
+        required: bool,
+        description: &'static str,
+    ) -> Self {
+        assert!(
+            self.params.iter().all(|param| param.name != name),
+            "Parameter {} declared twice",
+            name
+        );
+        self.params.push(ParamSchema {
+            name,
+            param_type,
+            required,
+            description,
+        });
+        self
+    }
+
+    pub fn required(
+        self,
+        name: &'static str,
+        param_type: ParamType,
+        description: &'static str,
+    ) -> Self {
+        self.param(name, param_type, true, description)
+    }
+
+    pub fn optional(
+        self,
+        name: &'static str,
+        param_type: ParamType,
+        description: &'static str,
+    ) -> Self {
+        self.param(name, param_type, false, description)
+    }
+
+    // Unknown, missing and mistyped parameters
+    pub fn check(&self, params: &Params) -> BuildResult<()> {
+        for (name, value) in params.0.iter() {
+            let Some(param) = self.params.iter().find(|param| param.name == name) else {
+                if self.params.is_empty() {
+                    return Err(format!("unknown parameter `{}`, expected none", name));
+                }
+                let names = self.params.iter().map(|param| param.name);
+                return Err(format!(
+                    "unknown parameter `{}`, expected one of: {}",
+                    name,
+                    names.collect::<Vec<_>>().join(", ")
+                ));
+            };
+            if !param.param_type.matches(value) {
+                return Err(format!("{}: expected a {}", name, param.param_type));
+            }
+        }
+        for param in self.params.iter().filter(|param| param.required) {
+            if !params.contains(param.name) {
+                return Err(format!("missing parameter `{}`", param.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description)?;
+        for param in self.params.iter() {
+            write!(f, "\n  {}: {}", param.name, param.param_type)?;
+            if !param.required {
+                write!(f, " (optional)")?;
+            }
+            if !param.description.is_empty() {
+                write!(f, " - {}", param.description)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type Constructor<T> = Box<dyn Fn(&mut Builder, &Params) -> BuildResult<T> + Send + Sync>;
+
+struct Entry<T> {
+    schema: Schema,
+    constructor: Constructor<T>,
+}
+
+// Constructors of one kind of object, by name
+pub struct Constructors<T> {
+    entries: BTreeMap<String, Entry<T>>,
+}
+
+impl<T> Constructors<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    // Panics if the name is already used
+    pub fn register<F>(&mut self, name: &str, schema: Schema, constructor: F)
+    where
+        F: Fn(&mut Builder, &Params) -> BuildResult<T> + Send + Sync + 'static,
+    {
+        assert!(
+            !self.entries.contains_key(name),
+            "{} is already registered",
+            name
+        );
+        let constructor = Box::new(constructor);
+        self.entries.insert(
+            name.to_string(),
+            Entry {
+                schema,
+                constructor,
+            },
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    // Sorted by name
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| name.as_str())
+    }
+
+    pub fn schema(&self, name: &str) -> Option<&Schema> {
+        self.entries.get(name).map(|entry| &entry.schema)
+    }
+
+    // Parameters are checked against the schema first
+    pub fn build(&self, name: &str, builder: &mut Builder, params: &Params) -> BuildResult<T> {
+        let entry = self.entries.get(name).ok_or_else(|| {
+            format!(
+                "unknown type `{}`, expected one of: {}",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        entry.schema.check(params)?;
+        (entry.constructor)(builder, params)
+    }
+}
+
+impl<T> Default for Constructors<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Registry {
+    pub systems: Constructors<Box<dyn System>>,
+    pub forces: Constructors<Box<dyn Force>>,
+    pub areas: Constructors<Box<dyn Area>>,
+    pub scalar_generators: Constructors<Box<dyn Generator<Scalar>>>,
+    pub vector_generators: Constructors<Box<dyn Generator<Vector2<Scalar>>>>,
+    pub color_generators: Constructors<Box<dyn Generator<Color>>>,
+}
+
+impl Registry {
+    // Without the built-in types
+    pub fn new_empty() -> Self {
+        Self {
+            systems: Constructors::new(),
+            forces: Constructors::new(),
+            areas: Constructors::new(),
+            scalar_generators: Constructors::new(),
+            vector_generators: Constructors::new(),
+            color_generators: Constructors::new(),
+        }
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::new_empty();
+        register_builtins(
+            &mut registry.systems,
+            builtin_systems(),
+            |builder, desc: &SystemDesc| builder.system(desc),
+        );
+        register_builtins(
+            &mut registry.forces,
+            builtin_forces(),
+            |builder, desc: &ForceDesc| builder.force(desc),
+        );
+        register_builtins(
+            &mut registry.areas,
+            builtin_areas(),
+            |builder, desc: &AreaDesc| builder.area(desc),
+        );
+        register_builtins(
+            &mut registry.scalar_generators,
+            builtin_scalar_generators(),
+            |builder, desc: &ScalarGeneratorDesc| builder.scalar_generator(desc),
+        );
+        register_builtins(
+            &mut registry.vector_generators,
+            builtin_vector_generators(),
+            |builder, desc: &VectorGeneratorDesc| builder.vector_generator(desc),
+        );
+        register_builtins(
+            &mut registry.color_generators,
+            builtin_color_generators(),
+            |builder, desc: &ColorGeneratorDesc| builder.color_generator(desc),
+        );
+        registry
+    }
+}
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn kind<T>(
+            f: &mut fmt::Formatter,
+            title: &str,
+            constructors: &Constructors<T>,
+        ) -> fmt::Result {
+            writeln!(f, "# {}", title)?;
+            for (name, entry) in constructors.entries.iter() {
+                writeln!(f, "{}: {}", name, entry.schema)?;
+            }
+            Ok(())
+        }
+
+        kind(f, "Systems", &self.systems)?;
+        kind(f, "Forces", &self.forces)?;
+        kind(f, "Areas", &self.areas)?;
+        kind(f, "Scalar generators", &self.scalar_generators)?;
+        kind(f, "Vector generators", &self.vector_generators)?;
+        kind(f, "Color generators", &self.color_generators)
+    }
+}
+
+// Built-in types are read as their scene description then built the same way
+fn register_builtins<T, D, F>(
+    constructors: &mut Constructors<T>,
+    schemas: Vec<(&'static str, Schema)>,
+    build: F,
+) where
+    D: DeserializeOwned,
+    F: Fn(&mut Builder, &D) -> BuildResult<T> + Copy + Send + Sync + 'static,
+{
+    for (name, schema) in schemas {
+        constructors.register(name, schema, move |builder, params| {
+            build(builder, &params.tagged(name)?)
+        });
+    }
+}
+
+const BOUNDS: &str = "Neighbour grid rect, the window if missing";
+const PERIODIC: &str = "Interactions across the edges of bounds, false if missing";
+
+fn builtin_systems() -> Vec<(&'static str, Schema)> {
+    use ParamType::*;
+
+    vec![
+        (
+            "wall",
+            Schema::new("Particles bounce on the edges of a rect")
+                .optional("bounds", Rect, "The window if missing")
+                .required("restitution", Scalar, "Speed kept after a bounce"),
+        ),
+        (
+            "loop",
+            Schema::new("Particles leaving a rect come back from the opposite edge").optional(
+                "bounds",
+                Rect,
+                "The window if missing",
+            ),
+        ),
+        (
+            "void",
+            Schema::new("Removes the particles in an area").required("area", Area, ""),
+        ),
+        (
+            "consumer",
+            Schema::new("Removes particles in an area at a constant rate")
+                .required("area", Area, "")
+                .required("rate", Scalar, "Particles per time unit"),
+        ),
+        (
+            "emitter",
+            Schema::new("Creates particles at a rate, with bursts and duty cycles")
+                .required("rate", Scalar, "Particles per time unit")
+                .required("position", VectorGenerator, "Spawn shape")
+                .optional("velocity", VectorGenerator, "Zero if missing")
+                .optional("mass", ScalarGenerator, "1 if missing")
+                .optional("color", ColorGenerator, "White if missing")
+                .optional("origin", Vector, "Position of the spawn shape")
+                .optional("angle", Scalar, "Rotation of the spawn shape")
+                .optional("duty_cycle", Table, "{ period, on_time, offset }")
+                .optional("bursts", List(&Table), "[{ time, count }]"),
+        ),
+        (
+            "physics",
+            Schema::new("Sums forces and integrates the velocities").required(
+                "forces",
+                List(&Force),
+                "",
+            ),
+        ),
+        (
+            "velocity_integrator",
+            Schema::new("Integrates the positions"),
+        ),
+        (
+            "color_wheel",
+            Schema::new("Rotates the hue of the particles").required(
+                "speed",
+                Scalar,
+                "Degrees per time unit",
+            ),
+        ),
+        (
+            "sph",
+            Schema::new("Smoothed-particle hydrodynamics")
+                .required("smoothing_length", Scalar, "")
+                .required("viscosity", Scalar, "")
+                .required("equation_of_state", EquationOfState, "")
+                .optional("bounds", Rect, BOUNDS)
+                .optional("periodic", Bool, PERIODIC),
+        ),
+        (
+            "colliders",
+            Schema::new("Static obstacles particles bounce on")
+                .required("restitution", Scalar, "")
+                .required("friction", Scalar, "")
+                .optional("max_bounces", Integer, "Per step, 4 if missing")
+                .required("cell_size", Scalar, "Size of the collision grid cells")
+                .optional("segments", List(&List(&Vector)), "[[start, end]]")
+                .optional("polylines", List(&List(&Vector)), "")
+                .optional("polygons", List(&List(&Vector)), "")
+                .optional("circles", List(&Table), "[{ position, radius }]")
+                .optional("containers", List(&Table), "[{ position, radius }]"),
+        ),
+        (
+            "velocity_rescaling",
+            Schema::new("Sets the kinetic temperature to the target each update").required(
+                "temperature",
+                Scalar,
+                "",
+            ),
+        ),
+        (
+            "berendsen",
+            Schema::new("Relaxes the kinetic temperature towards the target")
+                .required("temperature", Scalar, "")
+                .required("tau", Scalar, "Time constant"),
+        ),
+        (
+            "langevin",
+            Schema::new("Friction towards a bath velocity with thermal noise")
+                .required("temperature", Scalar, "")
+                .required("gamma", Scalar, "Friction rate")
+                .optional("velocity", Vector, "Of the bath, zero if missing"),
+        ),
+        (
+            "diagnostics",
+            Schema::new(
+                "Samples energies, temperature, momenta, center of mass & virial ratio each update",
+            )
+            .optional(
+                "potentials",
+                List(&Force),
+                "The physics forces deriving from a potential if missing",
+            )
+            .optional("drift_threshold", Scalar, "Relative drift warned about")
+            .optional("file", String, "Csv relative to the scene")
+            .optional("log", Bool, "true if missing"),
+        ),
+        (
+            "script",
+            Schema::new("Rhai script run every step, reloaded when the file changes").required(
+                "file",
+                String,
+                "Relative to the scene",
+            ),
+        ),
+    ]
+}
+
+fn builtin_forces() -> Vec<(&'static str, Schema)> {
+    use ParamType::*;
+
+    vec![
+        (
+            "uniform_gravity",
+            Schema::new("Constant acceleration").required("acceleration", Vector, ""),
+        ),
+        (
+            "uniform_drag",
+            Schema::new("Drag relative to a constant velocity")
+                .required("coef", Scalar, "")
+                .optional("velocity", Vector, "Zero if missing"),
+        ),
+        (
+            "gravity",
+            Schema::new("Direct pairwise gravity")
+                .required("coef", Scalar, "")
+                .required("epsilon", Scalar, "Softening"),
+        ),
+        (
+            "repulsion",
+            Schema::new("Direct pairwise repulsion")
+                .required("coef", Scalar, "")
+                .required("power", Integer, "")
+                .required("epsilon", Scalar, "Softening"),
+        ),
+        (
+            "drag",
+            Schema::new("Direct pairwise drag")
+                .required("coef", Scalar, "")
+                .required("distance", Scalar, ""),
+        ),
+        (
+            "quadtree",
+            Schema::new("Barnes-Hut forces of the scene quadtree"),
+        ),
+        (
+            "lennard_jones",
+            Schema::new("Lennard-Jones pair potential")
+                .required("epsilon", Scalar, "Depth of the well")
+                .required("sigma", Scalar, "Distance of zero potential")
+                .optional("cutoff", Scalar, "2.5 sigma if missing")
+                .optional("bounds", Rect, BOUNDS)
+                .optional("periodic", Bool, PERIODIC),
+        ),
+        (
+            "morse",
+            Schema::new("Morse pair potential")
+                .required("depth", Scalar, "")
+                .required("width", Scalar, "")
+                .required("equilibrium", Scalar, "")
+                .required("cutoff", Scalar, "")
+                .optional("bounds", Rect, BOUNDS)
+                .optional("periodic", Bool, PERIODIC),
+        ),
+        (
+            "soft_sphere",
+            Schema::new("Purely repulsive soft-sphere pair potential")
+                .required("epsilon", Scalar, "")
+                .required("sigma", Scalar, "")
+                .required("power", Integer, "")
+                .required("cutoff", Scalar, "")
+                .optional("bounds", Rect, BOUNDS)
+                .optional("periodic", Bool, PERIODIC),
+        ),
+        (
+            "field_acceleration",
+            Schema::new("Acceleration sampled in a vector field").required(
+                "field",
+                VectorField,
+                "",
+            ),
+        ),
+        (
+            "field_drag",
+            Schema::new("Drag relative to the velocity of a vector field")
+                .required("field", VectorField, "")
+                .required("coef", Scalar, ""),
+        ),
+    ]
+}
+
+fn builtin_areas() -> Vec<(&'static str, Schema)> {
+    use ParamType::*;
+
+    vec![
+        ("window", Schema::new("The window rect")),
+        (
+            "rect",
+            Schema::new("Axis aligned rect")
+                .required("position", Vector, "Lower corner")
+                .required("size", Vector, ""),
+        ),
+        (
+            "disk",
+            Schema::new("Disk")
+                .required("position", Vector, "Center")
+                .required("radius", Scalar, ""),
+        ),
+        (
+            "point",
+            Schema::new("Single point").required("position", Vector, ""),
+        ),
+        (
+            "polygon",
+            Schema::new("Simple polygon").required("vertices", List(&Vector), ""),
+        ),
+        (
+            "regular_polygon",
+            Schema::new("Regular polygon")
+                .required("position", Vector, "Center")
+                .required("radius", Scalar, "")
+                .required("sides", Integer, "")
+                .optional("angle", Scalar, "0 if missing"),
+        ),
+        (
+            "annulus",
+            Schema::new("Ring between two circles")
+                .required("position", Vector, "Center")
+                .required("inner_radius", Scalar, "")
+                .required("outer_radius", Scalar, ""),
+        ),
+        (
+            "ellipse",
+            Schema::new("Ellipse")
+                .required("position", Vector, "Center")
+                .required("radii", Vector, "")
+                .optional("angle", Scalar, "0 if missing"),
+        ),
+        (
+            "union",
+            Schema::new("Union of areas").required("areas", List(&Area), ""),
+        ),
+        (
+            "intersection",
+            Schema::new("Intersection of areas").required("areas", List(&Area), ""),
+        ),
+        (
+            "difference",
+            Schema::new("Area minus another")
+                .required("area", Area, "")
+                .required("removed", Area, ""),
+        ),
+        (
+            "complement",
+            Schema::new("Everything outside an area").required("area", Area, ""),
+        ),
+        (
+            "transformed",
+            Schema::new("Area scaled, then rotated around the origin, then translated")
+                .required("area", Area, "")
+                .optional("scale", Vector, "[1, 1] if missing")
+                .optional("rotation", Scalar, "0 if missing")
+                .optional("translation", Vector, "[0, 0] if missing"),
+        ),
+    ]
+}
+
+fn builtin_scalar_generators() -> Vec<(&'static str, Schema)> {
+    use ParamType::*;
+
+    vec![
+        (
+            "constant",
+            Schema::new("Always the same value").required("value", Scalar, ""),
+        ),
+        (
+            "uniform",
+            Schema::new("Uniform distribution")
+                .required("min", Scalar, "")
+                .required("max", Scalar, ""),
+        ),
+        (
+            "normal",
+            Schema::new("Normal distribution")
+                .required("mean", Scalar, "")
+                .required("std_dev", Scalar, ""),
+        ),
+        (
+            "log_normal",
+            Schema::new("Log-normal distribution")
+                .required("mean", Scalar, "Of the values, not of their log")
+                .required("std_dev", Scalar, "Of the values, not of their log"),
+        ),
+        (
+            "exponential",
+            Schema::new("Exponential distribution").required("rate", Scalar, ""),
+        ),
+        (
+            "power_law",
+            Schema::new("Power-law distribution between min and max")
+                .required("exponent", Scalar, "")
+                .required("min", Scalar, "")
+                .required("max", Scalar, ""),
+        ),
+        (
+            "maxwell_boltzmann_speed",
+            Schema::new("Maxwell-Boltzmann speeds")
+                .required("temperature", Scalar, "")
+                .required("mass", Scalar, ""),
+        ),
+        (
+            "choice",
+            Schema::new("Weighted choice among values")
+                .required("values", List(&Scalar), "")
+                .optional("weights", List(&Scalar), "Uniform if missing"),
+        ),
+    ]
+}
+
+fn builtin_vector_generators() -> Vec<(&'static str, Schema)> {
+    use ParamType::*;
+
+    vec![
+        (
+            "constant",
+            Schema::new("Always the same vector").required("value", Vector, ""),
+        ),
+        (
+            "cartesian",
+            Schema::new("Independent components")
+                .required("x", ScalarGenerator, "")
+                .required("y", ScalarGenerator, ""),
+        ),
+        (
+            "polar",
+            Schema::new("Length and angle")
+                .required("length", ScalarGenerator, "")
+                .required("angle", ScalarGenerator, ""),
+        ),
+        (
+            "random",
+            Schema::new("Random points uniformly distributed in an area")
+                .required("area", Area, ""),
+        ),
+        (
+            "uniform_rect",
+            Schema::new("Evenly spaced points in a rect")
+                .required("position", Vector, "")
+                .required("size", Vector, ""),
+        ),
+        (
+            "uniform_disk",
+            Schema::new("Evenly spaced points in a disk")
+                .required("position", Vector, "")
+                .required("radius", Scalar, ""),
+        ),
+        (
+            "poisson_disk",
+            Schema::new("Blue noise points in an area")
+                .required("area", Area, "")
+                .optional("min_distance", Scalar, "Fits the particle count if missing"),
+        ),
+        (
+            "hex_lattice",
+            Schema::new("Hexagonal lattice in an area").required("area", Area, ""),
+        ),
+        (
+            "jittered_grid",
+            Schema::new("Grid with random offsets in an area")
+                .required("area", Area, "")
+                .required("jitter", Scalar, "In [0, 1]"),
+        ),
+        (
+            "maxwell_boltzmann",
+            Schema::new("Maxwell-Boltzmann velocities")
+                .required("temperature", Scalar, "")
+                .required("mass", Scalar, ""),
+        ),
+        (
+            "choice",
+            Schema::new("Weighted choice among vectors")
+                .required("values", List(&Vector), "")
+                .optional("weights", List(&Scalar), "Uniform if missing"),
+        ),
+    ]
+}
+
+fn builtin_color_generators() -> Vec<(&'static str, Schema)> {
+    use ParamType::*;
+
+    vec![
+        (
+            "constant",
+            Schema::new("Always the same color").required("value", Color, ""),
+        ),
+        (
+            "rgba",
+            Schema::new("Components in [0, 1]")
+                .required("r", ScalarGenerator, "")
+                .required("g", ScalarGenerator, "")
+                .required("b", ScalarGenerator, "")
+                .optional("a", ScalarGenerator, "1 if missing"),
+        ),
+        (
+            "hsva",
+            Schema::new("Hue in degrees, other components in [0, 1]")
+                .required("h", ScalarGenerator, "")
+                .optional("s", ScalarGenerator, "1 if missing")
+                .optional("v", ScalarGenerator, "1 if missing")
+                .optional("a", ScalarGenerator, "1 if missing"),
+        ),
+        (
+            "choice",
+            Schema::new("Weighted choice among colors")
+                .required("values", List(&Color), "")
+                .optional("weights", List(&Scalar), "Uniform if missing"),
+        ),
+    ]
+}
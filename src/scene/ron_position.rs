@@ -0,0 +1,190 @@
+// Finds where a value is in a RON source from its path in the description (e.g. systems[2].rate),
+// as RON scenes are deserialized from a ron::Value which has no positions. Only the structure is
+// scanned: brackets, fields, strings & comments. When a part of the path can't be followed, the
+// position of the last value found is returned.
+use serde_path_to_error::{Path, Segment};
+
+// Byte offset of the value at path
+pub fn locate(source: &str, path: &Path) -> usize {
+    let source = source.as_bytes();
+    let mut at = skip_blank(source, 0);
+    for segment in path.iter() {
+        match find(source, at, segment) {
+            Some(value) => at = value,
+            None => break,
+        }
+    }
+    at
+}
+
+// Start of a field or element of the value at `at`, looking through Some(...)
+fn find(source: &[u8], mut at: usize, segment: &Segment) -> Option<usize> {
+    if let Segment::Enum { .. } = segment {
+        return Some(at);
+    }
+    loop {
+        let ident_end = skip_ident(source, at);
+        let open = skip_blank(source, ident_end);
+        if !matches!(source.get(open), Some(b'(' | b'[' | b'{')) {
+            return None;
+        }
+        let entries = entries(source, open);
+
+        let some = &source[at..ident_end] == b"Some";
+        if !some {
+            let found = match segment {
+                Segment::Map { key } => entries
+                    .iter()
+                    .find(|(entry_key, _)| entry_key.as_deref() == Some(key.as_str())),
+                Segment::Seq { index } => entries.get(*index),
+                _ => None,
+            };
+            if let Some((_, value)) = found {
+                return Some(*value);
+            }
+        }
+
+        // Newtypes & options
+        match entries.as_slice() {
+            [(None, value)] if source[open] == b'(' => at = *value,
+            _ => return None,
+        }
+    }
+}
+
+// Keys (fields or string keys) & value offsets of the bracketed value at open
+fn entries(source: &[u8], open: usize) -> Vec<(Option<String>, usize)> {
+    let mut entries = Vec::new();
+    let mut i = skip_blank(source, open + 1);
+    while i < source.len() && !matches!(source[i], b')' | b']' | b'}') {
+        let key_end = match source[i] {
+            b'"' => skip_string(source, i),
+            _ => skip_ident(source, i),
+        };
+        let colon = skip_blank(source, key_end);
+        let (key, value) = if key_end > i && source.get(colon) == Some(&b':') {
+            let key = String::from_utf8_lossy(&source[i..key_end]);
+            (
+                Some(key.trim_matches('"').to_string()),
+                skip_blank(source, colon + 1),
+            )
+        } else {
+            (None, i)
+        };
+        entries.push((key, value));
+
+        i = skip_blank(source, skip_value(source, value));
+        if source.get(i) == Some(&b',') {
+            i = skip_blank(source, i + 1);
+        } else if i == value {
+            break; // Not valid RON, already reported by the parser
+        }
+    }
+    entries
+}
+
+fn skip_value(source: &[u8], at: usize) -> usize {
+    match source.get(at) {
+        Some(b'"') => skip_string(source, at),
+        Some(b'\'') => skip_char(source, at),
+        Some(b'(' | b'[' | b'{') => skip_group(source, at),
+        Some(_) => {
+            let end = skip_ident(source, at);
+            let open = skip_blank(source, end);
+            if end > at && source.get(open) == Some(&b'(') {
+                skip_group(source, open)
+            } else {
+                end
+            }
+        }
+        None => at,
+    }
+}
+
+fn skip_group(source: &[u8], open: usize) -> usize {
+    let mut depth = 0;
+    let mut i = open;
+    while i < source.len() {
+        match source[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            b'"' => {
+                i = skip_string(source, i);
+                continue;
+            }
+            b'\'' => {
+                i = skip_char(source, i);
+                continue;
+            }
+            b'/' if matches!(source.get(i + 1), Some(b'/' | b'*')) => {
+                i = skip_blank(source, i);
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+// Identifiers, numbers & other bare words
+fn skip_ident(source: &[u8], at: usize) -> usize {
+    let mut i = at;
+    while i < source.len()
+        && (source[i].is_ascii_alphanumeric() || matches!(source[i], b'_' | b'.' | b'+' | b'-'))
+    {
+        i += 1;
+    }
+    i
+}
+
+fn skip_string(source: &[u8], at: usize) -> usize {
+    let mut i = at + 1;
+    while i < source.len() {
+        match source[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    i
+}
+
+fn skip_char(source: &[u8], at: usize) -> usize {
+    let mut i = at + 1;
+    while i < source.len() {
+        match source[i] {
+            b'\\' => i += 2,
+            b'\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    i
+}
+
+// Whitespace & comments
+fn skip_blank(source: &[u8], at: usize) -> usize {
+    let mut i = at;
+    while i < source.len() {
+        if source[i].is_ascii_whitespace() {
+            i += 1;
+        } else if source[i..].starts_with(b"//") {
+            while i < source.len() && source[i] != b'\n' {
+                i += 1;
+            }
+        } else if source[i..].starts_with(b"/*") {
+            i = source[i + 2..]
+                .windows(2)
+                .position(|end| end == b"*/")
+                .map_or(source.len(), |end| i + 2 + end + 2);
+        } else {
+            break;
+        }
+    }
+    i
+}
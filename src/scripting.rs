@@ -0,0 +1,545 @@
+// Rhai scripts defining systems, events & input handlers, reloaded when their file changes.
+//
+// A script defines any of these functions, all optional:
+//   init(particles)                        before the first update
+//   update(particles, dt)                  every step, as a system
+//   on_key_pressed(particles, key)         key names as in sfml, e.g. "Space", "A", "Num1"
+//   on_key_released(particles, key)
+//   on_mouse_pressed(particles, button, x, y)   button names: "Left", "Right", "Middle"...
+//   on_mouse_released(particles, button, x, y)
+//   on_mouse_moved(particles, x, y)
+// and can call:
+//   time()                                 simulated time
+//   schedule(time, "name") / schedule_in(delay, "name")   calls name(particles) at that time,
+//                                          as an event of the simulation
+//   enable_system(id) / disable_system(id) / remove_system(id)   systems with an id in the scene
+//   pause() / resume() / stop() / set_steps_per_frame(steps)     of the app running the simulation
+//   random()                               uniform in [0, 1), seeded by the scene
+//   print(...)                             logged as info
+// The schedules, systems & app changes are applied by the event handler of the simulation: at
+// once in the scheduled functions, before the next step from the other ones.
+// Particles have len(), x(i), y(i), vx(i), vy(i), mass(i), set_position(i, x, y),
+// set_velocity(i, vx, vy), set_mass(i, mass), set_color(i, r, g, b, a), push(x, y, vx, vy, mass),
+// remove(i) (the last particle takes its index), clear(), scale_velocities(factor) and
+// add_velocity(vx, vy).
+//
+// `this` is a map kept between calls and reloads for the state of the script, it is only bound
+// in the functions above, not in the helpers they call.
+use log::{error, info, warn};
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT, INT};
+use sfml::window::Event;
+use std::{
+    cell::{Cell, RefCell},
+    fs, io, mem,
+    path::Path,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    rendering::{input::WindowEvent, renderer::InputCallback},
+    simulation::{
+        color::Color,
+        particles::Particles,
+        sim_events::{AppCommand, SimEvent, SimEventContext, SimEventQueue},
+        systems::System,
+        types::{Position, Time, Velocity},
+    },
+    utils::file_watcher::FileWatcher,
+};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// Particles handed to the script, moved in and out around each call
+#[derive(Clone)]
+struct ScriptParticles(Rc<RefCell<Particles>>);
+
+impl ScriptParticles {
+    fn index(&self, i: INT) -> ScriptResult<usize> {
+        let len = self.0.borrow().len();
+        if i >= 0 && (i as usize) < len {
+            Ok(i as usize)
+        } else {
+            Err(format!("particle index {} out of range (len {})", i, len).into())
+        }
+    }
+
+    fn get<T>(&mut self, i: INT, f: impl Fn(&Particles, usize) -> T) -> ScriptResult<T> {
+        let i = self.index(i)?;
+        Ok(f(&self.0.borrow(), i))
+    }
+
+    fn set(&mut self, i: INT, f: impl Fn(&mut Particles, usize)) -> ScriptResult<()> {
+        let i = self.index(i)?;
+        f(&mut self.0.borrow_mut(), i);
+        Ok(())
+    }
+}
+
+// Made by a script call, applied with the context of a simulation event
+enum ScriptRequest {
+    Schedule(Time, String),
+    Command(AppCommand),
+    SetSystemEnabled(String, bool),
+    RemoveSystem(String),
+}
+
+impl ScriptRequest {
+    fn apply(self, context: &mut SimEventContext, script: &Weak<RefCell<Script>>) {
+        let systems = &mut *context.systems;
+        match self {
+            ScriptRequest::Schedule(time, function) => {
+                context.schedule(script_event(script.clone(), time, function))
+            }
+            ScriptRequest::Command(command) => context.command(command),
+            ScriptRequest::SetSystemEnabled(id, enabled) => match systems.handle(&id) {
+                Some(handle) => {
+                    systems.set_enabled(handle, enabled);
+                }
+                None => warn!("Script: no system with id {}", id),
+            },
+            ScriptRequest::RemoveSystem(id) => match systems.handle(&id) {
+                Some(handle) => {
+                    systems.remove(handle);
+                }
+                None => warn!("Script: no system with id {}", id),
+            },
+        }
+    }
+}
+
+// Calls the function of the script at time, nothing if the script is gone
+fn script_event(script: Weak<RefCell<Script>>, time: Time, function: String) -> SimEvent {
+    SimEvent::new(
+        time,
+        Box::new(move |context| {
+            if let Some(script) = script.upgrade() {
+                script.borrow_mut().fire(context, &function);
+            }
+        }),
+    )
+}
+
+// Integers are accepted where floats are expected, Rhai doesn't convert them
+fn number(value: Dynamic) -> ScriptResult<FLOAT> {
+    match value.as_float() {
+        Ok(value) => Ok(value),
+        Err(type_name) => value
+            .as_int()
+            .map(|value| value as FLOAT)
+            .map_err(|_| format!("expected a number, got {}", type_name).into()),
+    }
+}
+
+fn register_particles(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptParticles>("Particles")
+        .register_fn("len", |p: &mut ScriptParticles| p.0.borrow().len() as INT)
+        .register_fn("x", |p: &mut ScriptParticles, i: INT| {
+            p.get(i, |particles, i| particles.positions[i].x)
+        })
+        .register_fn("y", |p: &mut ScriptParticles, i: INT| {
+            p.get(i, |particles, i| particles.positions[i].y)
+        })
+        .register_fn("vx", |p: &mut ScriptParticles, i: INT| {
+            p.get(i, |particles, i| particles.velocities[i].x)
+        })
+        .register_fn("vy", |p: &mut ScriptParticles, i: INT| {
+            p.get(i, |particles, i| particles.velocities[i].y)
+        })
+        .register_fn("mass", |p: &mut ScriptParticles, i: INT| {
+            p.get(i, |particles, i| particles.masses[i])
+        })
+        .register_fn(
+            "set_position",
+            |p: &mut ScriptParticles, i: INT, x: Dynamic, y: Dynamic| {
+                let position = Position::new(number(x)?, number(y)?);
+                p.set(i, |particles, i| particles.positions[i] = position)
+            },
+        )
+        .register_fn(
+            "set_velocity",
+            |p: &mut ScriptParticles, i: INT, vx: Dynamic, vy: Dynamic| {
+                let velocity = Velocity::new(number(vx)?, number(vy)?);
+                p.set(i, |particles, i| particles.velocities[i] = velocity)
+            },
+        )
+        .register_fn(
+            "set_mass",
+            |p: &mut ScriptParticles, i: INT, mass: Dynamic| {
+                let mass = number(mass)?;
+                p.set(i, |particles, i| particles.masses[i] = mass)
+            },
+        )
+        .register_fn(
+            "set_color",
+            |p: &mut ScriptParticles, i: INT, r: Dynamic, g: Dynamic, b: Dynamic, a: Dynamic| {
+                let color = Color::new(number(r)?, number(g)?, number(b)?, number(a)?);
+                p.set(i, |particles, i| particles.colors[i] = color)
+            },
+        )
+        .register_fn(
+            "push",
+            |p: &mut ScriptParticles,
+             x: Dynamic,
+             y: Dynamic,
+             vx: Dynamic,
+             vy: Dynamic,
+             mass: Dynamic|
+             -> ScriptResult<()> {
+                p.0.borrow_mut().push(
+                    Position::new(number(x)?, number(y)?),
+                    Velocity::new(number(vx)?, number(vy)?),
+                    number(mass)?,
+                    Color::WHITE,
+                );
+                Ok(())
+            },
+        )
+        .register_fn("remove", |p: &mut ScriptParticles, i: INT| {
+            p.set(i, |particles, i| particles.swap_remove(i))
+        })
+        .register_fn("clear", |p: &mut ScriptParticles| p.0.borrow_mut().clear())
+        .register_fn(
+            "scale_velocities",
+            |p: &mut ScriptParticles, factor: Dynamic| -> ScriptResult<()> {
+                let factor = number(factor)?;
+                for velocity in p.0.borrow_mut().velocities.iter_mut() {
+                    *velocity *= factor;
+                }
+                Ok(())
+            },
+        )
+        .register_fn(
+            "add_velocity",
+            |p: &mut ScriptParticles, vx: Dynamic, vy: Dynamic| -> ScriptResult<()> {
+                let offset = Velocity::new(number(vx)?, number(vy)?);
+                for velocity in p.0.borrow_mut().velocities.iter_mut() {
+                    *velocity += offset;
+                }
+                Ok(())
+            },
+        );
+}
+
+// Functions making requests, applied later with the context of an event
+fn register_requests(
+    engine: &mut Engine,
+    requests: &Rc<RefCell<Vec<ScriptRequest>>>,
+    time: &Rc<Cell<Time>>,
+) {
+    let system = |request: fn(String) -> ScriptRequest| {
+        let requests = requests.clone();
+        move |id: &str| requests.borrow_mut().push(request(id.to_string()))
+    };
+    engine
+        .register_fn(
+            "enable_system",
+            system(|id| ScriptRequest::SetSystemEnabled(id, true)),
+        )
+        .register_fn(
+            "disable_system",
+            system(|id| ScriptRequest::SetSystemEnabled(id, false)),
+        )
+        .register_fn("remove_system", system(ScriptRequest::RemoveSystem));
+
+    let command = |command: AppCommand| {
+        let requests = requests.clone();
+        move || requests.borrow_mut().push(ScriptRequest::Command(command))
+    };
+    engine
+        .register_fn("pause", command(AppCommand::Pause))
+        .register_fn("resume", command(AppCommand::Resume))
+        .register_fn("stop", command(AppCommand::Stop));
+    {
+        let requests = requests.clone();
+        engine.register_fn(
+            "set_steps_per_frame",
+            move |steps: INT| -> ScriptResult<()> {
+                let steps = usize::try_from(steps)
+                    .map_err(|_| format!("steps per frame must be positive, got {}", steps))?;
+                let command = AppCommand::SetStepsPerFrame(steps);
+                requests.borrow_mut().push(ScriptRequest::Command(command));
+                Ok(())
+            },
+        );
+    }
+
+    {
+        let requests = requests.clone();
+        engine.register_fn(
+            "schedule",
+            move |time: Dynamic, function: &str| -> ScriptResult<()> {
+                let request = ScriptRequest::Schedule(number(time)?, function.to_string());
+                requests.borrow_mut().push(request);
+                Ok(())
+            },
+        );
+    }
+    {
+        let (requests, time) = (requests.clone(), time.clone());
+        engine.register_fn(
+            "schedule_in",
+            move |delay: Dynamic, function: &str| -> ScriptResult<()> {
+                let request =
+                    ScriptRequest::Schedule(time.get() + number(delay)?, function.to_string());
+                requests.borrow_mut().push(request);
+                Ok(())
+            },
+        );
+    }
+}
+
+pub struct Script {
+    pub watcher: FileWatcher, // Of the script file
+    engine: Engine,
+    ast: AST,
+    state: Dynamic, // `this` of the functions
+    particles: ScriptParticles,
+    time: Rc<Cell<Time>>,
+    requests: Rc<RefCell<Vec<ScriptRequest>>>,
+    events: Option<SimEventQueue>, // Of the event handler applying the requests
+    this: Weak<RefCell<Script>>,   // Called back by the scheduled events
+    initialized: bool,
+    failed: bool, // After a runtime error, until the file is fixed
+}
+
+pub type SharedScript = Rc<RefCell<Script>>;
+
+impl Script {
+    pub fn from_file<P: AsRef<Path>>(path: P, rng: Pcg64Mcg) -> io::Result<Self> {
+        let path = path.as_ref();
+        let time = Rc::new(Cell::new(0.));
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let rng = Rc::new(RefCell::new(rng));
+
+        let mut engine = Engine::new();
+        register_particles(&mut engine);
+        register_requests(&mut engine, &requests, &time);
+        {
+            let time = time.clone();
+            engine.register_fn("time", move || time.get());
+        }
+        engine.register_fn("random", move || rng.borrow_mut().gen::<FLOAT>());
+        {
+            let name = path.display().to_string();
+            engine.on_print(move |text| info!("{}: {}", name, text));
+        }
+
+        let watcher = FileWatcher::new(path);
+        let ast = compile(&engine, path)?;
+        Ok(Self {
+            watcher,
+            engine,
+            ast,
+            state: Dynamic::from_map(Map::new()),
+            particles: ScriptParticles(Rc::new(RefCell::new(Particles::new_empty()))),
+            time,
+            requests,
+            events: None,
+            this: Weak::new(),
+            initialized: false,
+            failed: false,
+        })
+    }
+
+    // Without queue the script counts its own time and its requests are dropped
+    pub fn with_events(mut self, events: SimEventQueue) -> Self {
+        self.time.set(events.time());
+        self.events = Some(events);
+        self
+    }
+
+    pub fn shared(mut self) -> SharedScript {
+        Rc::new_cyclic(|this| {
+            self.this = this.clone();
+            RefCell::new(self)
+        })
+    }
+
+    pub fn time(&self) -> Time {
+        self.time.get()
+    }
+
+    // Keeps the previous version when the new one doesn't compile
+    pub fn reload_if_changed(&mut self) {
+        if !self.watcher.changed() {
+            return;
+        }
+
+        match compile(&self.engine, &self.watcher.path) {
+            Ok(ast) => {
+                info!("Reloaded {}", self.watcher.path.display());
+                self.ast = ast;
+                self.failed = false;
+            }
+            Err(e) => warn!("{} (keeping the previous version)", e),
+        }
+    }
+
+    fn has_function(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == arity)
+    }
+
+    // Errors are logged and stop the script until the file changes
+    fn call(&mut self, particles: &mut Particles, name: &str, mut args: Vec<Dynamic>) {
+        if self.failed || !self.has_function(name, args.len() + 1) {
+            return;
+        }
+
+        *self.particles.0.borrow_mut() = mem::replace(particles, Particles::new_empty());
+        args.insert(0, Dynamic::from(self.particles.clone()));
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            &self.ast,
+            name,
+            args,
+        );
+        *particles = self.particles.0.replace(Particles::new_empty());
+
+        if let Err(e) = result {
+            error!(
+                "{}: {}: {} (disabled until the file changes)",
+                self.watcher.path.display(),
+                name,
+                e
+            );
+            self.failed = true;
+        }
+    }
+
+    fn init(&mut self, particles: &mut Particles) {
+        if !self.initialized {
+            self.initialized = true;
+            self.call(particles, "init", vec![]);
+        }
+    }
+
+    pub fn update(&mut self, particles: &mut Particles, dt: Time) {
+        self.reload_if_changed();
+        self.advance_time(dt);
+        self.init(particles);
+        self.call(particles, "update", vec![Dynamic::from_float(dt)]);
+        self.send_requests();
+    }
+
+    // A scheduled function, its requests are applied at once
+    fn fire(&mut self, context: &mut SimEventContext, function: &str) {
+        self.reload_if_changed();
+        self.time.set(context.time);
+        self.init(context.particles);
+        self.call(context.particles, function, vec![]);
+        for request in self.requests.take() {
+            request.apply(context, &self.this);
+        }
+    }
+
+    // Time of the event handler, or counted by the updates without one
+    fn advance_time(&self, dt: Time) {
+        let time = match &self.events {
+            Some(events) => events.time(),
+            None => self.time.get() + dt,
+        };
+        self.time.set(time);
+    }
+
+    // Applied by an event firing in the next step of the handler
+    fn send_requests(&mut self) {
+        let mut requests = self.requests.take();
+        if requests.is_empty() {
+            return;
+        }
+        let Some(events) = &self.events else {
+            warn!(
+                "{}: no simulation events, requests ignored",
+                self.watcher.path.display()
+            );
+            return;
+        };
+        let this = self.this.clone();
+        events.add(SimEvent::new(
+            self.time.get(),
+            Box::new(move |context| {
+                for request in requests.drain(..) {
+                    request.apply(context, &this);
+                }
+            }),
+        ));
+    }
+
+    pub fn input(&mut self, particles: &mut Particles, events: &[WindowEvent]) {
+        self.reload_if_changed();
+        self.advance_time(0.);
+        self.init(particles);
+
+        for event in events {
+            let position = event
+                .position
+                .map(|p| [Dynamic::from_float(p.x), Dynamic::from_float(p.y)]);
+            let name = |value: &dyn std::fmt::Debug| Dynamic::from(format!("{:?}", value));
+
+            let (function, args) = match (event.original, position) {
+                (Event::KeyPressed { code, .. }, _) => ("on_key_pressed", vec![name(&code)]),
+                (Event::KeyReleased { code, .. }, _) => ("on_key_released", vec![name(&code)]),
+                (Event::MouseButtonPressed { button, .. }, Some([x, y])) => {
+                    ("on_mouse_pressed", vec![name(&button), x, y])
+                }
+                (Event::MouseButtonReleased { button, .. }, Some([x, y])) => {
+                    ("on_mouse_released", vec![name(&button), x, y])
+                }
+                (Event::MouseMoved { .. }, Some([x, y])) => ("on_mouse_moved", vec![x, y]),
+                _ => continue,
+            };
+            self.call(particles, function, args);
+        }
+        self.send_requests();
+    }
+}
+
+fn compile(engine: &Engine, path: &Path) -> io::Result<AST> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    engine.compile(source).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+// The update function of a script, the script may be shared with an input callback
+pub struct ScriptSystem {
+    pub script: SharedScript,
+}
+
+impl ScriptSystem {
+    pub fn new(script: SharedScript) -> Self {
+        Self { script }
+    }
+}
+
+impl System for ScriptSystem {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        self.script.borrow_mut().update(particles, dt);
+    }
+}
+
+// Calls the input handlers of the scripts after the given callback
+pub fn script_input_callback(
+    scripts: Vec<SharedScript>,
+    mut callback: InputCallback,
+) -> InputCallback {
+    Box::new(move |data, render_data, dt, events| {
+        callback(data, render_data, dt, events);
+        for script in &scripts {
+            script.borrow_mut().input(&mut data.sim.particles, events);
+        }
+    })
+}
@@ -1,11 +1,51 @@
-use nalgebra::Vector2;
+use std::f64::consts::PI;
+
+use nalgebra::{Matrix2, Vector2};
+use rand::{Rng, RngCore};
 use rayon::prelude::*;
 
-use super::types::{Length, Position};
+use super::types::{Angle, Length, Position, Scalar};
+
+#[inline]
+fn cross(a: Vector2<Scalar>, b: Vector2<Scalar>) -> Scalar {
+    a.x * b.y - a.y * b.x
+}
+
+// Resolution of the grid used to estimate areas without a closed form
+const AREA_ESTIMATE_RESOLUTION: usize = 256;
 
 pub trait Area: Sync {
     fn contain(&self, position: Position) -> bool;
 
+    // Smallest axis aligned rect containing the area (may be conservative)
+    fn bounding_box(&self) -> Rect;
+
+    // Surface of the area, estimated on a grid over the bounding box by default
+    fn area(&self) -> Scalar {
+        let bounding_box = self.bounding_box();
+        if !bounding_box.size.x.is_finite() || !bounding_box.size.y.is_finite() {
+            return Scalar::INFINITY;
+        }
+
+        let n = AREA_ESTIMATE_RESOLUTION;
+        let cell = bounding_box.size / n as Scalar;
+        let inside = (0..n * n)
+            .into_par_iter()
+            .filter(|i| {
+                let (x, y) = ((i % n) as Scalar + 0.5, (i / n) as Scalar + 0.5);
+                self.contain(bounding_box.position + Vector2::new(x * cell.x, y * cell.y))
+            })
+            .count();
+
+        inside as Scalar * cell.x * cell.y
+    }
+
+    // Exact uniform sample of a point in the area
+    // None when the area has no direct method (generic samplers fall back to rejection)
+    fn sample(&self, _rng: &mut dyn RngCore) -> Option<Position> {
+        None
+    }
+
     // WARNING: indices should always be in ascending order
     fn contains(&self, positions: &Vec<Position>, indices: &mut Vec<usize>) {
         let mut result_indices: Vec<usize> = positions
@@ -43,6 +83,16 @@ pub struct Rect {
     pub size: Vector2<Length>,
 }
 
+// Sides of the infinite rect start at -infinity and end at +infinity, not at -inf + inf = NaN
+#[inline]
+fn side_end(start: Scalar, length: Length) -> Scalar {
+    if length == Scalar::INFINITY {
+        length
+    } else {
+        start + length
+    }
+}
+
 impl Rect {
     pub fn new(position: Position, size: Vector2<Length>) -> Self {
         Self { position, size }
@@ -53,29 +103,80 @@ impl Rect {
     }
 
     pub fn top_right(&self) -> Position {
-        self.position + Vector2::new(self.size.x, 0.)
+        Vector2::new(side_end(self.position.x, self.size.x), self.position.y)
     }
 
     pub fn bottom_left(&self) -> Position {
-        self.position + Vector2::new(0., self.size.y)
+        Vector2::new(self.position.x, side_end(self.position.y, self.size.y))
     }
 
     pub fn bottom_right(&self) -> Position {
-        self.position + self.size
+        Vector2::new(
+            side_end(self.position.x, self.size.x),
+            side_end(self.position.y, self.size.y),
+        )
     }
 
     pub fn center(&self) -> Position {
         self.position + self.size / 2.
     }
+
+    pub fn from_corners(min: Position, max: Position) -> Self {
+        Self::new(min, max - min)
+    }
+
+    // Whole plane (bounding box of unbounded areas)
+    pub fn infinite() -> Self {
+        Self::new(
+            Vector2::repeat(Scalar::NEG_INFINITY),
+            Vector2::repeat(Scalar::INFINITY),
+        )
+    }
+
+    // Smallest rect containing both rects
+    pub fn union(&self, other: &Rect) -> Rect {
+        Self::from_corners(
+            self.top_left().inf(&other.top_left()),
+            self.bottom_right().sup(&other.bottom_right()),
+        )
+    }
+
+    // Overlap of both rects (empty rects have a zero size)
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let min = self.top_left().sup(&other.top_left());
+        let max = self.bottom_right().inf(&other.bottom_right());
+        Self::from_corners(min, max.sup(&min))
+    }
+
+    pub fn corners(&self) -> [Position; 4] {
+        [
+            self.top_left(),
+            self.top_right(),
+            self.bottom_left(),
+            self.bottom_right(),
+        ]
+    }
 }
 
 impl Area for Rect {
     #[inline]
     fn contain(&self, position: Position) -> bool {
         position.x >= self.position.x
-            && position.x <= self.position.x + self.size.x
+            && position.x <= side_end(self.position.x, self.size.x)
             && position.y >= self.position.y
-            && position.y <= self.position.y + self.size.y
+            && position.y <= side_end(self.position.y, self.size.y)
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.clone()
+    }
+
+    fn area(&self) -> Scalar {
+        self.size.x * self.size.y
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<Position> {
+        Some(self.position + self.size.component_mul(&Vector2::new(rng.gen(), rng.gen())))
     }
 }
 
@@ -98,6 +199,24 @@ impl Area for Disk {
     fn contain(&self, position: Position) -> bool {
         (position - self.position).norm_squared() <= self.radius_squared
     }
+
+    fn bounding_box(&self) -> Rect {
+        let radius = self.radius_squared.sqrt();
+        Rect::new(
+            self.position - Vector2::new(radius, radius),
+            Vector2::new(2. * radius, 2. * radius),
+        )
+    }
+
+    fn area(&self) -> Scalar {
+        PI * self.radius_squared
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<Position> {
+        let angle = rng.gen::<Scalar>() * 2. * PI;
+        let radius = (rng.gen::<Scalar>() * self.radius_squared).sqrt();
+        Some(self.position + Vector2::new(angle.cos(), angle.sin()) * radius)
+    }
 }
 
 pub struct Point {
@@ -115,4 +234,476 @@ impl Area for Point {
     fn contain(&self, position: Position) -> bool {
         position == self.position
     }
+
+    fn bounding_box(&self) -> Rect {
+        Rect::new(self.position, Vector2::zeros())
+    }
+
+    fn area(&self) -> Scalar {
+        0.
+    }
+
+    fn sample(&self, _rng: &mut dyn RngCore) -> Option<Position> {
+        Some(self.position)
+    }
+}
+
+// Simple polygon (convex or concave), even-odd rule for self intersecting ones
+pub struct Polygon {
+    vertices: Vec<Position>,
+
+    // Triangulation used for sampling (empty if the polygon could not be triangulated)
+    triangles: Vec<[Position; 3]>,
+    cumulative_areas: Vec<Scalar>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Position>) -> Self {
+        if vertices.len() < 3 {
+            panic!("Polygon needs at least 3 vertices");
+        }
+
+        let triangles = Self::triangulate(&vertices);
+        let cumulative_areas = triangles
+            .iter()
+            .scan(0., |total, [a, b, c]| {
+                *total += cross(b - a, c - a).abs() / 2.;
+                Some(*total)
+            })
+            .collect();
+
+        Self {
+            vertices,
+            triangles,
+            cumulative_areas,
+        }
+    }
+
+    pub fn vertices(&self) -> &[Position] {
+        &self.vertices
+    }
+
+    // Ear clipping, only valid for simple polygons (empty result otherwise)
+    fn triangulate(vertices: &[Position]) -> Vec<[Position; 3]> {
+        // Counter-clockwise order
+        let mut remaining: Vec<usize> = (0..vertices.len()).collect();
+        let signed_area: Scalar = (0..vertices.len())
+            .map(|i| cross(vertices[i], vertices[(i + 1) % vertices.len()]))
+            .sum();
+        if signed_area < 0. {
+            remaining.reverse();
+        }
+
+        let mut triangles = Vec::with_capacity(vertices.len() - 2);
+        while remaining.len() > 3 {
+            let n = remaining.len();
+            let ear = (0..n).find(|&i| {
+                let a = vertices[remaining[(i + n - 1) % n]];
+                let b = vertices[remaining[i]];
+                let c = vertices[remaining[(i + 1) % n]];
+                if cross(b - a, c - b) <= 0. {
+                    // Reflex vertex
+                    return false;
+                }
+                // No other vertex inside the ear
+                remaining.iter().all(|&j| {
+                    let p = vertices[j];
+                    p == a
+                        || p == b
+                        || p == c
+                        || cross(b - a, p - a) < 0.
+                        || cross(c - b, p - b) < 0.
+                        || cross(a - c, p - c) < 0.
+                })
+            });
+
+            match ear {
+                Some(i) => {
+                    triangles.push([
+                        vertices[remaining[(i + n - 1) % n]],
+                        vertices[remaining[i]],
+                        vertices[remaining[(i + 1) % n]],
+                    ]);
+                    remaining.remove(i);
+                }
+                None => return Vec::new(),
+            }
+        }
+        triangles.push([
+            vertices[remaining[0]],
+            vertices[remaining[1]],
+            vertices[remaining[2]],
+        ]);
+
+        triangles
+    }
+
+    // Regular polygon inscribed in a circle, first vertex at the given angle
+    pub fn regular(center: Position, radius: Length, sides: usize, angle: Angle) -> Self {
+        Self::new(
+            (0..sides)
+                .map(|i| {
+                    let a = angle + 2. * PI * i as Scalar / sides as Scalar;
+                    center + Vector2::new(a.cos(), a.sin()) * radius
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Area for Polygon {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        // Crossing number of a ray going towards +x
+        let mut inside = false;
+        let mut j = self.vertices.len() - 1;
+        for i in 0..self.vertices.len() {
+            let (a, b) = (self.vertices[i], self.vertices[j]);
+            if (a.y > position.y) != (b.y > position.y)
+                && position.x < a.x + (position.y - a.y) * (b.x - a.x) / (b.y - a.y)
+            {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+
+    fn bounding_box(&self) -> Rect {
+        let mut min = self.vertices[0];
+        let mut max = self.vertices[0];
+        for vertex in self.vertices.iter() {
+            min = min.inf(vertex);
+            max = max.sup(vertex);
+        }
+        Rect::from_corners(min, max)
+    }
+
+    // Shoelace formula (exact for simple polygons)
+    fn area(&self) -> Scalar {
+        let mut sum = 0.;
+        let mut j = self.vertices.len() - 1;
+        for i in 0..self.vertices.len() {
+            let (a, b) = (self.vertices[j], self.vertices[i]);
+            sum += a.x * b.y - b.x * a.y;
+            j = i;
+        }
+        sum.abs() / 2.
+    }
+
+    // Pick a triangle by area, then a uniform point in it
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<Position> {
+        let total = *self.cumulative_areas.last()?;
+        let target = rng.gen::<Scalar>() * total;
+        let index = self
+            .cumulative_areas
+            .partition_point(|&area| area < target)
+            .min(self.triangles.len() - 1);
+        let [a, b, c] = self.triangles[index];
+
+        let (mut u, mut v) = (rng.gen::<Scalar>(), rng.gen::<Scalar>());
+        if u + v > 1. {
+            (u, v) = (1. - u, 1. - v);
+        }
+        Some(a + (b - a) * u + (c - a) * v)
+    }
+}
+
+// Ring between two circles
+pub struct Annulus {
+    pub position: Position,
+    pub inner_radius: Length,
+    pub outer_radius: Length,
+}
+
+impl Annulus {
+    pub fn new(position: Position, inner_radius: Length, outer_radius: Length) -> Self {
+        if inner_radius > outer_radius {
+            panic!("Annulus inner radius is larger than its outer radius");
+        }
+        Self {
+            position,
+            inner_radius,
+            outer_radius,
+        }
+    }
+}
+
+impl Area for Annulus {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        let distance_squared = (position - self.position).norm_squared();
+        distance_squared >= self.inner_radius * self.inner_radius
+            && distance_squared <= self.outer_radius * self.outer_radius
+    }
+
+    fn bounding_box(&self) -> Rect {
+        let offset = Vector2::new(self.outer_radius, self.outer_radius);
+        Rect::new(self.position - offset, offset * 2.)
+    }
+
+    fn area(&self) -> Scalar {
+        PI * (self.outer_radius * self.outer_radius - self.inner_radius * self.inner_radius)
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<Position> {
+        let inner_squared = self.inner_radius * self.inner_radius;
+        let outer_squared = self.outer_radius * self.outer_radius;
+        let angle = rng.gen::<Scalar>() * 2. * PI;
+        let radius = (inner_squared + rng.gen::<Scalar>() * (outer_squared - inner_squared)).sqrt();
+        Some(self.position + Vector2::new(angle.cos(), angle.sin()) * radius)
+    }
+}
+
+// Ellipse with semi axes radii, rotated by angle (radians, counter-clockwise)
+pub struct Ellipse {
+    pub position: Position,
+    pub radii: Vector2<Length>,
+    pub angle: Angle,
+}
+
+impl Ellipse {
+    pub fn new(position: Position, radii: Vector2<Length>, angle: Angle) -> Self {
+        Self {
+            position,
+            radii,
+            angle,
+        }
+    }
+}
+
+impl Area for Ellipse {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        let (sin, cos) = self.angle.sin_cos();
+        let relative = position - self.position;
+        // Coordinates along the axes of the ellipse
+        let u = (relative.x * cos + relative.y * sin) / self.radii.x;
+        let v = (-relative.x * sin + relative.y * cos) / self.radii.y;
+        u * u + v * v <= 1.
+    }
+
+    fn bounding_box(&self) -> Rect {
+        let (sin, cos) = self.angle.sin_cos();
+        let half_size = Vector2::new(
+            ((self.radii.x * cos).powi(2) + (self.radii.y * sin).powi(2)).sqrt(),
+            ((self.radii.x * sin).powi(2) + (self.radii.y * cos).powi(2)).sqrt(),
+        );
+        Rect::new(self.position - half_size, half_size * 2.)
+    }
+
+    fn area(&self) -> Scalar {
+        PI * self.radii.x * self.radii.y
+    }
+
+    // Uniform point in the unit disk, stretched along the axes
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<Position> {
+        let angle = rng.gen::<Scalar>() * 2. * PI;
+        let radius = rng.gen::<Scalar>().sqrt();
+        let local = Vector2::new(
+            angle.cos() * radius * self.radii.x,
+            angle.sin() * radius * self.radii.y,
+        );
+        let (sin, cos) = self.angle.sin_cos();
+        Some(
+            self.position
+                + Vector2::new(local.x * cos - local.y * sin, local.x * sin + local.y * cos),
+        )
+    }
+}
+
+// Points contained in any of the areas
+pub struct Union {
+    pub areas: Vec<Box<dyn Area>>,
+}
+
+impl Union {
+    pub fn new(areas: Vec<Box<dyn Area>>) -> Self {
+        Self { areas }
+    }
+}
+
+impl Area for Union {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        self.areas.iter().any(|area| area.contain(position))
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.areas
+            .iter()
+            .map(|area| area.bounding_box())
+            .reduce(|acc, bounding_box| acc.union(&bounding_box))
+            .unwrap_or(Rect::new(Vector2::zeros(), Vector2::zeros()))
+    }
+}
+
+// Points contained in all of the areas
+pub struct Intersection {
+    pub areas: Vec<Box<dyn Area>>,
+}
+
+impl Intersection {
+    pub fn new(areas: Vec<Box<dyn Area>>) -> Self {
+        if areas.is_empty() {
+            panic!("Intersection needs at least one area");
+        }
+        Self { areas }
+    }
+}
+
+impl Area for Intersection {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        self.areas.iter().all(|area| area.contain(position))
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.areas
+            .iter()
+            .map(|area| area.bounding_box())
+            .reduce(|acc, bounding_box| acc.intersection(&bounding_box))
+            .unwrap()
+    }
+}
+
+// Points of area not contained in removed
+pub struct Difference {
+    pub area: Box<dyn Area>,
+    pub removed: Box<dyn Area>,
+}
+
+impl Difference {
+    pub fn new(area: Box<dyn Area>, removed: Box<dyn Area>) -> Self {
+        Self { area, removed }
+    }
+}
+
+impl Area for Difference {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        self.area.contain(position) && !self.removed.contain(position)
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.area.bounding_box()
+    }
+}
+
+// Every point outside of the area (unbounded)
+pub struct Complement {
+    pub area: Box<dyn Area>,
+}
+
+impl Complement {
+    pub fn new(area: Box<dyn Area>) -> Self {
+        Self { area }
+    }
+}
+
+impl Area for Complement {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        !self.area.contain(position)
+    }
+
+    fn bounding_box(&self) -> Rect {
+        Rect::infinite()
+    }
+
+    fn area(&self) -> Scalar {
+        Scalar::INFINITY
+    }
+}
+
+// Affine transform of an area: position = matrix * local + translation
+// Transforms compose in call order, e.g. Transformed::new(area).scale(..).rotate(..).translate(..)
+pub struct Transformed {
+    pub area: Box<dyn Area>,
+    matrix: Matrix2<Scalar>,
+    inverse: Matrix2<Scalar>,
+    translation: Vector2<Scalar>,
+}
+
+impl Transformed {
+    pub fn new(area: Box<dyn Area>) -> Self {
+        Self {
+            area,
+            matrix: Matrix2::identity(),
+            inverse: Matrix2::identity(),
+            translation: Vector2::zeros(),
+        }
+    }
+
+    // Applies matrix after the current transform
+    pub fn transform(mut self, matrix: Matrix2<Scalar>) -> Self {
+        self.matrix = matrix * self.matrix;
+        self.translation = matrix * self.translation;
+        self.inverse = self
+            .matrix
+            .try_inverse()
+            .expect("Area transform is not invertible");
+        self
+    }
+
+    pub fn translate(mut self, offset: Vector2<Scalar>) -> Self {
+        self.translation += offset;
+        self
+    }
+
+    // Counter-clockwise rotation around the origin
+    pub fn rotate(self, angle: Angle) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        self.transform(Matrix2::new(cos, -sin, sin, cos))
+    }
+
+    // Scale relative to the origin
+    pub fn scale(self, factors: Vector2<Scalar>) -> Self {
+        self.transform(Matrix2::new(factors.x, 0., 0., factors.y))
+    }
+
+    pub fn rotate_around(self, angle: Angle, center: Position) -> Self {
+        self.translate(-center).rotate(angle).translate(center)
+    }
+
+    pub fn scale_around(self, factors: Vector2<Scalar>, center: Position) -> Self {
+        self.translate(-center).scale(factors).translate(center)
+    }
+}
+
+impl Area for Transformed {
+    #[inline]
+    fn contain(&self, position: Position) -> bool {
+        self.area
+            .contain(self.inverse * (position - self.translation))
+    }
+
+    // Bounding box of the transformed bounding box of the area
+    fn bounding_box(&self) -> Rect {
+        let local = self.area.bounding_box();
+        if !local.size.x.is_finite() || !local.size.y.is_finite() {
+            return Rect::infinite();
+        }
+
+        let corners = local
+            .corners()
+            .map(|corner| self.matrix * corner + self.translation);
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in corners.iter() {
+            min = min.inf(corner);
+            max = max.sup(corner);
+        }
+        Rect::from_corners(min, max)
+    }
+
+    fn area(&self) -> Scalar {
+        self.area.area() * self.matrix.determinant().abs()
+    }
+
+    // Affine transforms keep uniform distributions uniform
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<Position> {
+        let local = self.area.sample(rng)?;
+        Some(self.matrix * local + self.translation)
+    }
 }
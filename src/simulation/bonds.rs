@@ -0,0 +1,333 @@
+use nalgebra::Vector2;
+
+use super::{
+    areas::{Disk, Rect},
+    color::Color,
+    forces::Force,
+    generators::{
+        ConstantGenerator, Generator, IterGenerator, UniformDiskPointsGenerator,
+        UniformRectPointsGenerator,
+    },
+    neighbours::NeighbourGrid,
+    particles::{GeneratorFactory, ParticleFactory, Particles},
+    systems::System,
+    types::{Energy, Force as ForceType, Length, Mass, Position, Scalar, Time, Velocity},
+};
+
+// WARNING: bonds reference particles by index, removing particles (swap_remove) breaks them
+#[derive(Clone)]
+pub struct Spring {
+    pub i: usize,
+    pub j: usize,
+    pub rest_length: Length,
+    pub stiffness: Scalar,
+    pub damping: Scalar,
+}
+
+impl Spring {
+    pub fn new(
+        i: usize,
+        j: usize,
+        rest_length: Length,
+        stiffness: Scalar,
+        damping: Scalar,
+    ) -> Self {
+        Self {
+            i,
+            j,
+            rest_length,
+            stiffness,
+            damping,
+        }
+    }
+
+    // Force applied on particle i (particle j receives the opposite)
+    #[inline]
+    pub fn calc_force(
+        &self,
+        pos1: Position,
+        pos2: Position,
+        vel1: Velocity,
+        vel2: Velocity,
+    ) -> ForceType {
+        let distance_v = pos2 - pos1;
+        let distance = distance_v.norm();
+
+        if distance == 0.0 {
+            return ForceType::zeros();
+        }
+
+        let direction = distance_v / distance;
+        let stretch = distance - self.rest_length;
+        let relative_speed = (vel2 - vel1).dot(&direction);
+
+        (self.stiffness * stretch + self.damping * relative_speed) * direction
+    }
+
+    // Elastic energy, the damping only dissipates
+    #[inline]
+    pub fn calc_potential(&self, pos1: Position, pos2: Position) -> Energy {
+        let stretch = (pos2 - pos1).norm() - self.rest_length;
+        0.5 * self.stiffness * stretch * stretch
+    }
+}
+
+pub struct Springs {
+    pub springs: Vec<Spring>,
+}
+
+impl Springs {
+    pub fn new(springs: Vec<Spring>) -> Self {
+        Self { springs }
+    }
+}
+
+impl Force for Springs {
+    fn apply(&mut self, particles: &Particles, forces: &mut Vec<ForceType>) {
+        let len = particles.len();
+        for spring in self.springs.iter() {
+            if spring.i >= len || spring.j >= len {
+                continue;
+            }
+
+            let force = spring.calc_force(
+                particles.positions[spring.i],
+                particles.positions[spring.j],
+                particles.velocities[spring.i],
+                particles.velocities[spring.j],
+            );
+
+            forces[spring.i] += force;
+            forces[spring.j] -= force;
+        }
+    }
+
+    fn potential_energy(&mut self, particles: &Particles) -> Option<Energy> {
+        let len = particles.len();
+        let energy = self
+            .springs
+            .iter()
+            .filter(|spring| spring.i < len && spring.j < len)
+            .map(|spring| {
+                spring.calc_potential(particles.positions[spring.i], particles.positions[spring.j])
+            })
+            .sum();
+        Some(energy)
+    }
+}
+
+#[derive(Clone)]
+pub struct DistanceConstraint {
+    pub i: usize,
+    pub j: usize,
+    pub length: Length,
+}
+
+impl DistanceConstraint {
+    pub fn new(i: usize, j: usize, length: Length) -> Self {
+        Self { i, j, length }
+    }
+
+    pub fn from_springs(springs: &[Spring]) -> Vec<Self> {
+        springs
+            .iter()
+            .map(|spring| Self::new(spring.i, spring.j, spring.rest_length))
+            .collect()
+    }
+}
+
+// Rigid distance constraints solved iteratively (position based dynamics)
+// Must run after the velocity integrator: position corrections are also applied to velocities
+pub struct DistanceConstraints {
+    pub constraints: Vec<DistanceConstraint>,
+    pub iterations: usize,
+    pub stiffness: Scalar, // Fraction of the error corrected per iteration (0: none, 1: rigid)
+    corrections: Vec<Position>,
+}
+
+impl DistanceConstraints {
+    pub fn new(constraints: Vec<DistanceConstraint>, iterations: usize, stiffness: Scalar) -> Self {
+        Self {
+            constraints,
+            iterations,
+            stiffness,
+            corrections: Vec::new(),
+        }
+    }
+}
+
+impl System for DistanceConstraints {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        let len = particles.len();
+        self.corrections.clear();
+        self.corrections.resize(len, Vector2::zeros());
+
+        for _ in 0..self.iterations {
+            for constraint in self.constraints.iter() {
+                let (i, j) = (constraint.i, constraint.j);
+                if i >= len || j >= len {
+                    continue;
+                }
+
+                let distance_v = particles.positions[j] - particles.positions[i];
+                let distance = distance_v.norm();
+                if distance == 0.0 {
+                    continue;
+                }
+
+                // Split the correction between particles based on their inverse masses
+                let w1 = 1. / particles.masses[i];
+                let w2 = 1. / particles.masses[j];
+                let error = (distance - constraint.length) / (w1 + w2);
+                let correction = (self.stiffness * error / distance) * distance_v;
+
+                particles.positions[i] += w1 * correction;
+                particles.positions[j] -= w2 * correction;
+                self.corrections[i] += w1 * correction;
+                self.corrections[j] -= w2 * correction;
+            }
+        }
+
+        // Keep velocities consistent with the corrected positions, not possible without time step
+        if dt <= 0. {
+            return;
+        }
+        for (velocity, correction) in particles.velocities.iter_mut().zip(&self.corrections) {
+            *velocity += correction / dt;
+        }
+    }
+}
+
+// Create springs between all particles (starting from index offset) closer than distance
+// Rest lengths are the current distances
+pub fn connect_within(
+    positions: &[Position],
+    offset: usize,
+    distance: Length,
+    stiffness: Scalar,
+    damping: Scalar,
+) -> Vec<Spring> {
+    let positions = &positions[offset..];
+    if positions.is_empty() {
+        return Vec::new();
+    }
+
+    // Bounding box of the particles
+    let mut min = positions[0];
+    let mut max = positions[0];
+    for position in positions.iter() {
+        min = min.inf(position);
+        max = max.sup(position);
+    }
+
+    let mut grid = NeighbourGrid::new(Rect::new(min, max - min), distance);
+    grid.insert_particles(positions);
+
+    let mut springs = Vec::new();
+    for (i, position) in positions.iter().enumerate() {
+        grid.for_each_neighbour(positions, *position, distance, |j, _, distance_squared| {
+            // Only keep one spring per pair
+            if j > i {
+                springs.push(Spring::new(
+                    offset + i,
+                    offset + j,
+                    distance_squared.sqrt(),
+                    stiffness,
+                    damping,
+                ));
+            }
+        });
+    }
+    springs
+}
+
+// Ratio between the link distance and the mean spacing of particles
+const LINK_FACTOR: Scalar = 1.5;
+
+pub struct SoftBodyBuilder {
+    pub velocity: Velocity,
+    pub mass: Mass,
+    pub color: Color,
+    pub stiffness: Scalar,
+    pub damping: Scalar,
+}
+
+impl SoftBodyBuilder {
+    pub fn new(
+        velocity: Velocity,
+        mass: Mass,
+        color: Color,
+        stiffness: Scalar,
+        damping: Scalar,
+    ) -> Self {
+        Self {
+            velocity,
+            mass,
+            color,
+            stiffness,
+            damping,
+        }
+    }
+
+    fn factory(&self, position_generator: Box<dyn Generator<Position>>) -> GeneratorFactory {
+        GeneratorFactory::new(
+            position_generator,
+            Box::new(ConstantGenerator::new(self.velocity)),
+            Box::new(ConstantGenerator::new(self.mass)),
+            Box::new(ConstantGenerator::new(self.color)),
+        )
+    }
+
+    // Create n particles with the factory and link all particles closer than link_distance
+    pub fn build(
+        &self,
+        factory: &mut dyn ParticleFactory,
+        n: usize,
+        link_distance: Length,
+        particles: &mut Particles,
+    ) -> Vec<Spring> {
+        let offset = particles.len();
+        factory.create(n, particles);
+        connect_within(
+            &particles.positions,
+            offset,
+            link_distance,
+            self.stiffness,
+            self.damping,
+        )
+    }
+
+    // Chain of n particles from start to end
+    pub fn rope(
+        &self,
+        start: Position,
+        end: Position,
+        n: usize,
+        particles: &mut Particles,
+    ) -> Vec<Spring> {
+        let step = (end - start) / (n.max(2) - 1) as Scalar;
+        let positions = (0..n).map(move |i| start + step * i as Scalar);
+
+        let offset = particles.len();
+        self.factory(Box::new(IterGenerator::new(positions)))
+            .create(n, particles);
+
+        (offset..offset + n.saturating_sub(1))
+            .map(|i| Spring::new(i, i + 1, step.norm(), self.stiffness, self.damping))
+            .collect()
+    }
+
+    // Grid of n particles linked to their direct and diagonal neighbours
+    pub fn cloth(&self, rect: Rect, n: usize, particles: &mut Particles) -> Vec<Spring> {
+        let spacing = (rect.size.x * rect.size.y / n as Scalar).sqrt();
+        let mut factory = self.factory(Box::new(UniformRectPointsGenerator::new(rect)));
+        self.build(&mut factory, n, LINK_FACTOR * spacing, particles)
+    }
+
+    // Concentric rings of n particles linked to their neighbours
+    pub fn soft_disk(&self, disk: Disk, n: usize, particles: &mut Particles) -> Vec<Spring> {
+        let spacing = (std::f64::consts::PI * disk.radius_squared / n as Scalar).sqrt();
+        let mut factory = self.factory(Box::new(UniformDiskPointsGenerator::new(disk)));
+        self.build(&mut factory, n, LINK_FACTOR * spacing, particles)
+    }
+}
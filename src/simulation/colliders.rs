@@ -0,0 +1,333 @@
+use nalgebra::Vector2;
+use rayon::prelude::*;
+
+use super::{
+    particles::Particles,
+    systems::System,
+    tunables::Tunables,
+    types::{Length, Position, Scalar, Time},
+};
+
+// Distance particles are pushed away from a collider after a bounce
+const SKIN: Length = 1e-6;
+
+#[inline]
+fn cross(a: Vector2<Scalar>, b: Vector2<Scalar>) -> Scalar {
+    a.x * b.y - a.y * b.x
+}
+
+#[derive(Clone)]
+pub enum Collider {
+    Segment {
+        start: Position,
+        end: Position,
+    },
+    // Solid disk, or a container keeping particles inside
+    Circle {
+        center: Position,
+        radius: Length,
+        inside: bool,
+    },
+}
+
+impl Collider {
+    pub fn bounding_box(&self) -> (Position, Position) {
+        match self {
+            Collider::Segment { start, end } => (start.inf(end), start.sup(end)),
+            Collider::Circle { center, radius, .. } => {
+                let offset = Vector2::new(*radius, *radius);
+                (center - offset, center + offset)
+            }
+        }
+    }
+
+    // Earliest hit of the path from -> to: (fraction of the path, normal facing the particle)
+    #[inline]
+    pub fn sweep(&self, from: Position, to: Position) -> Option<(Scalar, Vector2<Scalar>)> {
+        let path = to - from;
+
+        match self {
+            Collider::Segment { start, end } => {
+                let segment = end - start;
+                let denom = cross(path, segment);
+                if denom == 0. {
+                    // Parallel
+                    return None;
+                }
+
+                let offset = start - from;
+                let t = cross(offset, segment) / denom;
+                let u = cross(offset, path) / denom;
+                if !(0. ..=1.).contains(&t) || !(0. ..=1.).contains(&u) {
+                    return None;
+                }
+
+                let mut normal = Vector2::new(-segment.y, segment.x).normalize();
+                if normal.dot(&path) > 0. {
+                    normal = -normal;
+                }
+                Some((t, normal))
+            }
+            Collider::Circle {
+                center,
+                radius,
+                inside,
+            } => {
+                let f = from - center;
+                let a = path.norm_squared();
+                let b = 2. * f.dot(&path);
+                let c = f.norm_squared() - radius * radius;
+                let discriminant = b * b - 4. * a * c;
+                if a == 0. || discriminant < 0. {
+                    return None;
+                }
+
+                let sqrt = discriminant.sqrt();
+                let t = if *inside {
+                    // Leaving the container
+                    if c > 0. {
+                        return None;
+                    }
+                    (-b + sqrt) / (2. * a)
+                } else {
+                    // Entering the disk
+                    if c < 0. {
+                        return None;
+                    }
+                    (-b - sqrt) / (2. * a)
+                };
+                if !(0. ..=1.).contains(&t) {
+                    return None;
+                }
+
+                let normal = (from + path * t - center) / *radius;
+                Some((t, if *inside { -normal } else { normal }))
+            }
+        }
+    }
+}
+
+// Uniform grid referencing the colliders overlapping each cell
+struct BroadPhase {
+    min: Position,
+    max: Position,
+    cell_size: Length,
+    nx: usize,
+    ny: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl BroadPhase {
+    fn new(colliders: &[Collider], cell_size: Length) -> Self {
+        let mut min = Vector2::repeat(Scalar::INFINITY);
+        let mut max = Vector2::repeat(Scalar::NEG_INFINITY);
+        for collider in colliders.iter() {
+            let (c_min, c_max) = collider.bounding_box();
+            min = min.inf(&c_min);
+            max = max.sup(&c_max);
+        }
+
+        let size = max - min;
+        let nx = ((size.x / cell_size).ceil() as usize).max(1);
+        let ny = ((size.y / cell_size).ceil() as usize).max(1);
+
+        let mut broad_phase = Self {
+            min,
+            max,
+            cell_size,
+            nx,
+            ny,
+            cells: vec![Vec::new(); nx * ny],
+        };
+
+        for (i, collider) in colliders.iter().enumerate() {
+            let (c_min, c_max) = collider.bounding_box();
+            let (x_min, y_min) = broad_phase.cell_coords(c_min);
+            let (x_max, y_max) = broad_phase.cell_coords(c_max);
+            for y in y_min..=y_max {
+                for x in x_min..=x_max {
+                    broad_phase.cells[y * nx + x].push(i);
+                }
+            }
+        }
+
+        broad_phase
+    }
+
+    #[inline]
+    fn cell_coords(&self, position: Position) -> (usize, usize) {
+        let relative = (position - self.min) / self.cell_size;
+        let x = (relative.x.floor().max(0.) as usize).min(self.nx - 1);
+        let y = (relative.y.floor().max(0.) as usize).min(self.ny - 1);
+        (x, y)
+    }
+
+    #[inline]
+    fn earliest_hit(
+        &self,
+        colliders: &[Collider],
+        from: Position,
+        to: Position,
+    ) -> Option<(Scalar, Vector2<Scalar>)> {
+        let (p_min, p_max) = (from.inf(&to), from.sup(&to));
+        if p_max.x < self.min.x
+            || p_max.y < self.min.y
+            || p_min.x > self.max.x
+            || p_min.y > self.max.y
+        {
+            return None;
+        }
+
+        let (x_min, y_min) = self.cell_coords(p_min);
+        let (x_max, y_max) = self.cell_coords(p_max);
+
+        let mut earliest: Option<(Scalar, Vector2<Scalar>)> = None;
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                for &i in self.cells[y * self.nx + x].iter() {
+                    if let Some((t, normal)) = colliders[i].sweep(from, to) {
+                        if earliest.is_none_or(|(earliest_t, _)| t < earliest_t) {
+                            earliest = Some((t, normal));
+                        }
+                    }
+                }
+            }
+        }
+        earliest
+    }
+}
+
+// Static collider geometry particles bounce on
+// Collisions are detected along the path travelled during the last step (position - velocity * dt)
+// so this system must run right after the velocity integrator (or first in the next step)
+pub struct Colliders {
+    colliders: Vec<Collider>,
+    pub restitution: Scalar,
+    pub friction: Scalar,
+    pub max_bounces: usize,
+    cell_size: Length,
+    broad_phase: Option<BroadPhase>,
+}
+
+impl Colliders {
+    pub fn new(
+        restitution: Scalar,
+        friction: Scalar,
+        max_bounces: usize,
+        cell_size: Length,
+    ) -> Self {
+        assert!(
+            cell_size > 0.,
+            "Colliders cell size must be strictly positive"
+        );
+        Self {
+            colliders: Vec::new(),
+            restitution,
+            friction,
+            max_bounces,
+            cell_size,
+            broad_phase: None,
+        }
+    }
+
+    pub fn colliders(&self) -> &Vec<Collider> {
+        &self.colliders
+    }
+
+    pub fn add(&mut self, collider: Collider) {
+        self.colliders.push(collider);
+        self.broad_phase = None;
+    }
+
+    pub fn add_segment(&mut self, start: Position, end: Position) {
+        self.add(Collider::Segment { start, end });
+    }
+
+    pub fn add_polyline(&mut self, points: &[Position]) {
+        for pair in points.windows(2) {
+            self.add_segment(pair[0], pair[1]);
+        }
+    }
+
+    // Closed polyline, convex or concave
+    pub fn add_polygon(&mut self, points: &[Position]) {
+        self.add_polyline(points);
+        if points.len() > 2 {
+            self.add_segment(points[points.len() - 1], points[0]);
+        }
+    }
+
+    pub fn add_circle(&mut self, center: Position, radius: Length) {
+        self.add(Collider::Circle {
+            center,
+            radius,
+            inside: false,
+        });
+    }
+
+    pub fn add_circle_container(&mut self, center: Position, radius: Length) {
+        self.add(Collider::Circle {
+            center,
+            radius,
+            inside: true,
+        });
+    }
+
+    #[inline]
+    fn respond(&self, motion: Vector2<Scalar>, normal: Vector2<Scalar>) -> Vector2<Scalar> {
+        let normal_part = motion.dot(&normal) * normal;
+        let tangent_part = motion - normal_part;
+        tangent_part * (1. - self.friction) - normal_part * self.restitution
+    }
+}
+
+impl System for Colliders {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        if self.colliders.is_empty() {
+            return;
+        }
+        if self.broad_phase.is_none() {
+            self.broad_phase = Some(BroadPhase::new(&self.colliders, self.cell_size));
+        }
+
+        let this = &*self;
+        let broad_phase = this.broad_phase.as_ref().unwrap();
+
+        particles
+            .positions
+            .par_iter_mut()
+            .zip(particles.velocities.par_iter_mut())
+            .for_each(|(position, velocity)| {
+                let mut from = *position - *velocity * dt;
+                let mut to = *position;
+
+                for bounce in 0..=this.max_bounces {
+                    let (t, normal) = match broad_phase.earliest_hit(&this.colliders, from, to) {
+                        Some(hit) => hit,
+                        None => break,
+                    };
+
+                    let hit_point = from + (to - from) * t + normal * SKIN;
+                    *velocity = this.respond(*velocity, normal);
+
+                    if bounce == this.max_bounces {
+                        // Out of bounces: stop at the collider
+                        to = hit_point;
+                        break;
+                    }
+
+                    // Reflect the remaining motion
+                    let remaining = (to - from) * (1. - t);
+                    from = hit_point;
+                    to = hit_point + this.respond(remaining, normal);
+                }
+
+                *position = to;
+            });
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("restitution", &mut self.restitution);
+        tunables.scalar("friction", &mut self.friction);
+    }
+}
@@ -0,0 +1,361 @@
+// Global quantities telling whether a run is physically sane: energies, temperature, momentum,
+// angular momentum (around the origin), center of mass & virial ratio. The Diagnostics system samples
+// them each time it is updated (give it a period in the system list), records the series, and
+// warns when the conserved ones drift away from their value at the first sample.
+use log::{info, warn};
+use nalgebra::Vector2;
+use rayon::prelude::*;
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use super::{
+    forces::Force,
+    particles::Particles,
+    systems::System,
+    tunables::Tunables,
+    types::{Energy, Mass, Position, Scalar, Temperature, Time, Velocity},
+};
+
+pub fn total_mass(particles: &Particles) -> Mass {
+    particles.masses.par_iter().sum()
+}
+
+pub fn kinetic_energy(particles: &Particles) -> Energy {
+    particles
+        .velocities
+        .par_iter()
+        .zip(particles.masses.par_iter())
+        .map(|(velocity, mass)| 0.5 * mass * velocity.norm_squared())
+        .sum()
+}
+
+pub fn momentum(particles: &Particles) -> Vector2<Scalar> {
+    particles
+        .velocities
+        .par_iter()
+        .zip(particles.masses.par_iter())
+        .map(|(velocity, mass)| velocity * *mass)
+        .sum()
+}
+
+// Zero without mass
+pub fn center_of_mass_velocity(particles: &Particles) -> Velocity {
+    let mass = total_mass(particles);
+    if mass == 0. {
+        return Velocity::zeros();
+    }
+    momentum(particles) / mass
+}
+
+// Of the motion relative to the center of mass: sum of m |v - v_cm|^2 / 2 per degree of freedom,
+// 2 per particle minus the 2 of the center of mass. Zero for less than 2 particles. Only reads the
+// velocities & masses, the columns concurrent thermostats get.
+pub fn kinetic_temperature(particles: &Particles) -> Temperature {
+    let count = particles.masses.len();
+    if count < 2 {
+        return 0.;
+    }
+
+    let center = center_of_mass_velocity(particles);
+    let energy: Energy = particles
+        .velocities
+        .par_iter()
+        .zip(particles.masses.par_iter())
+        .map(|(velocity, mass)| 0.5 * mass * (velocity - center).norm_squared())
+        .sum();
+    energy / (count - 1) as Scalar
+}
+
+// Around the origin, positive counterclockwise
+pub fn angular_momentum(particles: &Particles) -> Scalar {
+    particles
+        .positions
+        .par_iter()
+        .zip(particles.velocities.par_iter())
+        .zip(particles.masses.par_iter())
+        .map(|((position, velocity), mass)| mass * position.perp(velocity))
+        .sum()
+}
+
+// The origin without mass
+pub fn center_of_mass(particles: &Particles) -> Position {
+    let mass = total_mass(particles);
+    if mass == 0. {
+        return Position::zeros();
+    }
+
+    let weighted: Position = particles
+        .positions
+        .par_iter()
+        .zip(particles.masses.par_iter())
+        .map(|(position, mass)| position * *mass)
+        .sum();
+    weighted / mass
+}
+
+// Sum of the forces deriving from a potential, None if none does
+pub fn potential_energy(particles: &Particles, forces: &mut [Box<dyn Force>]) -> Option<Energy> {
+    forces
+        .iter_mut()
+        .filter_map(|force| force.potential_energy(particles))
+        .reduce(|a, b| a + b)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiagnosticsSample {
+    pub time: Time,
+    pub particles: usize,
+    pub kinetic_energy: Energy,
+    pub potential_energy: Option<Energy>,
+    pub temperature: Temperature,
+    pub momentum: Vector2<Scalar>,
+    pub angular_momentum: Scalar,
+    pub center_of_mass: Position,
+}
+
+impl DiagnosticsSample {
+    pub fn new(particles: &Particles, potentials: &mut [Box<dyn Force>], time: Time) -> Self {
+        Self {
+            time,
+            particles: particles.len(),
+            kinetic_energy: kinetic_energy(particles),
+            potential_energy: potential_energy(particles, potentials),
+            temperature: kinetic_temperature(particles),
+            momentum: momentum(particles),
+            angular_momentum: angular_momentum(particles),
+            center_of_mass: center_of_mass(particles),
+        }
+    }
+
+    pub fn total_energy(&self) -> Energy {
+        self.kinetic_energy + self.potential_energy.unwrap_or(0.)
+    }
+
+    // 2K / |U|, 1 for a self-gravitating system in equilibrium
+    pub fn virial_ratio(&self) -> Option<Scalar> {
+        match self.potential_energy {
+            Some(potential_energy) if potential_energy != 0. => {
+                Some(2. * self.kinetic_energy / potential_energy.abs())
+            }
+            _ => None,
+        }
+    }
+}
+
+pub type DiagnosticsSeries = Rc<RefCell<Vec<DiagnosticsSample>>>;
+
+const HEADER: &str = "time,particles,kinetic_energy,potential_energy,total_energy,temperature,\
+momentum_x,momentum_y,angular_momentum,center_of_mass_x,center_of_mass_y,virial_ratio";
+
+fn write_sample(writer: &mut impl Write, sample: &DiagnosticsSample) -> io::Result<()> {
+    let optional = |value: Option<Scalar>| value.map(|v| v.to_string()).unwrap_or_default();
+    writeln!(
+        writer,
+        "{},{},{},{},{},{},{},{},{},{},{},{}",
+        sample.time,
+        sample.particles,
+        sample.kinetic_energy,
+        optional(sample.potential_energy),
+        sample.total_energy(),
+        sample.temperature,
+        sample.momentum.x,
+        sample.momentum.y,
+        sample.angular_momentum,
+        sample.center_of_mass.x,
+        sample.center_of_mass.y,
+        optional(sample.virial_ratio())
+    )?;
+    writer.flush()
+}
+
+// Values at the first sample, with the scales drifts are relative to
+struct Reference {
+    sample: DiagnosticsSample,
+    energy_scale: Energy,   // |K| + |U|
+    momentum_scale: Scalar, // Sum of the particle momentum norms
+    angular_scale: Scalar,  // Sum of the particle angular momentum norms
+}
+
+impl Reference {
+    fn new(sample: DiagnosticsSample, particles: &Particles) -> Self {
+        let momentum_scale = particles
+            .velocities
+            .par_iter()
+            .zip(particles.masses.par_iter())
+            .map(|(velocity, mass)| mass * velocity.norm())
+            .sum();
+        let angular_scale = particles
+            .positions
+            .par_iter()
+            .zip(particles.velocities.par_iter())
+            .zip(particles.masses.par_iter())
+            .map(|((position, velocity), mass)| (mass * position.perp(velocity)).abs())
+            .sum();
+        Self {
+            energy_scale: sample.kinetic_energy.abs() + sample.potential_energy.unwrap_or(0.).abs(),
+            momentum_scale,
+            angular_scale,
+            sample,
+        }
+    }
+
+    // Relative drifts of the energy, momentum & angular momentum
+    fn drifts(&self, sample: &DiagnosticsSample) -> [(&'static str, Scalar); 3] {
+        let relative = |difference: Scalar, scale: Scalar| {
+            if scale > 0. {
+                difference / scale
+            } else {
+                difference
+            }
+        };
+        [
+            (
+                "Energy",
+                relative(
+                    (sample.total_energy() - self.sample.total_energy()).abs(),
+                    self.energy_scale,
+                ),
+            ),
+            (
+                "Momentum",
+                relative(
+                    (sample.momentum - self.sample.momentum).norm(),
+                    self.momentum_scale,
+                ),
+            ),
+            (
+                "Angular momentum",
+                relative(
+                    (sample.angular_momentum - self.sample.angular_momentum).abs(),
+                    self.angular_scale,
+                ),
+            ),
+        ]
+    }
+}
+
+pub struct Diagnostics {
+    pub potentials: Vec<Box<dyn Force>>, // Counted in the potential energy
+    pub drift_threshold: Option<Scalar>, // Relative drift warned about
+    pub log: bool,                       // Each sample
+    series: DiagnosticsSeries,
+    writer: Option<BufWriter<File>>,
+    time: Time,
+    reference: Option<Reference>,
+    drifting: [bool; 3],
+}
+
+impl Diagnostics {
+    pub fn new(potentials: Vec<Box<dyn Force>>) -> Self {
+        Self {
+            potentials,
+            drift_threshold: None,
+            log: false,
+            series: Rc::new(RefCell::new(Vec::new())),
+            writer: None,
+            time: 0.,
+            reference: None,
+            drifting: [false; 3],
+        }
+    }
+
+    pub fn with_drift_threshold(mut self, threshold: Scalar) -> Self {
+        self.drift_threshold = Some(threshold);
+        self
+    }
+
+    pub fn with_log(mut self) -> Self {
+        self.log = true;
+        self
+    }
+
+    // Samples also written as csv lines
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", HEADER)?;
+        self.writer = Some(writer);
+        Ok(self)
+    }
+
+    // Shared with the system, to read the samples while it runs
+    pub fn series(&self) -> DiagnosticsSeries {
+        self.series.clone()
+    }
+
+    fn check_drifts(&mut self, sample: &DiagnosticsSample) {
+        let (Some(threshold), Some(reference)) = (self.drift_threshold, &self.reference) else {
+            return;
+        };
+
+        for (i, (name, drift)) in reference.drifts(sample).into_iter().enumerate() {
+            let drifting = drift > threshold;
+            if drifting && !self.drifting[i] {
+                warn!(
+                    "{} drift of {:.2e} at time {} (threshold {:.2e})",
+                    name, drift, sample.time, threshold
+                );
+            }
+            self.drifting[i] = drifting;
+        }
+    }
+}
+
+impl System for Diagnostics {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        self.time += dt;
+        let sample = DiagnosticsSample::new(particles, &mut self.potentials, self.time);
+
+        // Nothing is conserved when particles are added or removed, drifts start again
+        if self
+            .reference
+            .as_ref()
+            .is_none_or(|reference| reference.sample.particles != sample.particles)
+        {
+            self.reference = Some(Reference::new(sample, particles));
+            self.drifting = [false; 3];
+        }
+        self.check_drifts(&sample);
+
+        if self.log {
+            info!(
+                "t = {}: E = {:.6e} (K = {:.6e}, U = {}), T = {:.4e}, P = ({:.3e}, {:.3e}), \
+                 L = {:.6e}, center of mass ({:.2}, {:.2}), virial ratio {}",
+                sample.time,
+                sample.total_energy(),
+                sample.kinetic_energy,
+                sample
+                    .potential_energy
+                    .map_or("-".to_string(), |u| format!("{:.6e}", u)),
+                sample.temperature,
+                sample.momentum.x,
+                sample.momentum.y,
+                sample.angular_momentum,
+                sample.center_of_mass.x,
+                sample.center_of_mass.y,
+                sample
+                    .virial_ratio()
+                    .map_or("-".to_string(), |q| format!("{:.3}", q)),
+            );
+        }
+        if let Some(writer) = &mut self.writer {
+            if let Err(e) = write_sample(writer, &sample) {
+                warn!("Diagnostics not written anymore: {}", e);
+                self.writer = None;
+            }
+        }
+        self.series.borrow_mut().push(sample);
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        for (i, force) in self.potentials.iter_mut().enumerate() {
+            tunables.nested(&format!("potentials.{}", i), |tunables| {
+                force.tunables(tunables)
+            });
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use std::f64::consts::PI;
+
+use nalgebra::Vector2;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+
+use super::{
+    generators::Generator,
+    random::par_generate_n,
+    types::{Mass, Scalar, Temperature, Velocity},
+};
+
+// Standard normal sample (Marsaglia polar method)
+#[inline]
+pub(crate) fn standard_normal(rng: &mut Pcg64Mcg) -> Scalar {
+    loop {
+        let u = rng.gen::<Scalar>() * 2. - 1.;
+        let v = rng.gen::<Scalar>() * 2. - 1.;
+        let s = u * u + v * v;
+        if s > 0. && s < 1. {
+            return u * (-2. * s.ln() / s).sqrt();
+        }
+    }
+}
+
+// Exponential sample with rate 1
+#[inline]
+fn standard_exponential(rng: &mut Pcg64Mcg) -> Scalar {
+    -(1. - rng.gen::<Scalar>()).ln()
+}
+
+// Speed of a 2D Maxwell-Boltzmann distribution (Rayleigh distribution)
+#[inline]
+fn maxwell_boltzmann_speed(rng: &mut Pcg64Mcg, temperature: Temperature, mass: Mass) -> Scalar {
+    (temperature / mass).sqrt() * (2. * standard_exponential(rng)).sqrt()
+}
+
+pub struct NormalGenerator {
+    rng: Pcg64Mcg,
+    mean: Scalar,
+    std_dev: Scalar,
+}
+
+impl NormalGenerator {
+    pub fn new(rng: Pcg64Mcg, mean: Scalar, std_dev: Scalar) -> Self {
+        if std_dev < 0. {
+            panic!("std_dev must be positive");
+        }
+        Self { rng, mean, std_dev }
+    }
+}
+
+impl Generator<Scalar> for NormalGenerator {
+    fn generate(&mut self) -> Scalar {
+        self.mean + self.std_dev * standard_normal(&mut self.rng)
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Scalar>) {
+        let (mean, std_dev) = (self.mean, self.std_dev);
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            mean + std_dev * standard_normal(rng)
+        });
+    }
+}
+
+// exp(X) with X normal(mu, sigma)
+pub struct LogNormalGenerator {
+    rng: Pcg64Mcg,
+    mu: Scalar,
+    sigma: Scalar,
+}
+
+impl LogNormalGenerator {
+    pub fn new(rng: Pcg64Mcg, mu: Scalar, sigma: Scalar) -> Self {
+        if sigma < 0. {
+            panic!("sigma must be positive");
+        }
+        Self { rng, mu, sigma }
+    }
+
+    // Parameters giving the wanted mean and standard deviation of the samples
+    pub fn from_mean_std_dev(rng: Pcg64Mcg, mean: Scalar, std_dev: Scalar) -> Self {
+        if mean <= 0. {
+            panic!("mean must be strictly positive");
+        }
+        let sigma_squared = (1. + (std_dev / mean).powi(2)).ln();
+        Self::new(rng, mean.ln() - sigma_squared / 2., sigma_squared.sqrt())
+    }
+}
+
+impl Generator<Scalar> for LogNormalGenerator {
+    fn generate(&mut self) -> Scalar {
+        (self.mu + self.sigma * standard_normal(&mut self.rng)).exp()
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Scalar>) {
+        let (mu, sigma) = (self.mu, self.sigma);
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            (mu + sigma * standard_normal(rng)).exp()
+        });
+    }
+}
+
+// Density: rate * exp(-rate * x)
+pub struct ExponentialGenerator {
+    rng: Pcg64Mcg,
+    rate: Scalar,
+}
+
+impl ExponentialGenerator {
+    pub fn new(rng: Pcg64Mcg, rate: Scalar) -> Self {
+        if rate <= 0. {
+            panic!("rate must be strictly positive");
+        }
+        Self { rng, rate }
+    }
+}
+
+impl Generator<Scalar> for ExponentialGenerator {
+    fn generate(&mut self) -> Scalar {
+        standard_exponential(&mut self.rng) / self.rate
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Scalar>) {
+        let rate = self.rate;
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            standard_exponential(rng) / rate
+        });
+    }
+}
+
+// Density proportional to x^-exponent between min and max (e.g. Salpeter mass function: 2.35)
+pub struct PowerLawGenerator {
+    rng: Pcg64Mcg,
+    exponent: Scalar,
+    min: Scalar,
+    max: Scalar,
+}
+
+impl PowerLawGenerator {
+    pub fn new(rng: Pcg64Mcg, exponent: Scalar, min: Scalar, max: Scalar) -> Self {
+        if min <= 0. || min >= max {
+            panic!("Power law needs 0 < min < max");
+        }
+        Self {
+            rng,
+            exponent,
+            min,
+            max,
+        }
+    }
+
+    // Inverse of the cumulative distribution
+    #[inline]
+    fn sample(rng: &mut Pcg64Mcg, exponent: Scalar, min: Scalar, max: Scalar) -> Scalar {
+        let u = rng.gen::<Scalar>();
+        if (exponent - 1.).abs() < 1e-12 {
+            // Log-uniform
+            min * (max / min).powf(u)
+        } else {
+            let k = 1. - exponent;
+            let (low, high) = (min.powf(k), max.powf(k));
+            (low + u * (high - low)).powf(1. / k)
+        }
+    }
+}
+
+impl Generator<Scalar> for PowerLawGenerator {
+    fn generate(&mut self) -> Scalar {
+        Self::sample(&mut self.rng, self.exponent, self.min, self.max)
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Scalar>) {
+        let (exponent, min, max) = (self.exponent, self.min, self.max);
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            Self::sample(rng, exponent, min, max)
+        });
+    }
+}
+
+// Speeds of a 2D ideal gas at a temperature (Boltzmann constant = 1)
+// Density: m v / T * exp(-m v^2 / 2T), mean speed sqrt(pi T / 2m)
+pub struct MaxwellBoltzmannSpeedGenerator {
+    rng: Pcg64Mcg,
+    pub temperature: Temperature,
+    pub mass: Mass,
+}
+
+impl MaxwellBoltzmannSpeedGenerator {
+    pub fn new(rng: Pcg64Mcg, temperature: Temperature, mass: Mass) -> Self {
+        if temperature < 0. || mass <= 0. {
+            panic!("Maxwell-Boltzmann needs a positive temperature and mass");
+        }
+        Self {
+            rng,
+            temperature,
+            mass,
+        }
+    }
+}
+
+impl Generator<Scalar> for MaxwellBoltzmannSpeedGenerator {
+    fn generate(&mut self) -> Scalar {
+        maxwell_boltzmann_speed(&mut self.rng, self.temperature, self.mass)
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Scalar>) {
+        let (temperature, mass) = (self.temperature, self.mass);
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            maxwell_boltzmann_speed(rng, temperature, mass)
+        });
+    }
+}
+
+// Isotropic velocities of a 2D ideal gas: each component is normal(0, sqrt(T / m))
+pub struct MaxwellBoltzmannVelocityGenerator {
+    rng: Pcg64Mcg,
+    pub temperature: Temperature,
+    pub mass: Mass,
+}
+
+impl MaxwellBoltzmannVelocityGenerator {
+    pub fn new(rng: Pcg64Mcg, temperature: Temperature, mass: Mass) -> Self {
+        if temperature < 0. || mass <= 0. {
+            panic!("Maxwell-Boltzmann needs a positive temperature and mass");
+        }
+        Self {
+            rng,
+            temperature,
+            mass,
+        }
+    }
+
+    #[inline]
+    fn sample(rng: &mut Pcg64Mcg, temperature: Temperature, mass: Mass) -> Velocity {
+        let speed = maxwell_boltzmann_speed(rng, temperature, mass);
+        let angle = rng.gen::<Scalar>() * 2. * PI;
+        Vector2::new(angle.cos(), angle.sin()) * speed
+    }
+}
+
+impl Generator<Velocity> for MaxwellBoltzmannVelocityGenerator {
+    fn generate(&mut self) -> Velocity {
+        Self::sample(&mut self.rng, self.temperature, self.mass)
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Velocity>) {
+        let (temperature, mass) = (self.temperature, self.mass);
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            Self::sample(rng, temperature, mass)
+        });
+    }
+}
+
+// Picks one of the values with a probability proportional to its weight
+pub struct WeightedChoiceGenerator<T: Clone> {
+    rng: Pcg64Mcg,
+    values: Vec<T>,
+    cumulative_weights: Vec<Scalar>,
+}
+
+impl<T: Clone> WeightedChoiceGenerator<T> {
+    pub fn new(rng: Pcg64Mcg, choices: Vec<(T, Scalar)>) -> Self {
+        if choices.iter().any(|(_, weight)| *weight < 0.) {
+            panic!("Weights must be positive");
+        }
+
+        let (values, weights): (Vec<T>, Vec<Scalar>) = choices.into_iter().unzip();
+        let cumulative_weights: Vec<Scalar> = weights
+            .iter()
+            .scan(0., |total, weight| {
+                *total += weight;
+                Some(*total)
+            })
+            .collect();
+        if cumulative_weights.last().is_none_or(|total| *total <= 0.) {
+            panic!("Weighted choice needs at least one strictly positive weight");
+        }
+
+        Self {
+            rng,
+            values,
+            cumulative_weights,
+        }
+    }
+
+    #[inline]
+    fn sample(rng: &mut Pcg64Mcg, values: &[T], cumulative_weights: &[Scalar]) -> T {
+        let total = cumulative_weights[cumulative_weights.len() - 1];
+        let target = rng.gen::<Scalar>() * total;
+        // First value whose cumulative weight passes the target (skips zero weights)
+        let index = cumulative_weights
+            .partition_point(|&weight| weight <= target)
+            .min(values.len() - 1);
+        values[index].clone()
+    }
+}
+
+impl<T: Clone + Send + Sync> Generator<T> for WeightedChoiceGenerator<T> {
+    fn generate(&mut self) -> T {
+        Self::sample(&mut self.rng, &self.values, &self.cumulative_weights)
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<T>) {
+        let (values, cumulative_weights) = (&self.values, &self.cumulative_weights);
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            Self::sample(rng, values, cumulative_weights)
+        });
+    }
+}
@@ -0,0 +1,201 @@
+use nalgebra::{Rotation2, Vector2};
+
+use super::{
+    particles::{ParticleFactory, Particles},
+    systems::System,
+    types::{Angle, Position, Scalar, Time},
+};
+
+pub type RateCurve = Box<dyn Fn(Time) -> Scalar>;
+pub type EmitterPath = Box<dyn Fn(Time) -> Position>;
+pub type EmitterRotation = Box<dyn Fn(Time) -> Angle>;
+
+// Emits only during the first on_time of each period (shifted by offset)
+pub struct DutyCycle {
+    pub period: Time,
+    pub on_time: Time,
+    pub offset: Time,
+}
+
+impl DutyCycle {
+    pub fn new(period: Time, on_time: Time, offset: Time) -> Self {
+        if period <= 0. {
+            panic!("Duty cycle period must be strictly positive");
+        }
+        Self {
+            period,
+            on_time,
+            offset,
+        }
+    }
+
+    // Time spent on between start and end
+    fn on_duration(&self, start: Time, end: Time) -> Time {
+        // Time on since the beginning of the cycle containing t = offset
+        let cumulative = |t: Time| {
+            let t = t - self.offset;
+            let cycles = (t / self.period).floor();
+            cycles * self.on_time + (t - cycles * self.period).min(self.on_time)
+        };
+        cumulative(end) - cumulative(start)
+    }
+}
+
+// Particles emitted at once when the emitter time reaches time
+#[derive(Clone)]
+pub struct Burst {
+    pub time: Time,
+    pub count: usize,
+}
+
+impl Burst {
+    pub fn new(time: Time, count: usize) -> Self {
+        Self { time, count }
+    }
+
+    // Bursts every period from start
+    pub fn periodic(start: Time, period: Time, count: usize, repeats: usize) -> Vec<Self> {
+        (0..repeats)
+            .map(|i| Self::new(start + i as Scalar * period, count))
+            .collect()
+    }
+}
+
+// Emitter with a time varying rate, duty cycle and bursts
+// With a path or a rotation, the factory creates particles around the origin (spawn shape) and
+// they are rotated then moved to the emitter position. Particles also inherit a fraction of the
+// emitter velocity (rocket trails, sprinklers...)
+pub struct Emitter {
+    p_factory: Box<dyn ParticleFactory>,
+    pub rate: RateCurve, // Particles per time unit
+    pub duty_cycle: Option<DutyCycle>,
+    pub path: Option<EmitterPath>,
+    pub rotation: Option<EmitterRotation>,
+    pub inherit_velocity: Scalar,
+    pub time: Time,
+    bursts: Vec<Burst>, // Sorted by time
+    next_burst: usize,
+    remainder: Scalar,
+}
+
+impl Emitter {
+    pub fn new(p_factory: Box<dyn ParticleFactory>, rate: Scalar) -> Self {
+        Self {
+            p_factory,
+            rate: Box::new(move |_| rate),
+            duty_cycle: None,
+            path: None,
+            rotation: None,
+            inherit_velocity: 0.,
+            time: 0.,
+            bursts: Vec::new(),
+            next_burst: 0,
+            remainder: 0.,
+        }
+    }
+
+    pub fn with_rate_curve(mut self, rate: RateCurve) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    pub fn with_duty_cycle(mut self, duty_cycle: DutyCycle) -> Self {
+        self.duty_cycle = Some(duty_cycle);
+        self
+    }
+
+    pub fn with_bursts(mut self, bursts: Vec<Burst>) -> Self {
+        for burst in bursts {
+            self.add_burst(burst);
+        }
+        self
+    }
+
+    pub fn with_path(mut self, path: EmitterPath, inherit_velocity: Scalar) -> Self {
+        self.path = Some(path);
+        self.inherit_velocity = inherit_velocity;
+        self
+    }
+
+    // Fixed spawn position, e.g. for rotating emitters
+    pub fn with_position(self, position: Position) -> Self {
+        self.with_path(Box::new(move |_| position), 0.)
+    }
+
+    pub fn with_rotation(mut self, rotation: EmitterRotation) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    // Bursts already due are emitted at the next update
+    pub fn add_burst(&mut self, burst: Burst) {
+        let index = self.bursts[self.next_burst..]
+            .partition_point(|other| other.time <= burst.time)
+            + self.next_burst;
+        self.bursts.insert(index, burst);
+    }
+
+    // Number of particles to emit during [time, time + dt]
+    fn count(&mut self, dt: Time) -> usize {
+        let (start, end) = (self.time, self.time + dt);
+
+        // Midpoint rule, restricted to the time the emitter is on
+        let on_time = match &self.duty_cycle {
+            Some(duty_cycle) => duty_cycle.on_duration(start, end),
+            None => dt,
+        };
+        let n = ((self.rate)(start + dt / 2.) * on_time).max(0.) + self.remainder;
+        let mut count = n as usize;
+        self.remainder = n - count as Scalar;
+
+        while let Some(burst) = self.bursts.get(self.next_burst) {
+            if burst.time > end {
+                break;
+            }
+            count += burst.count;
+            self.next_burst += 1;
+        }
+
+        count
+    }
+}
+
+impl System for Emitter {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        let count = self.count(dt);
+        self.time += dt;
+        if count == 0 {
+            return;
+        }
+
+        let start = particles.len();
+        self.p_factory.create(count, particles);
+
+        // Move the spawn shape
+        let rotation = self
+            .rotation
+            .as_ref()
+            .map(|rotation| Rotation2::new(rotation(self.time)));
+        let (position, velocity) = match &self.path {
+            Some(path) => {
+                let position = path(self.time);
+                let velocity = if dt > 0. {
+                    (position - path(self.time - dt)) / dt
+                } else {
+                    Vector2::zeros()
+                };
+                (position, velocity * self.inherit_velocity)
+            }
+            None => (Vector2::zeros(), Vector2::zeros()),
+        };
+
+        for i in start..particles.len() {
+            if let Some(rotation) = &rotation {
+                particles.positions[i] = rotation * particles.positions[i];
+                particles.velocities[i] = rotation * particles.velocities[i];
+            }
+            particles.positions[i] += position;
+            particles.velocities[i] += velocity;
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::{fs, io, path::Path};
+
+use nalgebra::Vector2;
+use rand::seq::SliceRandom;
+use rand_pcg::Pcg64Mcg;
+use rayon::prelude::*;
+
+use super::{
+    areas::Rect,
+    forces::Force,
+    particles::Particles,
+    tunables::Tunables,
+    types::{Force as ForceType, Position, Scalar, Time},
+};
+
+// Position and time dependent vector field
+pub trait VectorField: Sync {
+    fn sample(&self, position: Position, time: Time) -> Vector2<Scalar>;
+}
+
+// Analytic field from a closure
+pub struct FnField<F: Fn(Position, Time) -> Vector2<Scalar> + Sync> {
+    pub f: F,
+}
+
+impl<F: Fn(Position, Time) -> Vector2<Scalar> + Sync> FnField<F> {
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F: Fn(Position, Time) -> Vector2<Scalar> + Sync> VectorField for FnField<F> {
+    #[inline]
+    fn sample(&self, position: Position, time: Time) -> Vector2<Scalar> {
+        (self.f)(position, time)
+    }
+}
+
+// Values sampled on a regular grid covering rect, bilinearly interpolated
+// Positions outside of rect use the closest border value
+pub struct SampledField {
+    pub rect: Rect,
+    nx: usize,
+    ny: usize,
+    values: Vec<Vector2<Scalar>>, // Row major, starting from rect.position
+}
+
+impl SampledField {
+    pub fn new(rect: Rect, nx: usize, ny: usize, values: Vec<Vector2<Scalar>>) -> Self {
+        if nx < 2 || ny < 2 {
+            panic!("Sampled field needs at least 2x2 samples");
+        }
+        if values.len() != nx * ny {
+            panic!("Expected {} values, got {}", nx * ny, values.len());
+        }
+        Self {
+            rect,
+            nx,
+            ny,
+            values,
+        }
+    }
+
+    // Text format: "nx ny" on the first line then one "x y" vector per line (row major)
+    // Empty lines and lines starting with '#' are ignored
+    pub fn from_file<P: AsRef<Path>>(path: P, rect: Rect) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let invalid = |line: usize, message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line, message),
+            )
+        };
+
+        let parse_pair = |line_number: usize, line: &str| -> io::Result<(Scalar, Scalar)> {
+            let values = line
+                .split_whitespace()
+                .map(|v| v.parse::<Scalar>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| invalid(line_number, &e.to_string()))?;
+            match values[..] {
+                [x, y] => Ok((x, y)),
+                _ => Err(invalid(line_number, "expected 2 values")),
+            }
+        };
+
+        let (line_number, header) = lines.next().ok_or(invalid(0, "empty file"))?;
+        let (nx, ny) = parse_pair(line_number, header)?;
+        let (nx, ny) = (nx as usize, ny as usize);
+        if nx < 2 || ny < 2 {
+            return Err(invalid(line_number, "needs at least 2x2 samples"));
+        }
+
+        let mut values = Vec::with_capacity(nx * ny);
+        for (line_number, line) in lines {
+            let (x, y) = parse_pair(line_number, line)?;
+            values.push(Vector2::new(x, y));
+        }
+
+        if values.len() != nx * ny {
+            return Err(invalid(
+                content.lines().count(),
+                &format!("expected {} values, got {}", nx * ny, values.len()),
+            ));
+        }
+
+        Ok(Self::new(rect, nx, ny, values))
+    }
+}
+
+impl VectorField for SampledField {
+    fn sample(&self, position: Position, _time: Time) -> Vector2<Scalar> {
+        // Continuous grid coordinates
+        let relative = position - self.rect.position;
+        let gx = (relative.x / self.rect.size.x * (self.nx - 1) as Scalar)
+            .clamp(0., (self.nx - 1) as Scalar);
+        let gy = (relative.y / self.rect.size.y * (self.ny - 1) as Scalar)
+            .clamp(0., (self.ny - 1) as Scalar);
+
+        let x0 = (gx as usize).min(self.nx - 2);
+        let y0 = (gy as usize).min(self.ny - 2);
+        let (tx, ty) = (gx - x0 as Scalar, gy - y0 as Scalar);
+
+        let value = |x: usize, y: usize| self.values[y * self.nx + x];
+        let bottom = value(x0, y0) * (1. - tx) + value(x0 + 1, y0) * tx;
+        let top = value(x0, y0 + 1) * (1. - tx) + value(x0 + 1, y0 + 1) * tx;
+        bottom * (1. - ty) + top * ty
+    }
+}
+
+// Divergence free turbulence: curl of a 3D Perlin noise potential (x, y, time)
+pub struct CurlNoiseField {
+    pub scale: Scalar,    // Spatial size of the swirls
+    pub strength: Scalar, // Magnitude of the field
+    pub speed: Scalar,    // Evolution speed over time
+    permutation: Vec<usize>,
+}
+
+impl CurlNoiseField {
+    pub fn new(mut rng: Pcg64Mcg, scale: Scalar, strength: Scalar, speed: Scalar) -> Self {
+        let mut permutation: Vec<usize> = (0..256).collect();
+        permutation.shuffle(&mut rng);
+        permutation.extend_from_within(..);
+
+        Self {
+            scale,
+            strength,
+            speed,
+            permutation,
+        }
+    }
+
+    #[inline]
+    fn fade(t: Scalar) -> Scalar {
+        t * t * t * (t * (t * 6. - 15.) + 10.)
+    }
+
+    #[inline]
+    fn lerp(t: Scalar, a: Scalar, b: Scalar) -> Scalar {
+        a + t * (b - a)
+    }
+
+    #[inline]
+    fn grad(hash: usize, x: Scalar, y: Scalar, z: Scalar) -> Scalar {
+        let h = hash & 15;
+        let u = if h < 8 { x } else { y };
+        let v = if h < 4 {
+            y
+        } else if h == 12 || h == 14 {
+            x
+        } else {
+            z
+        };
+        (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+    }
+
+    // Improved Perlin noise (reference implementation)
+    fn noise(&self, x: Scalar, y: Scalar, z: Scalar) -> Scalar {
+        let p = &self.permutation;
+        let (xf, yf, zf) = (x.floor(), y.floor(), z.floor());
+        let (xi, yi, zi) = (
+            (xf as i64 & 255) as usize,
+            (yf as i64 & 255) as usize,
+            (zf as i64 & 255) as usize,
+        );
+        let (x, y, z) = (x - xf, y - yf, z - zf);
+        let (u, v, w) = (Self::fade(x), Self::fade(y), Self::fade(z));
+
+        let a = p[xi] + yi;
+        let aa = p[a] + zi;
+        let ab = p[a + 1] + zi;
+        let b = p[xi + 1] + yi;
+        let ba = p[b] + zi;
+        let bb = p[b + 1] + zi;
+
+        Self::lerp(
+            w,
+            Self::lerp(
+                v,
+                Self::lerp(
+                    u,
+                    Self::grad(p[aa], x, y, z),
+                    Self::grad(p[ba], x - 1., y, z),
+                ),
+                Self::lerp(
+                    u,
+                    Self::grad(p[ab], x, y - 1., z),
+                    Self::grad(p[bb], x - 1., y - 1., z),
+                ),
+            ),
+            Self::lerp(
+                v,
+                Self::lerp(
+                    u,
+                    Self::grad(p[aa + 1], x, y, z - 1.),
+                    Self::grad(p[ba + 1], x - 1., y, z - 1.),
+                ),
+                Self::lerp(
+                    u,
+                    Self::grad(p[ab + 1], x, y - 1., z - 1.),
+                    Self::grad(p[bb + 1], x - 1., y - 1., z - 1.),
+                ),
+            ),
+        )
+    }
+}
+
+impl VectorField for CurlNoiseField {
+    fn sample(&self, position: Position, time: Time) -> Vector2<Scalar> {
+        let (x, y, z) = (
+            position.x / self.scale,
+            position.y / self.scale,
+            time * self.speed,
+        );
+
+        // Central differences of the potential
+        let eps = 1e-4;
+        let dx = self.noise(x + eps, y, z) - self.noise(x - eps, y, z);
+        let dy = self.noise(x, y + eps, z) - self.noise(x, y - eps, z);
+
+        Vector2::new(dy, -dx) * (self.strength / (2. * eps))
+    }
+}
+
+// Field of accelerations (force = mass * field), like UniformGravity
+pub struct FieldAcceleration {
+    pub field: Box<dyn VectorField>,
+    pub time: Time,
+}
+
+impl FieldAcceleration {
+    pub fn new(field: Box<dyn VectorField>) -> Self {
+        Self { field, time: 0. }
+    }
+}
+
+impl Force for FieldAcceleration {
+    fn update(&mut self, dt: Time) {
+        self.time += dt;
+    }
+
+    fn apply(&mut self, particles: &Particles, forces: &mut Vec<ForceType>) {
+        let field = &self.field;
+        let time = self.time;
+        particles
+            .positions
+            .par_iter()
+            .zip(particles.masses.par_iter())
+            .zip(forces.par_iter_mut())
+            .for_each(|((position, mass), force)| {
+                *force += *mass * field.sample(*position, time);
+            });
+    }
+}
+
+// Field of velocities particles are dragged towards (wind), like UniformDrag
+pub struct FieldDrag {
+    pub field: Box<dyn VectorField>,
+    pub coef: Scalar,
+    pub time: Time,
+}
+
+impl FieldDrag {
+    pub fn new(field: Box<dyn VectorField>, coef: Scalar) -> Self {
+        Self {
+            field,
+            coef,
+            time: 0.,
+        }
+    }
+}
+
+impl Force for FieldDrag {
+    fn update(&mut self, dt: Time) {
+        self.time += dt;
+    }
+
+    fn apply(&mut self, particles: &Particles, forces: &mut Vec<ForceType>) {
+        let field = &self.field;
+        let time = self.time;
+        particles
+            .positions
+            .par_iter()
+            .zip(particles.velocities.par_iter())
+            .zip(forces.par_iter_mut())
+            .for_each(|((position, velocity), force)| {
+                *force -= self.coef * (velocity - field.sample(*position, time));
+            });
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("coef", &mut self.coef);
+    }
+}
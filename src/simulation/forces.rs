@@ -2,12 +2,25 @@ use rayon::prelude::*;
 
 use super::{
     particles::Particles,
-    types::{Acceleration, Force as ForceType, Mass, Position, Scalar, Velocity},
+    periodic::PeriodicBoundary,
+    tunables::Tunables,
+    types::{Acceleration, Energy, Force as ForceType, Mass, Position, Scalar, Time, Velocity},
 };
 
 pub trait Force {
     fn apply(&mut self, particles: &Particles, forces: &mut Vec<ForceType>);
 
+    // Called once per step before apply (time dependent forces)
+    fn update(&mut self, _dt: Time) {}
+
+    // Named parameters changed while running
+    fn tunables(&mut self, _tunables: &mut Tunables) {}
+
+    // Energy of the particles in the force, None when it doesn't derive from a potential
+    fn potential_energy(&mut self, _particles: &Particles) -> Option<Energy> {
+        None
+    }
+
     fn type_name(&self) -> &'static str {
         std::any::type_name::<Self>()
     }
@@ -33,6 +46,29 @@ impl Force for UniformGravity {
                 *force += *mass * self.acceleration;
             });
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.vector("acceleration", &mut self.acceleration);
+    }
+
+    fn potential_energy(&mut self, particles: &Particles) -> Option<Energy> {
+        let energy = particles
+            .positions
+            .par_iter()
+            .zip(particles.masses.par_iter())
+            .map(|(position, mass)| -mass * self.acceleration.dot(position))
+            .sum();
+        Some(energy)
+    }
+}
+
+// Sum over all pairs i < j
+fn pairs_energy(particles: &Particles, energy: impl Fn(usize, usize) -> Energy + Sync) -> Energy {
+    let len = particles.len();
+    (0..len)
+        .into_par_iter()
+        .map(|i| ((i + 1)..len).map(|j| energy(i, j)).sum::<Energy>())
+        .sum()
 }
 
 pub struct UniformDrag {
@@ -56,17 +92,33 @@ impl Force for UniformDrag {
                 *force -= self.coef * (velocity - &self.velocity);
             });
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("coef", &mut self.coef);
+        tunables.vector("velocity", &mut self.velocity);
+    }
 }
 
 #[derive(Clone)]
 pub struct Gravity {
     pub coef: Scalar,
     pub epsilon: Scalar,
+    pub periodic: Option<PeriodicBoundary>,
 }
 
 impl Gravity {
     pub fn new(coef: Scalar, epsilon: Scalar) -> Self {
-        Self { coef, epsilon }
+        Self {
+            coef,
+            epsilon,
+            periodic: None,
+        }
+    }
+
+    // Use minimum image distances
+    pub fn with_periodic(mut self, periodic: PeriodicBoundary) -> Self {
+        self.periodic = Some(periodic);
+        self
     }
 
     #[inline]
@@ -77,7 +129,7 @@ impl Gravity {
         mass1: Mass,
         mass2: Mass,
     ) -> ForceType {
-        let distance_v = pos1 - pos2;
+        let distance_v = PeriodicBoundary::displacement(&self.periodic, pos1, pos2);
         let distance = distance_v.norm();
 
         if distance < self.epsilon {
@@ -86,6 +138,22 @@ impl Gravity {
 
         -self.coef * distance_v * mass1 * mass2 / distance.powi(3)
     }
+
+    // Constant below epsilon, where there is no force
+    #[inline]
+    pub fn calc_potential(
+        &self,
+        pos1: Position,
+        pos2: Position,
+        mass1: Mass,
+        mass2: Mass,
+    ) -> Energy {
+        let distance = PeriodicBoundary::displacement(&self.periodic, pos1, pos2)
+            .norm()
+            .max(self.epsilon);
+
+        -self.coef * mass1 * mass2 / distance
+    }
 }
 
 impl Force for Gravity {
@@ -125,17 +193,44 @@ impl Force for Gravity {
             }
         });
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("coef", &mut self.coef);
+        tunables.scalar("epsilon", &mut self.epsilon);
+    }
+
+    fn potential_energy(&mut self, particles: &Particles) -> Option<Energy> {
+        Some(pairs_energy(particles, |i, j| {
+            self.calc_potential(
+                particles.positions[i],
+                particles.positions[j],
+                particles.masses[i],
+                particles.masses[j],
+            )
+        }))
+    }
 }
 
 #[derive(Clone)]
 pub struct Drag {
     pub coef: Scalar,
     pub distance: Scalar,
+    pub periodic: Option<PeriodicBoundary>,
 }
 
 impl Drag {
     pub fn new(coef: Scalar, distance: Scalar) -> Self {
-        Self { coef, distance }
+        Self {
+            coef,
+            distance,
+            periodic: None,
+        }
+    }
+
+    // Use minimum image distances
+    pub fn with_periodic(mut self, periodic: PeriodicBoundary) -> Self {
+        self.periodic = Some(periodic);
+        self
     }
 
     #[inline]
@@ -146,7 +241,7 @@ impl Drag {
         vel1: Velocity,
         vel2: Velocity,
     ) -> ForceType {
-        let distance = (pos1 - pos2).norm();
+        let distance = PeriodicBoundary::displacement(&self.periodic, pos1, pos2).norm();
 
         if distance > self.distance || distance == 0.0 {
             return ForceType::zeros();
@@ -196,6 +291,11 @@ impl Force for Drag {
             }
         });
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("coef", &mut self.coef);
+        tunables.scalar("distance", &mut self.distance);
+    }
 }
 
 #[derive(Clone)]
@@ -203,6 +303,7 @@ pub struct Repulsion {
     pub coef: Scalar,
     pub power: i32,
     pub epsilon: Scalar,
+    pub periodic: Option<PeriodicBoundary>,
 }
 
 impl Repulsion {
@@ -211,12 +312,19 @@ impl Repulsion {
             coef,
             power,
             epsilon,
+            periodic: None,
         }
     }
 
+    // Use minimum image distances
+    pub fn with_periodic(mut self, periodic: PeriodicBoundary) -> Self {
+        self.periodic = Some(periodic);
+        self
+    }
+
     #[inline]
     pub fn calc_force(&self, pos1: Position, pos2: Position) -> ForceType {
-        let distance_v = pos1 - pos2;
+        let distance_v = PeriodicBoundary::displacement(&self.periodic, pos1, pos2);
         let distance = distance_v.norm();
 
         if distance < self.epsilon {
@@ -225,6 +333,20 @@ impl Repulsion {
 
         self.coef * distance_v / distance.powi(self.power)
     }
+
+    // Constant below epsilon, where there is no force
+    #[inline]
+    pub fn calc_potential(&self, pos1: Position, pos2: Position) -> Energy {
+        let distance = PeriodicBoundary::displacement(&self.periodic, pos1, pos2)
+            .norm()
+            .max(self.epsilon);
+
+        if self.power == 2 {
+            -self.coef * distance.ln()
+        } else {
+            self.coef / ((self.power - 2) as Scalar * distance.powi(self.power - 2))
+        }
+    }
 }
 
 impl Force for Repulsion {
@@ -260,4 +382,15 @@ impl Force for Repulsion {
             }
         });
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("coef", &mut self.coef);
+        tunables.scalar("epsilon", &mut self.epsilon);
+    }
+
+    fn potential_energy(&mut self, particles: &Particles) -> Option<Energy> {
+        Some(pairs_energy(particles, |i, j| {
+            self.calc_potential(particles.positions[i], particles.positions[j])
+        }))
+    }
 }
@@ -1,25 +1,33 @@
-use std::f64::consts::PI;
+use std::f64::consts::{PI, SQRT_2};
 
 use nalgebra::Vector2;
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng};
 use rand_pcg::Pcg64Mcg;
+use rayon::prelude::*;
 
 use super::{
-    areas::{Disk, Point, Rect},
+    areas::{Area, Disk, Point, Rect},
     color::Color,
-    types::Scalar,
+    random::par_generate_n,
+    types::{Length, Scalar},
 };
 
 pub trait Generator<T> {
     fn generate(&mut self) -> T;
 
+    // Sequential by default, random generators override it with random::par_generate_n
     fn generate_n(&mut self, n: usize, vec: &mut Vec<T>) {
         vec.reserve_exact(n);
-        // TODO parallelize
         for _ in 0..n {
             vec.push(self.generate());
         }
     }
+
+    // Generators that can run out of values report it instead of panicking, nothing is added then
+    fn try_generate_n(&mut self, n: usize, vec: &mut Vec<T>) -> Result<(), String> {
+        self.generate_n(n, vec);
+        Ok(())
+    }
 }
 
 pub struct IterGenerator<T: Iterator> {
@@ -52,6 +60,10 @@ impl<T: Clone> Generator<T> for ConstantGenerator<T> {
     fn generate(&mut self) -> T {
         self.value.clone()
     }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<T>) {
+        vec.resize(vec.len() + n, self.value.clone());
+    }
 }
 
 pub struct UniformGenerator {
@@ -73,6 +85,11 @@ impl Generator<Scalar> for UniformGenerator {
     fn generate(&mut self) -> Scalar {
         self.rng.gen_range(self.min..self.max)
     }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Scalar>) {
+        let range = self.min..self.max;
+        par_generate_n(&mut self.rng, n, vec, |rng| rng.gen_range(range.clone()));
+    }
 }
 
 pub struct Vector2Generator {
@@ -100,11 +117,12 @@ impl Generator<Vector2<Scalar>> for Vector2Generator {
         self.x_generator.generate_n(n, &mut x);
         self.y_generator.generate_n(n, &mut y);
 
-        // TODO parallelize
         vec.reserve_exact(n);
-        for (x, y) in x.into_iter().zip(y.into_iter()) {
-            vec.push(Vector2::new(x, y));
-        }
+        vec.par_extend(
+            x.into_par_iter()
+                .zip(y.into_par_iter())
+                .map(|(x, y)| Vector2::new(x, y)),
+        );
     }
 
     fn generate(&mut self) -> Vector2<Scalar> {
@@ -139,11 +157,12 @@ impl Generator<Vector2<Scalar>> for Vector2PolarGenerator {
         self.r_generator.generate_n(n, &mut r);
         self.theta_generator.generate_n(n, &mut theta);
 
-        // TODO parallelize
         vec.reserve_exact(n);
-        for (r, theta) in r.into_iter().zip(theta.into_iter()) {
-            vec.push(Vector2::new(r * theta.cos(), r * theta.sin()));
-        }
+        vec.par_extend(
+            r.into_par_iter()
+                .zip(theta.into_par_iter())
+                .map(|(r, theta)| Vector2::new(r * theta.cos(), r * theta.sin())),
+        );
     }
 
     fn generate(&mut self) -> Vector2<Scalar> {
@@ -166,10 +185,12 @@ impl RandomRectPointGenerator {
 
 impl Generator<Vector2<Scalar>> for RandomRectPointGenerator {
     fn generate(&mut self) -> Vector2<Scalar> {
-        Vector2::new(
-            self.rng.gen::<Scalar>() * self.rect.size.x + self.rect.position.x,
-            self.rng.gen::<Scalar>() * self.rect.size.y + self.rect.position.y,
-        )
+        self.rect.sample(&mut self.rng).unwrap()
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) {
+        let rect = &self.rect;
+        par_generate_n(&mut self.rng, n, vec, |rng| rect.sample(rng).unwrap());
     }
 }
 
@@ -191,8 +212,7 @@ impl Generator<Vector2<Scalar>> for UniformRectPointsGenerator {
     fn generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) {
         vec.reserve_exact(n);
 
-        // TODO area::area() ?
-        let area_per_point = (self.rect.size.x * self.rect.size.y) / n as f64;
+        let area_per_point = self.rect.area() / n as f64;
         let distance_increment = area_per_point.sqrt();
 
         let nx = (self.rect.size.x / distance_increment).floor() as usize;
@@ -238,12 +258,12 @@ impl RandomDiskPointGenerator {
 
 impl Generator<Vector2<Scalar>> for RandomDiskPointGenerator {
     fn generate(&mut self) -> Vector2<Scalar> {
-        let angle = self.rng.gen::<Scalar>() * 2.0 * PI;
-        let radius = (self.rng.gen::<Scalar>() * self.disk.radius_squared).sqrt();
-        Vector2::new(
-            self.disk.position.x + radius * angle.cos(),
-            self.disk.position.y + radius * angle.sin(),
-        )
+        self.disk.sample(&mut self.rng).unwrap()
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) {
+        let disk = &self.disk;
+        par_generate_n(&mut self.rng, n, vec, |rng| disk.sample(rng).unwrap());
     }
 }
 
@@ -270,12 +290,25 @@ impl Generator<Vector2<Scalar>> for UniformDiskPointsGenerator {
         let distance_increment = area_per_point.sqrt();
 
         let nr = (self.disk.radius_squared.sqrt() / distance_increment).ceil() as usize;
-        let mut placed = 0;
-        for i in 0..nr {
-            let r = (i as f64 + 0.5) * distance_increment;
-            let circumference = 2.0 * PI * r;
-            let points_on_this_radius =
-                std::cmp::min(n - placed, (circumference / distance_increment) as usize);
+
+        // Share the n points between the rings proportionally to their circumference
+        // (largest remainder first so exactly n points are placed)
+        let radii: Vec<f64> = (0..nr)
+            .map(|i| (i as f64 + 0.5) * distance_increment)
+            .collect();
+        let total_radius: f64 = radii.iter().sum();
+        let quotas: Vec<f64> = radii.iter().map(|r| r / total_radius * n as f64).collect();
+        let mut counts: Vec<usize> = quotas.iter().map(|q| q.floor() as usize).collect();
+        let mut by_remainder: Vec<usize> = (0..nr).collect();
+        by_remainder.sort_by(|&a, &b| {
+            (quotas[b] - quotas[b].floor()).total_cmp(&(quotas[a] - quotas[a].floor()))
+        });
+        let missing = n - counts.iter().sum::<usize>();
+        for &i in by_remainder.iter().take(missing) {
+            counts[i] += 1;
+        }
+
+        for (r, points_on_this_radius) in radii.into_iter().zip(counts) {
             let angle_increment = 2.0 * PI / points_on_this_radius as f64;
             for j in 0..points_on_this_radius {
                 let angle = j as f64 * angle_increment;
@@ -283,7 +316,451 @@ impl Generator<Vector2<Scalar>> for UniformDiskPointsGenerator {
                 let y = r * angle.sin();
                 vec.push(Vector2::new(x, y) + self.disk.position);
             }
-            placed += points_on_this_radius;
+        }
+    }
+}
+
+// Uniform random points in any area
+// Uses the exact sampler of the area when it has one, rejection sampling in its bounding box otherwise
+pub struct AreaPointGenerator {
+    area: Box<dyn Area>,
+    bounding_box: Rect,
+    rng: Pcg64Mcg,
+    pub max_attempts: usize, // Rejection sampling attempts before giving up
+}
+
+impl AreaPointGenerator {
+    pub fn new(area: Box<dyn Area>, rng: Pcg64Mcg) -> Self {
+        let bounding_box = bounded_box(area.as_ref());
+        Self {
+            area,
+            bounding_box,
+            rng,
+            max_attempts: MAX_SAMPLE_ATTEMPTS,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    // None if no point of the area was found within max_attempts
+    pub fn try_generate(&mut self) -> Option<Vector2<Scalar>> {
+        sample_area(
+            self.area.as_ref(),
+            &self.bounding_box,
+            &mut self.rng,
+            self.max_attempts,
+        )
+    }
+}
+
+// Default rejection sampling attempts of the generators before giving up
+const MAX_SAMPLE_ATTEMPTS: usize = 10_000;
+
+fn not_found(max_attempts: usize) -> ! {
+    panic!(
+        "No point found in the area after {} attempts (empty or very thin area?)",
+        max_attempts
+    )
+}
+
+// Exact sampler of the area if it has one, rejection sampling in its bounding box otherwise
+fn sample_area(
+    area: &dyn Area,
+    bounding_box: &Rect,
+    rng: &mut Pcg64Mcg,
+    max_attempts: usize,
+) -> Option<Vector2<Scalar>> {
+    if let Some(position) = area.sample(rng) {
+        return Some(position);
+    }
+
+    (0..max_attempts)
+        .map(|_| {
+            bounding_box.position
+                + bounding_box
+                    .size
+                    .component_mul(&Vector2::new(rng.gen::<Scalar>(), rng.gen::<Scalar>()))
+        })
+        .find(|position| area.contain(*position))
+}
+
+fn bounded_box(area: &dyn Area) -> Rect {
+    let bounding_box = area.bounding_box();
+    if !bounding_box.size.x.is_finite() || !bounding_box.size.y.is_finite() {
+        panic!("Can't generate points in an unbounded area");
+    }
+    bounding_box
+}
+
+// Also checks the area has a surface, for the generators spacing points out
+fn surface_box(area: &dyn Area) -> Rect {
+    let bounding_box = bounded_box(area);
+    if area.area() <= 0. {
+        panic!("Can't space points out in an area without surface");
+    }
+    bounding_box
+}
+
+impl Generator<Vector2<Scalar>> for AreaPointGenerator {
+    fn generate(&mut self) -> Vector2<Scalar> {
+        match self.try_generate() {
+            Some(position) => position,
+            None => not_found(self.max_attempts),
+        }
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) {
+        let (area, bounding_box, max_attempts) =
+            (self.area.as_ref(), &self.bounding_box, self.max_attempts);
+        par_generate_n(&mut self.rng, n, vec, |rng| {
+            sample_area(area, bounding_box, rng, max_attempts)
+                .unwrap_or_else(|| not_found(max_attempts))
+        });
+    }
+}
+
+// Blue noise: random points at least min_distance apart (Bridson's algorithm)
+// The distance is derived from the surface of the area by default, then lowered until the area
+// fits n points that far apart; extra points are dropped at random. A given min_distance is kept,
+// generating more points than fit is an error of try_generate_n (a panic of generate_n).
+// Times the derived distance is lowered by 10% before giving up (down to about 1%)
+const POISSON_DISK_MAX_FITS: usize = 40;
+
+pub struct PoissonDiskGenerator {
+    area: Box<dyn Area>,
+    bounding_box: Rect,
+    rng: Pcg64Mcg,
+    pub min_distance: Option<Length>,
+    pub attempts: usize, // Candidates tried around each point before it is retired
+}
+
+impl PoissonDiskGenerator {
+    pub fn new(area: Box<dyn Area>, rng: Pcg64Mcg) -> Self {
+        let bounding_box = surface_box(area.as_ref());
+        Self {
+            area,
+            bounding_box,
+            rng,
+            min_distance: None,
+            attempts: 30,
+        }
+    }
+
+    pub fn with_min_distance(mut self, min_distance: Length) -> Self {
+        assert!(
+            min_distance > 0.,
+            "Poisson disk min_distance must be strictly positive"
+        );
+        self.min_distance = Some(min_distance);
+        self
+    }
+
+    // At least n points, as far apart as the lowered distance allows. A maximal Poisson-disk set
+    // covers about 0.7 * area / min_distance^2, so the first guess rarely has to be lowered.
+    fn fit(&mut self, n: usize) -> Result<Vec<Vector2<Scalar>>, String> {
+        let mut min_distance = (0.65 * self.area.area() / n as Scalar).sqrt();
+        for _ in 0..POISSON_DISK_MAX_FITS {
+            let points = self.fill(min_distance);
+            if points.len() >= n {
+                return Ok(points);
+            }
+            min_distance *= 0.9;
+        }
+        Err(format!("Can't fit {} Poisson disk points in the area", n))
+    }
+
+    // Maximal set of points at least min_distance apart
+    fn fill(&mut self, min_distance: Length) -> Vec<Vector2<Scalar>> {
+        let rect = &self.bounding_box;
+        let cell_size = min_distance / SQRT_2;
+        let nx = ((rect.size.x / cell_size).ceil() as usize).max(1);
+        let ny = ((rect.size.y / cell_size).ceil() as usize).max(1);
+        let mut grid: Vec<Option<usize>> = vec![None; nx * ny];
+        let cell_coords = |position: Vector2<Scalar>| {
+            let relative = (position - rect.position) / cell_size;
+            (
+                (relative.x.max(0.) as usize).min(nx - 1),
+                (relative.y.max(0.) as usize).min(ny - 1),
+            )
+        };
+
+        let mut points: Vec<Vector2<Scalar>> = Vec::new();
+        let mut active = Vec::new();
+
+        let fits =
+            |points: &Vec<Vector2<Scalar>>, grid: &Vec<Option<usize>>, p: Vector2<Scalar>| {
+                let (x, y) = cell_coords(p);
+                for cy in y.saturating_sub(2)..(y + 3).min(ny) {
+                    for cx in x.saturating_sub(2)..(x + 3).min(nx) {
+                        if let Some(other) = grid[cy * nx + cx] {
+                            if (points[other] - p).norm_squared() < min_distance * min_distance {
+                                return false;
+                            }
+                        }
+                    }
+                }
+                true
+            };
+
+        // Seeds are retried so that disconnected parts of the area get filled too
+        let mut seed_failures = 0;
+        while seed_failures < self.attempts {
+            let seed = match sample_area(self.area.as_ref(), rect, &mut self.rng, self.attempts) {
+                Some(seed) if fits(&points, &grid, seed) => seed,
+                _ => {
+                    seed_failures += 1;
+                    continue;
+                }
+            };
+            let (x, y) = cell_coords(seed);
+            grid[y * nx + x] = Some(points.len());
+            active.push(points.len());
+            points.push(seed);
+
+            while !active.is_empty() {
+                let index = self.rng.gen_range(0..active.len());
+                let center = points[active[index]];
+
+                let mut found = false;
+                for _ in 0..self.attempts {
+                    // Uniform in the annulus [min_distance, 2 * min_distance]
+                    let angle = self.rng.gen::<Scalar>() * 2. * PI;
+                    let radius = min_distance * (1. + 3. * self.rng.gen::<Scalar>()).sqrt();
+                    let candidate = center + Vector2::new(angle.cos(), angle.sin()) * radius;
+
+                    if self.area.contain(candidate) && fits(&points, &grid, candidate) {
+                        let (x, y) = cell_coords(candidate);
+                        grid[y * nx + x] = Some(points.len());
+                        active.push(points.len());
+                        points.push(candidate);
+                        found = true;
+                        break;
+                    }
+                }
+
+                if !found {
+                    active.swap_remove(index);
+                }
+            }
+        }
+
+        points
+    }
+}
+
+impl Generator<Vector2<Scalar>> for PoissonDiskGenerator {
+    fn generate(&mut self) -> Vector2<Scalar> {
+        sample_area(
+            self.area.as_ref(),
+            &self.bounding_box,
+            &mut self.rng,
+            MAX_SAMPLE_ATTEMPTS,
+        )
+        .unwrap_or_else(|| not_found(MAX_SAMPLE_ATTEMPTS))
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) {
+        if let Err(e) = self.try_generate_n(n, vec) {
+            panic!("{}", e);
+        }
+    }
+
+    fn try_generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) -> Result<(), String> {
+        if n == 0 {
+            return Ok(());
+        }
+
+        let mut points = match self.min_distance {
+            Some(min_distance) => {
+                let points = self.fill(min_distance);
+                if points.len() < n {
+                    return Err(format!(
+                        "Only {} points fit {} apart in the area, {} asked",
+                        points.len(),
+                        min_distance,
+                        n
+                    ));
+                }
+                points
+            }
+            None => self.fit(n)?,
+        };
+        points.shuffle(&mut self.rng);
+        vec.reserve_exact(n);
+        vec.extend_from_slice(&points[..n]);
+        Ok(())
+    }
+}
+
+// Largest lattice spacing giving at least n points in the area, then the extra points furthest
+// from the center of the lattice are dropped
+// Returns the points and the spacing used
+fn fit_lattice<F: Fn(Length) -> Vec<Vector2<Scalar>>>(
+    area: &dyn Area,
+    n: usize,
+    lattice: F,
+) -> (Vec<Vector2<Scalar>>, Length) {
+    let guess = (area.area() / n as Scalar).sqrt();
+
+    // Spacing small enough to fit n points
+    let mut low = guess / 2.;
+    let mut points = lattice(low);
+    while points.len() < n {
+        low /= 2.;
+        if low < guess * 1e-6 {
+            panic!("Can't fit {} lattice points in the area", n);
+        }
+        points = lattice(low);
+    }
+
+    let mut high = guess * 4.;
+    for _ in 0..40 {
+        let spacing = (low + high) / 2.;
+        let candidate = lattice(spacing);
+        if candidate.len() >= n {
+            low = spacing;
+            points = candidate;
+        } else {
+            high = spacing;
+        }
+    }
+
+    let center = points.iter().sum::<Vector2<Scalar>>() / points.len() as Scalar;
+    points.sort_by(|a, b| {
+        (a - center)
+            .norm_squared()
+            .total_cmp(&(b - center).norm_squared())
+    });
+    points.truncate(n);
+    (points, low)
+}
+
+// Points on a hexagonal (triangular) lattice, spacing chosen to fit exactly n points
+pub struct HexLatticeGenerator {
+    area: Box<dyn Area>,
+    bounding_box: Rect,
+}
+
+impl HexLatticeGenerator {
+    pub fn new(area: Box<dyn Area>) -> Self {
+        let bounding_box = surface_box(area.as_ref());
+        Self { area, bounding_box }
+    }
+
+    fn lattice(&self, spacing: Length) -> Vec<Vector2<Scalar>> {
+        let rect = &self.bounding_box;
+        let center = rect.center();
+        let row_height = spacing * 3_f64.sqrt() / 2.;
+        let (half_columns, half_rows) = (
+            (rect.size.x / 2. / spacing).ceil() as i64 + 1,
+            (rect.size.y / 2. / row_height).ceil() as i64,
+        );
+
+        // Centered on the bounding box so symmetric areas get symmetric lattices
+        let mut points = Vec::new();
+        for row in -half_rows..=half_rows {
+            let offset = if row % 2 == 0 { 0. } else { spacing / 2. };
+            for column in -half_columns..=half_columns {
+                let position = center
+                    + Vector2::new(
+                        column as Scalar * spacing + offset,
+                        row as Scalar * row_height,
+                    );
+                if self.area.contain(position) {
+                    points.push(position);
+                }
+            }
+        }
+        points
+    }
+}
+
+impl Generator<Vector2<Scalar>> for HexLatticeGenerator {
+    fn generate(&mut self) -> Vector2<Scalar> {
+        let mut vec = Vec::with_capacity(1);
+        self.generate_n(1, &mut vec);
+        vec[0]
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) {
+        if n == 0 {
+            return;
+        }
+        let (points, _) = fit_lattice(self.area.as_ref(), n, |spacing| self.lattice(spacing));
+        vec.extend(points);
+    }
+}
+
+// Square grid with each point moved randomly inside its cell (jitter: 0 grid, 1 whole cell)
+// Jittered points falling out of the area stay at the center of their cell
+pub struct JitteredGridGenerator {
+    area: Box<dyn Area>,
+    bounding_box: Rect,
+    rng: Pcg64Mcg,
+    pub jitter: Scalar,
+}
+
+impl JitteredGridGenerator {
+    pub fn new(area: Box<dyn Area>, rng: Pcg64Mcg, jitter: Scalar) -> Self {
+        let bounding_box = surface_box(area.as_ref());
+        Self {
+            area,
+            bounding_box,
+            rng,
+            jitter,
+        }
+    }
+
+    fn grid(&self, spacing: Length) -> Vec<Vector2<Scalar>> {
+        let rect = &self.bounding_box;
+        let center = rect.center();
+        let (half_columns, half_rows) = (
+            (rect.size.x / 2. / spacing).ceil() as i64,
+            (rect.size.y / 2. / spacing).ceil() as i64,
+        );
+
+        let mut points = Vec::new();
+        for row in -half_rows..=half_rows {
+            for column in -half_columns..=half_columns {
+                let position = center + Vector2::new(column as Scalar, row as Scalar) * spacing;
+                if self.area.contain(position) {
+                    points.push(position);
+                }
+            }
+        }
+        points
+    }
+}
+
+impl Generator<Vector2<Scalar>> for JitteredGridGenerator {
+    fn generate(&mut self) -> Vector2<Scalar> {
+        let mut vec = Vec::with_capacity(1);
+        self.generate_n(1, &mut vec);
+        vec[0]
+    }
+
+    fn generate_n(&mut self, n: usize, vec: &mut Vec<Vector2<Scalar>>) {
+        if n == 0 {
+            return;
+        }
+
+        let (points, spacing) = fit_lattice(self.area.as_ref(), n, |spacing| self.grid(spacing));
+
+        vec.reserve_exact(n);
+        for point in points {
+            let offset = Vector2::new(
+                self.rng.gen::<Scalar>() - 0.5,
+                self.rng.gen::<Scalar>() - 0.5,
+            ) * (self.jitter * spacing);
+            let jittered = point + offset;
+            vec.push(if self.area.contain(jittered) {
+                jittered
+            } else {
+                point
+            });
         }
     }
 }
@@ -339,15 +816,13 @@ impl Generator<Color> for RGBAGenerator {
         self.b_generator.generate_n(n, &mut b);
         self.a_generator.generate_n(n, &mut a);
 
-        // TODO parallelize
         vec.reserve_exact(n);
-        for ((r, g), (b, a)) in r
-            .into_iter()
-            .zip(g.into_iter())
-            .zip(b.into_iter().zip(a.into_iter()))
-        {
-            vec.push(Color::from_rgba(r, g, b, a));
-        }
+        vec.par_extend(
+            r.into_par_iter()
+                .zip(g.into_par_iter())
+                .zip(b.into_par_iter().zip(a.into_par_iter()))
+                .map(|((r, g), (b, a))| Color::from_rgba(r, g, b, a)),
+        );
     }
 
     fn generate(&mut self) -> Color {
@@ -392,15 +867,13 @@ impl Generator<Color> for HSVAGenerator {
         self.v_generator.generate_n(n, &mut v);
         self.a_generator.generate_n(n, &mut a);
 
-        // TODO parallelize
         vec.reserve_exact(n);
-        for ((h, s), (v, a)) in h
-            .into_iter()
-            .zip(s.into_iter())
-            .zip(v.into_iter().zip(a.into_iter()))
-        {
-            vec.push(Color::from_hsva(h, s, v, a));
-        }
+        vec.par_extend(
+            h.into_par_iter()
+                .zip(s.into_par_iter())
+                .zip(v.into_par_iter().zip(a.into_par_iter()))
+                .map(|((h, s), (v, a))| Color::from_hsva(h, s, v, a)),
+        );
     }
 
     fn generate(&mut self) -> Color {
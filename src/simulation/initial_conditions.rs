@@ -0,0 +1,356 @@
+use std::f64::consts::PI;
+
+use nalgebra::Vector2;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+
+use super::{
+    color::Color,
+    distributions::standard_normal,
+    forces::Gravity,
+    particles::Particles,
+    types::{Angle, Length, Mass, Position, Scalar, Velocity},
+};
+
+#[inline]
+fn unit_vector(angle: Scalar) -> Vector2<Scalar> {
+    Vector2::new(angle.cos(), angle.sin())
+}
+
+// Perpendicular direction, counter-clockwise
+#[inline]
+fn perpendicular(v: Vector2<Scalar>) -> Vector2<Scalar> {
+    Vector2::new(-v.y, v.x)
+}
+
+// Shift particles from start so their center of mass and mean velocity are exactly the given ones
+// (random sampling leaves a small drift otherwise)
+fn recenter(particles: &mut Particles, start: usize, center: Position, velocity: Velocity) {
+    let n = particles.len() - start;
+    if n == 0 {
+        return;
+    }
+    let total_mass: Mass = particles.masses[start..].iter().sum();
+    let (mut center_of_mass, mut momentum) = (Vector2::zeros(), Vector2::zeros());
+    for i in start..particles.len() {
+        center_of_mass += particles.positions[i] * particles.masses[i];
+        momentum += particles.velocities[i] * particles.masses[i];
+    }
+
+    let position_offset = center_of_mass / total_mass - center;
+    let velocity_offset = momentum / total_mass - velocity;
+    for i in start..particles.len() {
+        particles.positions[i] -= position_offset;
+        particles.velocities[i] -= velocity_offset;
+    }
+}
+
+// Rotating disk galaxy with an exponential surface density (sigma ~ exp(-r / scale_length))
+// Particles start on circular orbits computed from the mass enclosed by their radius
+// Like real disks without a dark matter halo, cold self-gravitating disks develop bars and rings:
+// a central mass or velocity dispersion makes them steadier
+pub struct DiskGalaxy {
+    pub center: Position,
+    pub velocity: Velocity,
+    pub mass: Mass, // Mass of the disk, shared by its particles
+    pub scale_length: Length,
+    pub max_radius: Length,
+    pub central_mass: Mass, // Central black hole / bulge particle (none if 0)
+    pub dispersion: Scalar, // Random velocities, fraction of the circular speed
+    pub clockwise: bool,
+    pub color: Color,
+}
+
+impl DiskGalaxy {
+    pub fn new(center: Position, mass: Mass, scale_length: Length) -> Self {
+        Self {
+            center,
+            velocity: Vector2::zeros(),
+            mass,
+            scale_length,
+            max_radius: 4. * scale_length,
+            central_mass: 0.,
+            dispersion: 0.05,
+            clockwise: false,
+            color: Color::WHITE,
+        }
+    }
+
+    pub fn total_mass(&self) -> Mass {
+        self.mass + self.central_mass
+    }
+
+    // Adds n disk particles (plus the central one)
+    pub fn build(
+        &self,
+        n: usize,
+        gravity: &Gravity,
+        rng: &mut Pcg64Mcg,
+        particles: &mut Particles,
+    ) {
+        particles.reserve_exact(n + 1);
+        if self.central_mass > 0. {
+            particles.push(self.center, self.velocity, self.central_mass, self.color);
+        }
+        if n == 0 {
+            return;
+        }
+        let start = particles.len();
+
+        // Radii follow r * exp(-r / h): Gamma(2, h), truncated to max_radius
+        let mut radii: Vec<Length> = (0..n)
+            .map(|_| loop {
+                let (u1, u2) = (1. - rng.gen::<Scalar>(), 1. - rng.gen::<Scalar>());
+                let radius = -self.scale_length * (u1 * u2).ln();
+                if radius <= self.max_radius {
+                    break radius;
+                }
+            })
+            .collect();
+        radii.sort_by(|a, b| a.total_cmp(b));
+
+        let particle_mass = self.mass / n as Scalar;
+        let direction = if self.clockwise { -1. } else { 1. };
+
+        for (i, radius) in radii.into_iter().enumerate() {
+            // Mass inside the radius, as if spherically distributed
+            let enclosed = self.central_mass + i as Scalar * particle_mass;
+            let circular_speed = if radius > 0. {
+                (gravity.coef * enclosed / radius).sqrt()
+            } else {
+                0.
+            };
+
+            let radial = unit_vector(rng.gen::<Scalar>() * 2. * PI);
+            let dispersion =
+                Vector2::new(standard_normal(rng), standard_normal(rng)) * self.dispersion;
+            let velocity =
+                perpendicular(radial) * (direction * circular_speed) + dispersion * circular_speed;
+
+            particles.push(
+                self.center + radial * radius,
+                self.velocity + velocity,
+                particle_mass,
+                self.color,
+            );
+        }
+
+        recenter(particles, start, self.center, self.velocity);
+    }
+}
+
+// Plummer sphere (3D equilibrium cluster) projected on the plane
+// Positions and velocities are sampled in 3D then the z axis is dropped: the projected cluster
+// is close to, but not exactly at equilibrium under the planar dynamics
+pub struct PlummerSphere {
+    pub center: Position,
+    pub velocity: Velocity,
+    pub mass: Mass,
+    pub scale_radius: Length,
+    pub max_radius: Length, // Truncation of the (infinite) profile
+    pub color: Color,
+}
+
+impl PlummerSphere {
+    pub fn new(center: Position, mass: Mass, scale_radius: Length) -> Self {
+        Self {
+            center,
+            velocity: Vector2::zeros(),
+            mass,
+            scale_radius,
+            max_radius: 10. * scale_radius,
+            color: Color::WHITE,
+        }
+    }
+
+    // Isotropic 3D direction projected on the plane
+    #[inline]
+    fn projected_direction(rng: &mut Pcg64Mcg) -> Vector2<Scalar> {
+        let z = rng.gen::<Scalar>() * 2. - 1.;
+        unit_vector(rng.gen::<Scalar>() * 2. * PI) * (1. - z * z).sqrt()
+    }
+
+    pub fn build(
+        &self,
+        n: usize,
+        gravity: &Gravity,
+        rng: &mut Pcg64Mcg,
+        particles: &mut Particles,
+    ) {
+        particles.reserve_exact(n);
+        let start = particles.len();
+        let particle_mass = self.mass / n as Scalar;
+        let a = self.scale_radius;
+
+        for _ in 0..n {
+            // Inverse of the cumulative mass profile
+            let radius = loop {
+                let u: Scalar = rng.gen_range(1e-10..1.);
+                let radius = a / (u.powf(-2. / 3.) - 1.).sqrt();
+                if radius <= self.max_radius {
+                    break radius;
+                }
+            };
+
+            // Speed as a fraction q of the escape speed (Aarseth et al. rejection sampling)
+            let q = loop {
+                let q: Scalar = rng.gen();
+                if 0.1 * rng.gen::<Scalar>() < q * q * (1. - q * q).powf(3.5) {
+                    break q;
+                }
+            };
+            let escape_speed =
+                (2. * gravity.coef * self.mass).sqrt() * (radius * radius + a * a).powf(-0.25);
+
+            particles.push(
+                self.center + Self::projected_direction(rng) * radius,
+                self.velocity + Self::projected_direction(rng) * (q * escape_speed),
+                particle_mass,
+                self.color,
+            );
+        }
+
+        recenter(particles, start, self.center, self.velocity);
+    }
+}
+
+pub struct Orbit {
+    pub semi_major_axis: Length,
+    pub eccentricity: Scalar,
+    pub angle: Angle, // Direction of the periapsis, where the body starts
+    pub mass: Mass,
+    pub color: Color,
+}
+
+// Star with planets on Keplerian orbits (planets only feel the star)
+// The star moves so that the total momentum is the one of the system
+pub struct KeplerSystem {
+    pub center: Position,
+    pub velocity: Velocity,
+    pub star_mass: Mass,
+    pub star_color: Color,
+    pub orbits: Vec<Orbit>,
+    pub clockwise: bool,
+}
+
+impl KeplerSystem {
+    pub fn new(center: Position, star_mass: Mass, star_color: Color) -> Self {
+        Self {
+            center,
+            velocity: Vector2::zeros(),
+            star_mass,
+            star_color,
+            orbits: Vec::new(),
+            clockwise: false,
+        }
+    }
+
+    pub fn add_planet(
+        &mut self,
+        semi_major_axis: Length,
+        eccentricity: Scalar,
+        angle: Angle,
+        mass: Mass,
+        color: Color,
+    ) {
+        if !(0. ..1.).contains(&eccentricity) {
+            panic!("Orbit eccentricity must be in [0, 1)");
+        }
+        self.orbits.push(Orbit {
+            semi_major_axis,
+            eccentricity,
+            angle,
+            mass,
+            color,
+        });
+    }
+
+    pub fn total_mass(&self) -> Mass {
+        self.star_mass + self.orbits.iter().map(|orbit| orbit.mass).sum::<Mass>()
+    }
+
+    pub fn build(&self, gravity: &Gravity, particles: &mut Particles) {
+        particles.reserve_exact(self.orbits.len() + 1);
+        let star = particles.len();
+        particles.push(self.center, self.velocity, self.star_mass, self.star_color);
+
+        let direction = if self.clockwise { -1. } else { 1. };
+        let mut planets_momentum = Vector2::zeros();
+        for orbit in self.orbits.iter() {
+            // Vis-viva at periapsis
+            let distance = orbit.semi_major_axis * (1. - orbit.eccentricity);
+            let speed = (gravity.coef * (self.star_mass + orbit.mass) / orbit.semi_major_axis
+                * (1. + orbit.eccentricity)
+                / (1. - orbit.eccentricity))
+                .sqrt();
+
+            let radial = unit_vector(orbit.angle);
+            let velocity = perpendicular(radial) * (direction * speed);
+            planets_momentum += orbit.mass * velocity;
+
+            particles.push(
+                self.center + radial * distance,
+                self.velocity + velocity,
+                orbit.mass,
+                orbit.color,
+            );
+        }
+
+        particles.velocities[star] -= planets_momentum / self.star_mass;
+    }
+}
+
+// Two disk galaxies on a collision course, in their center of mass frame
+// The second galaxy starts at separation along x and impact_parameter along y, heading towards -x
+pub struct GalaxyCollision {
+    pub first: DiskGalaxy,
+    pub second: DiskGalaxy,
+    pub center: Position,
+    pub separation: Length,
+    pub impact_parameter: Length,
+    pub speed_factor: Scalar, // Relative speed as a fraction of the parabolic (escape) speed
+}
+
+impl GalaxyCollision {
+    pub fn new(
+        first: DiskGalaxy,
+        second: DiskGalaxy,
+        center: Position,
+        separation: Length,
+        impact_parameter: Length,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            center,
+            separation,
+            impact_parameter,
+            speed_factor: 1.,
+        }
+    }
+
+    // Builds n1 particles for the first galaxy and n2 for the second
+    pub fn build(
+        &mut self,
+        n1: usize,
+        n2: usize,
+        gravity: &Gravity,
+        rng: &mut Pcg64Mcg,
+        particles: &mut Particles,
+    ) {
+        let (m1, m2) = (self.first.total_mass(), self.second.total_mass());
+        let relative_position = Vector2::new(self.separation, self.impact_parameter);
+        let relative_speed =
+            self.speed_factor * (2. * gravity.coef * (m1 + m2) / relative_position.norm()).sqrt();
+        let relative_velocity = Vector2::new(-relative_speed, 0.);
+
+        // Center of mass frame
+        self.first.center = self.center - relative_position * (m2 / (m1 + m2));
+        self.second.center = self.center + relative_position * (m1 / (m1 + m2));
+        self.first.velocity = -relative_velocity * (m2 / (m1 + m2));
+        self.second.velocity = relative_velocity * (m1 / (m1 + m2));
+
+        self.first.build(n1, gravity, rng, particles);
+        self.second.build(n2, gravity, rng, particles);
+    }
+}
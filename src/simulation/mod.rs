@@ -1,12 +1,27 @@
 pub mod areas;
+pub mod bonds;
+pub mod colliders;
 pub mod color;
+pub mod diagnostics;
+pub mod distributions;
+pub mod emitters;
+pub mod fields;
 pub mod forces;
 pub mod generators;
+pub mod initial_conditions;
 pub mod integrator;
+pub mod neighbours;
+pub mod pair_potentials;
 pub mod particles;
+pub mod periodic;
 pub mod quadtree;
 pub mod random;
 pub mod sim_events;
 pub mod simulation;
+pub mod sources;
+pub mod sph;
+pub mod system_list;
 pub mod systems;
+pub mod thermostats;
+pub mod tunables;
 pub mod types;
@@ -0,0 +1,163 @@
+use nalgebra::Vector2;
+use rayon::prelude::*;
+
+use super::{
+    areas::Rect,
+    periodic::PeriodicBoundary,
+    types::{Length, Position, Scalar},
+};
+
+// Uniform grid of cells (cell list) for fixed radius neighbour queries
+// Particles outside of the grid rect are clamped into the border cells
+// With a periodic boundary, queries wrap around the edges and use minimum image distances
+pub struct NeighbourGrid {
+    pub rect: Rect,
+    pub cell_size: Length,
+    pub periodic: Option<PeriodicBoundary>,
+    nx: usize,
+    ny: usize,
+    cell_dims: Vector2<Scalar>,
+
+    // Particles indexes sorted by cell (counting sort)
+    particle_cells: Vec<usize>,
+    cell_starts: Vec<usize>,
+    sorted_indexes: Vec<usize>,
+}
+
+impl NeighbourGrid {
+    pub fn new(rect: Rect, cell_size: Length) -> Self {
+        if cell_size <= 0. {
+            panic!("cell_size must be positive");
+        }
+
+        let nx = ((rect.size.x / cell_size).ceil() as usize).max(1);
+        let ny = ((rect.size.y / cell_size).ceil() as usize).max(1);
+
+        Self {
+            rect,
+            cell_size,
+            periodic: None,
+            nx,
+            ny,
+            cell_dims: Vector2::new(cell_size, cell_size),
+            particle_cells: Vec::new(),
+            cell_starts: Vec::new(),
+            sorted_indexes: Vec::new(),
+        }
+    }
+
+    // The grid covers the periodic rect, cells are enlarged to tile it exactly
+    pub fn with_periodic(mut self, periodic: PeriodicBoundary) -> Self {
+        let size = periodic.rect.size;
+        self.nx = ((size.x / self.cell_size).floor() as usize).max(1);
+        self.ny = ((size.y / self.cell_size).floor() as usize).max(1);
+        self.cell_dims = Vector2::new(size.x / self.nx as Scalar, size.y / self.ny as Scalar);
+        self.rect = periodic.rect.clone();
+        self.periodic = Some(periodic);
+        self
+    }
+
+    // Unbounded cell coordinates
+    #[inline]
+    fn raw_cell_coords(&self, position: Position) -> (isize, isize) {
+        let relative = (position - self.rect.position).component_div(&self.cell_dims);
+        (relative.x.floor() as isize, relative.y.floor() as isize)
+    }
+
+    #[inline]
+    fn cell_coords(&self, position: Position) -> (usize, usize) {
+        let position = match &self.periodic {
+            Some(periodic) => periodic.wrap(position),
+            None => position,
+        };
+        let (x, y) = self.raw_cell_coords(position);
+        (
+            x.clamp(0, self.nx as isize - 1) as usize,
+            y.clamp(0, self.ny as isize - 1) as usize,
+        )
+    }
+
+    // Range of cells to visit along an axis (may exceed the grid when periodic)
+    #[inline]
+    fn cell_range(&self, low: isize, high: isize, n: usize) -> (isize, isize) {
+        let n = n as isize;
+        if self.periodic.is_none() {
+            (low.clamp(0, n - 1), high.clamp(0, n - 1))
+        } else if high - low + 1 >= n {
+            (0, n - 1)
+        } else {
+            (low, high)
+        }
+    }
+
+    pub fn insert_particles(&mut self, positions: &[Position]) {
+        let _span = tracy_client::span!("Neighbour grid insertion");
+
+        // Find the cell of each particle
+        let nx = self.nx;
+        let particle_cells: Vec<usize> = positions
+            .par_iter()
+            .map(|position| {
+                let (x, y) = self.cell_coords(*position);
+                y * nx + x
+            })
+            .collect();
+        self.particle_cells = particle_cells;
+
+        // Count particles per cell
+        self.cell_starts.clear();
+        self.cell_starts.resize(self.nx * self.ny + 1, 0);
+        for &cell in self.particle_cells.iter() {
+            self.cell_starts[cell + 1] += 1;
+        }
+
+        // Prefix sum to get the start of each cell
+        for i in 1..self.cell_starts.len() {
+            self.cell_starts[i] += self.cell_starts[i - 1];
+        }
+
+        // Distribute particles
+        let mut offsets = self.cell_starts.clone();
+        self.sorted_indexes.resize(positions.len(), 0);
+        for (i, &cell) in self.particle_cells.iter().enumerate() {
+            self.sorted_indexes[offsets[cell]] = i;
+            offsets[cell] += 1;
+        }
+    }
+
+    // Call f(index, position - other_position, distance_squared) for every particle within radius
+    // The queried particle itself is included if it belongs to the grid
+    #[inline]
+    pub fn for_each_neighbour<F: FnMut(usize, Vector2<Scalar>, Scalar)>(
+        &self,
+        positions: &[Position],
+        position: Position,
+        radius: Length,
+        mut f: F,
+    ) {
+        let radius_squared = radius * radius;
+        let offset = Vector2::new(radius, radius);
+        let (x_low, y_low) = self.raw_cell_coords(position - offset);
+        let (x_high, y_high) = self.raw_cell_coords(position + offset);
+        let (x_min, x_max) = self.cell_range(x_low, x_high, self.nx);
+        let (y_min, y_max) = self.cell_range(y_low, y_high, self.ny);
+
+        for y in y_min..=y_max {
+            let y = y.rem_euclid(self.ny as isize) as usize;
+            for x in x_min..=x_max {
+                let x = x.rem_euclid(self.nx as isize) as usize;
+                let cell = y * self.nx + x;
+                for &other in
+                    &self.sorted_indexes[self.cell_starts[cell]..self.cell_starts[cell + 1]]
+                {
+                    let distance_v =
+                        PeriodicBoundary::displacement(&self.periodic, position, positions[other]);
+                    let distance_squared = distance_v.norm_squared();
+                    if distance_squared <= radius_squared {
+                        f(other, distance_v, distance_squared);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use rayon::prelude::*;
+
+use super::{
+    areas::Rect,
+    forces::Force,
+    neighbours::NeighbourGrid,
+    particles::Particles,
+    periodic::PeriodicBoundary,
+    tunables::Tunables,
+    types::{Energy, Force as ForceType, Length, Scalar},
+};
+
+// Short range pair potential, truncated and shifted at the cutoff distance
+pub trait PairPotential: Sync {
+    fn cutoff(&self) -> Length;
+
+    // Potential energy of a pair at distance r (r <= cutoff)
+    fn energy(&self, distance: Length) -> Energy;
+
+    // Radial force magnitude (-dU/dr), positive is repulsive
+    fn force(&self, distance: Length) -> Scalar;
+
+    // Named parameters changed while running (the cutoff is fixed by the neighbour grid)
+    fn tunables(&mut self, _tunables: &mut Tunables) {}
+}
+
+#[derive(Clone)]
+pub struct LennardJones {
+    pub epsilon: Energy,
+    pub sigma: Length,
+    cutoff: Length,
+    shift: Energy,
+}
+
+impl LennardJones {
+    pub fn new(epsilon: Energy, sigma: Length, cutoff: Length) -> Self {
+        let mut potential = Self {
+            epsilon,
+            sigma,
+            cutoff,
+            shift: 0.,
+        };
+        potential.shift = potential.raw_energy(cutoff);
+        potential
+    }
+
+    #[inline]
+    fn raw_energy(&self, distance: Length) -> Energy {
+        let s6 = (self.sigma / distance).powi(6);
+        4. * self.epsilon * (s6 * s6 - s6)
+    }
+}
+
+impl PairPotential for LennardJones {
+    fn cutoff(&self) -> Length {
+        self.cutoff
+    }
+
+    #[inline]
+    fn energy(&self, distance: Length) -> Energy {
+        self.raw_energy(distance) - self.shift
+    }
+
+    #[inline]
+    fn force(&self, distance: Length) -> Scalar {
+        let s6 = (self.sigma / distance).powi(6);
+        24. * self.epsilon * (2. * s6 * s6 - s6) / distance
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        let epsilon = tunables.scalar("epsilon", &mut self.epsilon);
+        let sigma = tunables.scalar("sigma", &mut self.sigma);
+        if epsilon || sigma {
+            self.shift = self.raw_energy(self.cutoff);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Morse {
+    pub depth: Energy,
+    pub width: Scalar,
+    pub equilibrium: Length,
+    cutoff: Length,
+    shift: Energy,
+}
+
+impl Morse {
+    pub fn new(depth: Energy, width: Scalar, equilibrium: Length, cutoff: Length) -> Self {
+        let mut potential = Self {
+            depth,
+            width,
+            equilibrium,
+            cutoff,
+            shift: 0.,
+        };
+        potential.shift = potential.raw_energy(cutoff);
+        potential
+    }
+
+    #[inline]
+    fn raw_energy(&self, distance: Length) -> Energy {
+        let e = (-self.width * (distance - self.equilibrium)).exp();
+        self.depth * (e * e - 2. * e)
+    }
+}
+
+impl PairPotential for Morse {
+    fn cutoff(&self) -> Length {
+        self.cutoff
+    }
+
+    #[inline]
+    fn energy(&self, distance: Length) -> Energy {
+        self.raw_energy(distance) - self.shift
+    }
+
+    #[inline]
+    fn force(&self, distance: Length) -> Scalar {
+        let e = (-self.width * (distance - self.equilibrium)).exp();
+        2. * self.width * self.depth * (e * e - e)
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        let depth = tunables.scalar("depth", &mut self.depth);
+        let width = tunables.scalar("width", &mut self.width);
+        let equilibrium = tunables.scalar("equilibrium", &mut self.equilibrium);
+        if depth || width || equilibrium {
+            self.shift = self.raw_energy(self.cutoff);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SoftSphere {
+    pub epsilon: Energy,
+    pub sigma: Length,
+    pub power: i32,
+    cutoff: Length,
+    shift: Energy,
+}
+
+impl SoftSphere {
+    pub fn new(epsilon: Energy, sigma: Length, power: i32, cutoff: Length) -> Self {
+        let mut potential = Self {
+            epsilon,
+            sigma,
+            power,
+            cutoff,
+            shift: 0.,
+        };
+        potential.shift = potential.raw_energy(cutoff);
+        potential
+    }
+
+    #[inline]
+    fn raw_energy(&self, distance: Length) -> Energy {
+        self.epsilon * (self.sigma / distance).powi(self.power)
+    }
+}
+
+impl PairPotential for SoftSphere {
+    fn cutoff(&self) -> Length {
+        self.cutoff
+    }
+
+    #[inline]
+    fn energy(&self, distance: Length) -> Energy {
+        self.raw_energy(distance) - self.shift
+    }
+
+    #[inline]
+    fn force(&self, distance: Length) -> Scalar {
+        self.power as Scalar * self.raw_energy(distance) / distance
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        let epsilon = tunables.scalar("epsilon", &mut self.epsilon);
+        let sigma = tunables.scalar("sigma", &mut self.sigma);
+        if epsilon || sigma {
+            self.shift = self.raw_energy(self.cutoff);
+        }
+    }
+}
+
+// Applies a pair potential between all particles closer than its cutoff
+// Uses a neighbour grid with cells the size of the cutoff instead of the all-pairs loop
+pub struct PairPotentialForce<P: PairPotential> {
+    pub potential: P,
+    grid: NeighbourGrid,
+}
+
+impl<P: PairPotential> PairPotentialForce<P> {
+    pub fn new(potential: P, rect: Rect) -> Self {
+        let grid = NeighbourGrid::new(rect, potential.cutoff());
+        Self { potential, grid }
+    }
+
+    // Minimum image interactions across the edges of the periodic rect
+    pub fn with_periodic(mut self, periodic: PeriodicBoundary) -> Self {
+        self.grid = self.grid.with_periodic(periodic);
+        self
+    }
+
+    // Total potential energy of the particles
+    pub fn energy(&mut self, particles: &Particles) -> Energy {
+        self.grid.insert_particles(&particles.positions);
+
+        let potential = &self.potential;
+        let grid = &self.grid;
+        let cutoff = potential.cutoff();
+
+        let energy: Energy = particles
+            .positions
+            .par_iter()
+            .enumerate()
+            .map(|(i, position)| {
+                let mut energy = 0.;
+                grid.for_each_neighbour(
+                    &particles.positions,
+                    *position,
+                    cutoff,
+                    |j, _, distance_squared| {
+                        if j != i && distance_squared > 0. {
+                            energy += potential.energy(distance_squared.sqrt());
+                        }
+                    },
+                );
+                energy
+            })
+            .sum();
+
+        // Each pair was counted twice
+        energy / 2.
+    }
+}
+
+impl<P: PairPotential> Force for PairPotentialForce<P> {
+    fn apply(&mut self, particles: &Particles, forces: &mut Vec<ForceType>) {
+        self.grid.insert_particles(&particles.positions);
+
+        let potential = &self.potential;
+        let grid = &self.grid;
+        let cutoff = potential.cutoff();
+
+        // Every pair is computed twice (once per particle) to avoid synchronization
+        forces
+            .par_iter_mut()
+            .zip(particles.positions.par_iter())
+            .enumerate()
+            .for_each(|(i, (force, position))| {
+                grid.for_each_neighbour(
+                    &particles.positions,
+                    *position,
+                    cutoff,
+                    |j, distance_v, distance_squared| {
+                        if j != i && distance_squared > 0. {
+                            let distance = distance_squared.sqrt();
+                            *force += distance_v * (potential.force(distance) / distance);
+                        }
+                    },
+                );
+            });
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        self.potential.tunables(tunables);
+    }
+
+    fn potential_energy(&mut self, particles: &Particles) -> Option<Energy> {
+        Some(self.energy(particles))
+    }
+}
@@ -1,9 +1,12 @@
+use std::{mem, ops::BitOr};
+
 use super::{
     color::Color,
     generators::Generator,
     types::{Mass, Position, Velocity},
 };
 
+#[derive(Clone)]
 pub struct Particles {
     pub positions: Vec<Position>,
     pub velocities: Vec<Velocity>,
@@ -30,8 +33,20 @@ impl Particles {
         Self::new(Vec::new(), Vec::new(), Vec::new(), Vec::new())
     }
 
+    // Same with the columns moved out by take_columns, taken ones or what is left
     pub fn len(&self) -> usize {
-        self.positions.len()
+        self.positions
+            .len()
+            .max(self.velocities.len())
+            .max(self.masses.len())
+            .max(self.colors.len())
+    }
+
+    pub fn push(&mut self, position: Position, velocity: Velocity, mass: Mass, color: Color) {
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.masses.push(mass);
+        self.colors.push(color);
     }
 
     pub fn swap_remove(&mut self, i: usize) {
@@ -41,6 +56,14 @@ impl Particles {
         self.colors.swap_remove(i);
     }
 
+    // Moves the particles of other at the end, leaving other empty
+    pub fn append(&mut self, other: &mut Particles) {
+        self.positions.append(&mut other.positions);
+        self.velocities.append(&mut other.velocities);
+        self.masses.append(&mut other.masses);
+        self.colors.append(&mut other.colors);
+    }
+
     pub fn clear(&mut self) {
         self.positions.clear();
         self.velocities.clear();
@@ -72,6 +95,62 @@ impl Particles {
             self.colors.push(particles.colors[i]);
         });
     }
+
+    // Moves the columns out, the others are empty in the result but len still gives the count
+    pub fn take_columns(&mut self, columns: Columns) -> Particles {
+        let mut taken = Particles::new_empty();
+        self.swap_columns(&mut taken, columns);
+        taken
+    }
+
+    // Puts back columns moved out by take_columns
+    pub fn restore_columns(&mut self, mut taken: Particles, columns: Columns) {
+        self.swap_columns(&mut taken, columns);
+    }
+
+    fn swap_columns(&mut self, other: &mut Particles, columns: Columns) {
+        if columns.contains(Columns::POSITIONS) {
+            mem::swap(&mut self.positions, &mut other.positions);
+        }
+        if columns.contains(Columns::VELOCITIES) {
+            mem::swap(&mut self.velocities, &mut other.velocities);
+        }
+        if columns.contains(Columns::MASSES) {
+            mem::swap(&mut self.masses, &mut other.masses);
+        }
+        if columns.contains(Columns::COLORS) {
+            mem::swap(&mut self.colors, &mut other.colors);
+        }
+    }
+}
+
+// Set of particle columns, systems adding or removing particles touch all of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Columns(u8);
+
+impl Columns {
+    pub const NONE: Self = Self(0);
+    pub const POSITIONS: Self = Self(1);
+    pub const VELOCITIES: Self = Self(1 << 1);
+    pub const MASSES: Self = Self(1 << 2);
+    pub const COLORS: Self = Self(1 << 3);
+    pub const ALL: Self = Self(0b1111);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Columns {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
 }
 
 pub trait ParticleFactory {
@@ -99,16 +178,24 @@ impl GeneratorFactory {
             color_generator,
         }
     }
-}
 
-impl ParticleFactory for GeneratorFactory {
-    fn create(&mut self, n: usize, particles: &mut Particles) {
+    // Fails without adding anything when the positions run out (e.g. a Poisson disk too sparse)
+    pub fn try_create(&mut self, n: usize, particles: &mut Particles) -> Result<(), String> {
         let _span = tracy_client::span!("Particle Factory");
         self.position_generator
-            .generate_n(n, &mut particles.positions);
+            .try_generate_n(n, &mut particles.positions)?;
         self.velocity_generator
             .generate_n(n, &mut particles.velocities);
         self.mass_generator.generate_n(n, &mut particles.masses);
         self.color_generator.generate_n(n, &mut particles.colors);
+        Ok(())
+    }
+}
+
+impl ParticleFactory for GeneratorFactory {
+    fn create(&mut self, n: usize, particles: &mut Particles) {
+        if let Err(e) = self.try_create(n, particles) {
+            panic!("{}", e);
+        }
     }
 }
@@ -0,0 +1,52 @@
+use nalgebra::Vector2;
+
+use super::{
+    areas::Rect,
+    types::{Position, Scalar},
+};
+
+// Periodic boundary conditions over a rect (the simulation space is a torus)
+#[derive(Clone)]
+pub struct PeriodicBoundary {
+    pub rect: Rect,
+}
+
+impl PeriodicBoundary {
+    pub fn new(rect: Rect) -> Self {
+        Self { rect }
+    }
+
+    // Bring a position back inside the rect
+    #[inline]
+    pub fn wrap(&self, position: Position) -> Position {
+        let relative = position - self.rect.position;
+        self.rect.position
+            + Vector2::new(
+                relative.x.rem_euclid(self.rect.size.x),
+                relative.y.rem_euclid(self.rect.size.y),
+            )
+    }
+
+    // Shortest equivalent of a distance vector (minimum image convention)
+    #[inline]
+    pub fn min_image(&self, distance_v: Vector2<Scalar>) -> Vector2<Scalar> {
+        let size = self.rect.size;
+        Vector2::new(
+            distance_v.x - size.x * (distance_v.x / size.x).round(),
+            distance_v.y - size.y * (distance_v.y / size.y).round(),
+        )
+    }
+
+    // pos1 - pos2 with an optional periodic boundary
+    #[inline]
+    pub fn displacement(
+        periodic: &Option<Self>,
+        pos1: Position,
+        pos2: Position,
+    ) -> Vector2<Scalar> {
+        match periodic {
+            Some(periodic) => periodic.min_image(pos1 - pos2),
+            None => pos1 - pos2,
+        }
+    }
+}
@@ -7,7 +7,9 @@ use super::{
     areas::{Area, Rect},
     forces::{Drag, Force as ForceTrait, Gravity, Repulsion},
     particles::Particles,
-    types::{Force, Mass, Position, Velocity},
+    periodic::PeriodicBoundary,
+    tunables::Tunables,
+    types::{Energy, Force, Mass, Position, Velocity},
 };
 
 pub struct QuadTreeNode {
@@ -169,6 +171,8 @@ pub struct QuadTree {
     // Max depth behavior
     max_depth: Option<usize>,
     max_depth_panics: bool,
+
+    periodic: Option<PeriodicBoundary>,
 }
 
 impl QuadTree {
@@ -192,9 +196,32 @@ impl QuadTree {
             theta,
             max_depth,
             max_depth_panics,
+            periodic: None,
         }
     }
 
+    // Forces use minimum image distances (the rect should match the periodic rect)
+    pub fn with_periodic(mut self, periodic: PeriodicBoundary) -> Self {
+        self.gravity = self.gravity.with_periodic(periodic.clone());
+        self.repulsion = self.repulsion.with_periodic(periodic.clone());
+        self.drag = self.drag.with_periodic(periodic.clone());
+        self.periodic = Some(periodic);
+        self
+    }
+
+    pub fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("theta", &mut self.theta);
+        tunables.nested("gravity", |tunables| self.gravity.tunables(tunables));
+        tunables.nested("repulsion", |tunables| self.repulsion.tunables(tunables));
+        tunables.nested("drag", |tunables| self.drag.tunables(tunables));
+    }
+
+    // Exact gravity & repulsion energy over all pairs, not the Barnes-Hut approximation
+    pub fn potential_energy(&mut self, particles: &Particles) -> Energy {
+        self.gravity.potential_energy(particles).unwrap_or(0.)
+            + self.repulsion.potential_energy(particles).unwrap_or(0.)
+    }
+
     pub fn insert_particles(&mut self, particles: &Particles) {
         // Insert particles (will prune the tree if necessary)
         self.root.insert_particles(
@@ -232,6 +259,7 @@ impl QuadTree {
         gravity: &Gravity,
         repulsion: &Repulsion,
         drag: &Drag,
+        periodic: &Option<PeriodicBoundary>,
         theta: f64,
         max_depth: usize,
         particle: usize,
@@ -268,7 +296,10 @@ impl QuadTree {
                     *force += repulsion.calc_force(pos, other_pos);
                     *force += drag.calc_force(pos, other_pos, vel, other_vel);
                 }
-            } else if (node.scale / (node.center_of_mass - pos).norm()) < theta {
+            } else if (node.scale
+                / PeriodicBoundary::displacement(periodic, node.center_of_mass, pos).norm())
+                < theta
+            {
                 // Barnes-Hut criterion satisfied: Approximate the force
                 approx += 1;
                 *force += gravity.calc_force(pos, node.center_of_mass, mass, node.total_mass);
@@ -304,6 +335,7 @@ impl QuadTree {
                 &self.gravity,
                 &self.repulsion,
                 &self.drag,
+                &self.periodic,
                 self.theta,
                 max_depth,
                 i,
@@ -329,4 +361,12 @@ impl ForceTrait for QuadtreeForces {
         let mut quadtree = self.quadtree.write().unwrap();
         quadtree.barnes_hut_particles(particles, forces);
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        self.quadtree.write().unwrap().tunables(tunables);
+    }
+
+    fn potential_energy(&mut self, particles: &Particles) -> Option<Energy> {
+        Some(self.quadtree.write().unwrap().potential_energy(particles))
+    }
 }
@@ -1,5 +1,6 @@
 use rand::{RngCore, SeedableRng};
 use rand_pcg::Pcg64Mcg;
+use rayon::prelude::*;
 
 pub struct RngGenerator {
     rng: Pcg64Mcg,
@@ -19,3 +20,42 @@ impl RngGenerator {
         Pcg64Mcg::from_seed(seed)
     }
 }
+
+// Number of values generated with the same rng stream in parallel batches
+pub const CHUNK_SIZE: usize = 1024;
+
+#[inline]
+fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Independent rng stream for one chunk of a batch, only depending on the batch seed and chunk index
+pub fn chunk_rng(seed: u128, chunk: usize) -> Pcg64Mcg {
+    let chunk = splitmix64(chunk as u64);
+    let low = splitmix64(seed as u64 ^ chunk);
+    let high = splitmix64((seed >> 64) as u64 ^ chunk.rotate_left(32));
+    Pcg64Mcg::new(((high as u128) << 64) | low as u128)
+}
+
+// Appends n values to vec in parallel, chunk by chunk, each chunk with its own rng stream
+// derived from a single draw of rng: the output is the same whatever the number of threads
+pub fn par_generate_n<T: Send, F: Fn(&mut Pcg64Mcg) -> T + Sync>(
+    rng: &mut Pcg64Mcg,
+    n: usize,
+    vec: &mut Vec<T>,
+    f: F,
+) {
+    let seed = ((rng.next_u64() as u128) << 64) | rng.next_u64() as u128;
+    let chunks = n.div_ceil(CHUNK_SIZE);
+
+    vec.reserve_exact(n);
+    vec.par_extend((0..chunks).into_par_iter().flat_map_iter(|chunk| {
+        let mut rng = chunk_rng(seed, chunk);
+        let size = CHUNK_SIZE.min(n - chunk * CHUNK_SIZE);
+        let f = &f;
+        (0..size).map(move |_| f(&mut rng))
+    }));
+}
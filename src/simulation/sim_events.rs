@@ -1,60 +1,307 @@
-use super::{particles::Particles, systems::System, types::Time};
-use crate::utils::sorted_vec::SortedVec;
+use rayon::prelude::*;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use super::{
+    areas::Area,
+    diagnostics::kinetic_energy,
+    particles::Particles,
+    system_list::SystemList,
+    types::{Energy, Time},
+};
+
+// Requests of events to the app running the simulation, applied after the step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppCommand {
+    Pause,
+    Resume,
+    Stop,
+    SetStepsPerFrame(usize),
+}
+
+// What an event callback can see & change
+pub struct SimEventContext<'a> {
+    pub particles: &'a mut Particles,
+    pub systems: &'a mut SystemList,
+    pub time: Time, // Simulated time at the end of the step
+    pub dt: Time,
+    scheduled: &'a mut Vec<SimEvent>,
+    commands: &'a mut Vec<AppCommand>,
+    cancelled: bool,
+}
+
+impl SimEventContext<'_> {
+    // Added to the handler after the callback, fired in this step if already due
+    pub fn schedule(&mut self, event: SimEvent) {
+        self.scheduled.push(event);
+    }
+
+    pub fn command(&mut self, command: AppCommand) {
+        self.commands.push(command);
+    }
+
+    // The event firing won't fire again
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+    }
+}
+
+type SimEventCallback = Box<dyn FnMut(&mut SimEventContext)>;
+
+// Checked after each step, given the particles & the simulated time
+pub type Condition = Box<dyn FnMut(&Particles, Time) -> bool>;
+
+pub enum Trigger {
+    At(Time),
+    Every {
+        next: Time,
+        period: Time,
+    },
+    // When the condition becomes true (not while it stays true)
+    When {
+        condition: Condition,
+        was_true: bool,
+    },
+}
 
-type SimEventCallback = Box<dyn Fn(&mut Particles, &mut Vec<Box<dyn System>>)>;
 pub struct SimEvent {
-    pub time: Time,
+    pub trigger: Trigger,
     pub callback: SimEventCallback,
+    pub remaining: Option<usize>, // Times it can still fire, unlimited if None
 }
 
 impl SimEvent {
+    // Fires once
     pub fn new(time: Time, callback: SimEventCallback) -> Self {
-        Self { time, callback }
+        Self {
+            trigger: Trigger::At(time),
+            callback,
+            remaining: Some(1),
+        }
     }
-}
 
-impl PartialEq for SimEvent {
-    fn eq(&self, _other: &Self) -> bool {
-        false
+    // Fires at start, then every period
+    pub fn every(start: Time, period: Time, callback: SimEventCallback) -> Self {
+        assert!(period > 0., "Event period must be strictly positive");
+        Self {
+            trigger: Trigger::Every {
+                next: start,
+                period,
+            },
+            callback,
+            remaining: None,
+        }
     }
-}
 
-// Sort from latest to earliest
-impl PartialOrd for SimEvent {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.time.partial_cmp(&self.time)
+    // Fires each time the condition becomes true, also if it is true from the start
+    pub fn when(condition: Condition, callback: SimEventCallback) -> Self {
+        Self {
+            trigger: Trigger::When {
+                condition,
+                was_true: false,
+            },
+            callback,
+            remaining: None,
+        }
+    }
+
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
     }
+
+    // Time of the next firing for timed events
+    fn due_time(&self) -> Option<Time> {
+        match self.trigger {
+            Trigger::At(time) => Some(time),
+            Trigger::Every { next, .. } => Some(next),
+            Trigger::When { .. } => None,
+        }
+    }
+
+    // After a firing, false when finished
+    fn advance(&mut self, cancelled: bool) -> bool {
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(1);
+        }
+        if let Trigger::Every { next, period } = &mut self.trigger {
+            *next += *period;
+        }
+        let once = matches!(self.trigger, Trigger::At(_));
+        !cancelled && !once && self.remaining != Some(0)
+    }
+}
+
+pub fn particle_count_above(count: usize) -> Condition {
+    Box::new(move |particles, _| particles.len() > count)
+}
+
+pub fn particle_count_below(count: usize) -> Condition {
+    Box::new(move |particles, _| particles.len() < count)
+}
+
+pub fn kinetic_energy_above(energy: Energy) -> Condition {
+    Box::new(move |particles, _| kinetic_energy(particles) > energy)
+}
+
+pub fn kinetic_energy_below(energy: Energy) -> Condition {
+    Box::new(move |particles, _| kinetic_energy(particles) < energy)
+}
+
+// Any particle in the area
+pub fn particle_in_area(area: Box<dyn Area>) -> Condition {
+    Box::new(move |particles, _| {
+        particles
+            .positions
+            .par_iter()
+            .any(|position| area.contain(*position))
+    })
 }
 
 pub trait SimEventsHandler {
-    fn update(&mut self, particles: &mut Particles, systems: &mut Vec<Box<dyn System>>, dt: Time);
+    fn update(&mut self, particles: &mut Particles, systems: &mut SystemList, dt: Time);
+
+    // Commands of the events since the last call
+    fn take_commands(&mut self) -> Vec<AppCommand> {
+        Vec::new()
+    }
+}
+
+// Events added from outside the handler (e.g. by scripts), taken by it before each update, with
+// the simulated time of the handler
+#[derive(Clone, Default)]
+pub struct SimEventQueue {
+    events: Rc<RefCell<Vec<SimEvent>>>,
+    time: Rc<Cell<Time>>,
+}
+
+impl SimEventQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&self, event: SimEvent) {
+        self.events.borrow_mut().push(event);
+    }
+
+    pub fn time(&self) -> Time {
+        self.time.get()
+    }
 }
 
 pub struct DefaultSimEventsHandler {
-    pub events: SortedVec<SimEvent>,
+    pub events: Vec<SimEvent>,
     pub current_time: Time,
+    queue: SimEventQueue,
+    commands: Vec<AppCommand>,
 }
 
 impl DefaultSimEventsHandler {
-    pub fn new(events: SortedVec<SimEvent>, current_time: Time) -> Self {
+    pub fn new(events: Vec<SimEvent>, current_time: Time) -> Self {
         Self {
             events,
             current_time,
+            queue: SimEventQueue::new(),
+            commands: Vec::new(),
         }
     }
+
+    // Shares a queue created before the handler
+    pub fn with_queue(mut self, queue: SimEventQueue) -> Self {
+        queue.time.set(self.current_time);
+        self.queue = queue;
+        self
+    }
+
+    pub fn add(&mut self, event: SimEvent) {
+        self.events.push(event);
+    }
+
+    pub fn queue(&self) -> SimEventQueue {
+        self.queue.clone()
+    }
+
+    // Returns false when the event is finished
+    fn fire(
+        &mut self,
+        event: &mut SimEvent,
+        particles: &mut Particles,
+        systems: &mut SystemList,
+        dt: Time,
+    ) -> bool {
+        let mut scheduled = Vec::new();
+        let mut context = SimEventContext {
+            particles,
+            systems,
+            time: self.current_time,
+            dt,
+            scheduled: &mut scheduled,
+            commands: &mut self.commands,
+            cancelled: false,
+        };
+        (event.callback)(&mut context);
+        let cancelled = context.cancelled;
+
+        self.events.append(&mut scheduled);
+        event.advance(cancelled)
+    }
 }
 
 impl SimEventsHandler for DefaultSimEventsHandler {
-    fn update(&mut self, particles: &mut Particles, systems: &mut Vec<Box<dyn System>>, dt: Time) {
+    fn update(&mut self, particles: &mut Particles, systems: &mut SystemList, dt: Time) {
         self.current_time += dt;
+        self.queue.time.set(self.current_time);
+        self.events.append(&mut self.queue.events.borrow_mut());
 
-        while let Some(event) = self.events.first() {
-            if event.time > self.current_time {
+        // Timed events in time order, recurring ones catch up when their period is below dt
+        loop {
+            let due = self
+                .events
+                .iter()
+                .enumerate()
+                .filter_map(|(i, event)| Some((i, event.due_time()?)))
+                .filter(|(_, time)| *time <= self.current_time)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+            let Some((i, _)) = due else {
                 break;
+            };
+
+            let mut event = self.events.remove(i);
+            if self.fire(&mut event, particles, systems, dt) {
+                self.events.insert(i, event);
             }
+        }
 
-            (event.callback)(particles, systems);
-            self.events.pop();
+        // Conditions, on their rising edge
+        let mut i = 0;
+        while i < self.events.len() {
+            let fired = match &mut self.events[i].trigger {
+                Trigger::When {
+                    condition,
+                    was_true,
+                } => {
+                    let is_true = condition(particles, self.current_time);
+                    let fired = is_true && !*was_true;
+                    *was_true = is_true;
+                    fired
+                }
+                _ => false,
+            };
+
+            if fired {
+                let mut event = self.events.remove(i);
+                if !self.fire(&mut event, particles, systems, dt) {
+                    continue;
+                }
+                self.events.insert(i, event);
+            }
+            i += 1;
         }
     }
+
+    fn take_commands(&mut self) -> Vec<AppCommand> {
+        std::mem::take(&mut self.commands)
+    }
 }
@@ -1,20 +1,25 @@
-use super::{particles::Particles, sim_events::SimEventsHandler, systems::System, types::Time};
+use super::{
+    particles::Particles,
+    sim_events::{AppCommand, SimEventsHandler},
+    system_list::SystemList,
+    types::Time,
+};
 
 pub struct Simulation {
     pub particles: Particles,
-    pub systems: Vec<Box<dyn System>>,
+    pub systems: SystemList,
     pub event_handler: Option<Box<dyn SimEventsHandler>>,
 }
 
 impl Simulation {
     pub fn new(
         particles: Particles,
-        systems: Vec<Box<dyn System>>,
+        systems: impl Into<SystemList>,
         event_handler: Option<Box<dyn SimEventsHandler>>,
     ) -> Self {
         Self {
             particles,
-            systems,
+            systems: systems.into(),
             event_handler,
         }
     }
@@ -27,10 +32,14 @@ impl Simulation {
         }
 
         // Update systems
-        for (i, system) in &mut self.systems.iter_mut().enumerate() {
-            let span = tracy_client::span!("System");
-            span.emit_text(&format!("[{}] {}", i, system.type_name()));
-            system.update(&mut self.particles, dt);
+        self.systems.update(&mut self.particles, dt);
+    }
+
+    // Requests of the events to the app since the last call
+    pub fn take_commands(&mut self) -> Vec<AppCommand> {
+        match &mut self.event_handler {
+            Some(event_handler) => event_handler.take_commands(),
+            None => Vec::new(),
         }
     }
 }
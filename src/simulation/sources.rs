@@ -0,0 +1,350 @@
+use std::sync::{Arc, RwLock};
+
+use nalgebra::Vector2;
+use rayon::prelude::*;
+
+use super::{
+    forces::Force,
+    particles::Particles,
+    types::{Force as ForceType, Length, Mass, Position, Scalar, Time, Velocity},
+};
+
+// Source of force placed in the scene, acting on all particles without being a particle itself
+pub trait FieldSource: Send + Sync {
+    fn calc_force(&self, position: Position, velocity: Velocity, mass: Mass) -> ForceType;
+
+    fn position(&self) -> Position;
+
+    fn set_position(&mut self, position: Position);
+
+    // Called once per step (moving sources)
+    fn update(&mut self, _dt: Time) {}
+}
+
+// Softened distance & direction from position to source (None when out of range)
+#[inline]
+fn towards(
+    source: Position,
+    position: Position,
+    epsilon: Length,
+    range: Option<Length>,
+) -> Option<(Vector2<Scalar>, Length)> {
+    let distance_v = source - position;
+    let distance_squared = distance_v.norm_squared();
+
+    if let Some(range) = range {
+        if distance_squared > range * range {
+            return None;
+        }
+    }
+    if distance_squared == 0. {
+        return None;
+    }
+
+    let distance = distance_squared.sqrt();
+    let softened = (distance_squared + epsilon * epsilon).sqrt();
+    Some((distance_v / distance, softened))
+}
+
+// Attracts (positive strength) or repels (negative strength) particles
+// Acceleration: strength / distance^falloff
+#[derive(Clone)]
+pub struct RadialSource {
+    pub position: Position,
+    pub strength: Scalar,
+    pub falloff: i32,
+    pub epsilon: Length,
+    pub range: Option<Length>,
+}
+
+impl RadialSource {
+    pub fn new(
+        position: Position,
+        strength: Scalar,
+        falloff: i32,
+        epsilon: Length,
+        range: Option<Length>,
+    ) -> Self {
+        Self {
+            position,
+            strength,
+            falloff,
+            epsilon,
+            range,
+        }
+    }
+}
+
+impl FieldSource for RadialSource {
+    #[inline]
+    fn calc_force(&self, position: Position, _velocity: Velocity, mass: Mass) -> ForceType {
+        match towards(self.position, position, self.epsilon, self.range) {
+            Some((direction, distance)) => {
+                direction * (mass * self.strength / distance.powi(self.falloff))
+            }
+            None => ForceType::zeros(),
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    fn set_position(&mut self, position: Position) {
+        self.position = position;
+    }
+}
+
+// Spins particles around the source (counter-clockwise for positive strength)
+// Acceleration: strength / distance^falloff, perpendicular to the source direction
+#[derive(Clone)]
+pub struct VortexSource {
+    pub position: Position,
+    pub strength: Scalar,
+    pub falloff: i32,
+    pub epsilon: Length,
+    pub range: Option<Length>,
+}
+
+impl VortexSource {
+    pub fn new(
+        position: Position,
+        strength: Scalar,
+        falloff: i32,
+        epsilon: Length,
+        range: Option<Length>,
+    ) -> Self {
+        Self {
+            position,
+            strength,
+            falloff,
+            epsilon,
+            range,
+        }
+    }
+}
+
+impl FieldSource for VortexSource {
+    #[inline]
+    fn calc_force(&self, position: Position, _velocity: Velocity, mass: Mass) -> ForceType {
+        match towards(self.position, position, self.epsilon, self.range) {
+            Some((direction, distance)) => {
+                let tangent = Vector2::new(direction.y, -direction.x);
+                tangent * (mass * self.strength / distance.powi(self.falloff))
+            }
+            None => ForceType::zeros(),
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    fn set_position(&mut self, position: Position) {
+        self.position = position;
+    }
+}
+
+// Point mass gravity well (strength = G * M)
+// With a positive circularization, particles are also pushed towards circular orbits
+#[derive(Clone)]
+pub struct WellSource {
+    pub position: Position,
+    pub strength: Scalar,
+    pub circularization: Scalar,
+    pub epsilon: Length,
+}
+
+impl WellSource {
+    pub fn new(
+        position: Position,
+        strength: Scalar,
+        circularization: Scalar,
+        epsilon: Length,
+    ) -> Self {
+        Self {
+            position,
+            strength,
+            circularization,
+            epsilon,
+        }
+    }
+}
+
+impl FieldSource for WellSource {
+    #[inline]
+    fn calc_force(&self, position: Position, velocity: Velocity, mass: Mass) -> ForceType {
+        let (direction, distance) = match towards(self.position, position, self.epsilon, None) {
+            Some(towards) => towards,
+            None => return ForceType::zeros(),
+        };
+
+        let mut force = direction * (mass * self.strength / (distance * distance));
+
+        if self.circularization != 0. {
+            // Circular orbit velocity, keeping the current rotation direction
+            let tangent = Vector2::new(direction.y, -direction.x);
+            let sign = if velocity.dot(&tangent) < 0. { -1. } else { 1. };
+            let orbit_velocity = tangent * (sign * (self.strength / distance).sqrt());
+            force += mass * self.circularization * (orbit_velocity - velocity);
+        }
+
+        force
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    fn set_position(&mut self, position: Position) {
+        self.position = position;
+    }
+}
+
+// Radial source acting from the closest point of a segment
+#[derive(Clone)]
+pub struct LineSource {
+    pub start: Position,
+    pub end: Position,
+    pub strength: Scalar,
+    pub falloff: i32,
+    pub epsilon: Length,
+    pub range: Option<Length>,
+}
+
+impl LineSource {
+    pub fn new(
+        start: Position,
+        end: Position,
+        strength: Scalar,
+        falloff: i32,
+        epsilon: Length,
+        range: Option<Length>,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            strength,
+            falloff,
+            epsilon,
+            range,
+        }
+    }
+
+    #[inline]
+    fn closest_point(&self, position: Position) -> Position {
+        let segment = self.end - self.start;
+        let length_squared = segment.norm_squared();
+        if length_squared == 0. {
+            return self.start;
+        }
+        let t = ((position - self.start).dot(&segment) / length_squared).clamp(0., 1.);
+        self.start + t * segment
+    }
+}
+
+impl FieldSource for LineSource {
+    #[inline]
+    fn calc_force(&self, position: Position, _velocity: Velocity, mass: Mass) -> ForceType {
+        let closest = self.closest_point(position);
+        match towards(closest, position, self.epsilon, self.range) {
+            Some((direction, distance)) => {
+                direction * (mass * self.strength / distance.powi(self.falloff))
+            }
+            None => ForceType::zeros(),
+        }
+    }
+
+    // Center of the segment
+    fn position(&self) -> Position {
+        (self.start + self.end) / 2.
+    }
+
+    fn set_position(&mut self, position: Position) {
+        let offset = position - self.position();
+        self.start += offset;
+        self.end += offset;
+    }
+}
+
+pub type SourcePath = Box<dyn Fn(Time) -> Position + Send + Sync>;
+
+// Moves a source along a path (position as a function of time)
+pub struct MovingSource {
+    pub source: Box<dyn FieldSource>,
+    pub path: SourcePath,
+    pub time: Time,
+}
+
+impl MovingSource {
+    pub fn new(mut source: Box<dyn FieldSource>, path: SourcePath) -> Self {
+        source.set_position(path(0.));
+        Self {
+            source,
+            path,
+            time: 0.,
+        }
+    }
+}
+
+impl FieldSource for MovingSource {
+    #[inline]
+    fn calc_force(&self, position: Position, velocity: Velocity, mass: Mass) -> ForceType {
+        self.source.calc_force(position, velocity, mass)
+    }
+
+    fn position(&self) -> Position {
+        self.source.position()
+    }
+
+    fn set_position(&mut self, position: Position) {
+        self.source.set_position(position);
+    }
+
+    fn update(&mut self, dt: Time) {
+        self.time += dt;
+        self.source.set_position((self.path)(self.time));
+        self.source.update(dt);
+    }
+}
+
+// Shared with input callbacks so sources can be added or moved at runtime
+pub type SharedSources = Arc<RwLock<Vec<Box<dyn FieldSource>>>>;
+
+pub struct FieldSources {
+    pub sources: SharedSources,
+}
+
+impl FieldSources {
+    pub fn new(sources: SharedSources) -> Self {
+        Self { sources }
+    }
+}
+
+impl Force for FieldSources {
+    fn update(&mut self, dt: Time) {
+        let mut sources = self.sources.write().unwrap();
+        for source in sources.iter_mut() {
+            source.update(dt);
+        }
+    }
+
+    fn apply(&mut self, particles: &Particles, forces: &mut Vec<ForceType>) {
+        let sources = self.sources.read().unwrap();
+        if sources.is_empty() {
+            return;
+        }
+
+        particles
+            .positions
+            .par_iter()
+            .zip(particles.velocities.par_iter())
+            .zip(particles.masses.par_iter())
+            .zip(forces.par_iter_mut())
+            .for_each(|(((position, velocity), mass), force)| {
+                for source in sources.iter() {
+                    *force += source.calc_force(*position, *velocity, *mass);
+                }
+            });
+    }
+}
@@ -0,0 +1,308 @@
+use std::f64::consts::PI;
+
+use nalgebra::Vector2;
+use rayon::prelude::*;
+
+use super::{
+    areas::Rect,
+    neighbours::NeighbourGrid,
+    particles::Particles,
+    periodic::PeriodicBoundary,
+    systems::System,
+    tunables::Tunables,
+    types::{Acceleration, Length, Scalar, Time},
+};
+
+// 2D smoothing kernel with support radius h
+pub trait Kernel: Sync {
+    fn value(&self, distance: Length, h: Length) -> Scalar;
+
+    // Derivative of the kernel along the radius (dW/dr)
+    fn derivative(&self, distance: Length, h: Length) -> Scalar;
+}
+
+pub struct Poly6Kernel;
+
+impl Kernel for Poly6Kernel {
+    #[inline]
+    fn value(&self, distance: Length, h: Length) -> Scalar {
+        if distance >= h {
+            return 0.;
+        }
+        4. / (PI * h.powi(8)) * (h * h - distance * distance).powi(3)
+    }
+
+    #[inline]
+    fn derivative(&self, distance: Length, h: Length) -> Scalar {
+        if distance >= h {
+            return 0.;
+        }
+        -24. / (PI * h.powi(8)) * distance * (h * h - distance * distance).powi(2)
+    }
+}
+
+pub struct SpikyKernel;
+
+impl Kernel for SpikyKernel {
+    #[inline]
+    fn value(&self, distance: Length, h: Length) -> Scalar {
+        if distance >= h {
+            return 0.;
+        }
+        10. / (PI * h.powi(5)) * (h - distance).powi(3)
+    }
+
+    #[inline]
+    fn derivative(&self, distance: Length, h: Length) -> Scalar {
+        if distance >= h {
+            return 0.;
+        }
+        -30. / (PI * h.powi(5)) * (h - distance).powi(2)
+    }
+}
+
+// Cubic B-spline (Monaghan), rescaled to a support radius of h
+pub struct CubicSplineKernel;
+
+impl Kernel for CubicSplineKernel {
+    #[inline]
+    fn value(&self, distance: Length, h: Length) -> Scalar {
+        let q = 2. * distance / h;
+        let sigma = 40. / (7. * PI * h * h);
+        if q < 1. {
+            sigma * (1. - 1.5 * q * q + 0.75 * q.powi(3))
+        } else if q < 2. {
+            sigma * 0.25 * (2. - q).powi(3)
+        } else {
+            0.
+        }
+    }
+
+    #[inline]
+    fn derivative(&self, distance: Length, h: Length) -> Scalar {
+        let q = 2. * distance / h;
+        let sigma = 40. / (7. * PI * h * h) * 2. / h;
+        if q < 1. {
+            sigma * (-3. * q + 2.25 * q * q)
+        } else if q < 2. {
+            sigma * -0.75 * (2. - q).powi(2)
+        } else {
+            0.
+        }
+    }
+}
+
+pub trait EquationOfState: Sync {
+    fn pressure(&self, density: Scalar) -> Scalar;
+}
+
+// p = k * (rho - rho_0)
+pub struct IdealGas {
+    pub stiffness: Scalar,
+    pub rest_density: Scalar,
+}
+
+impl IdealGas {
+    pub fn new(stiffness: Scalar, rest_density: Scalar) -> Self {
+        Self {
+            stiffness,
+            rest_density,
+        }
+    }
+}
+
+impl EquationOfState for IdealGas {
+    #[inline]
+    fn pressure(&self, density: Scalar) -> Scalar {
+        self.stiffness * (density - self.rest_density)
+    }
+}
+
+// Weakly compressible fluid: p = B * ((rho / rho_0)^gamma - 1) with B = rho_0 * c^2 / gamma
+pub struct Tait {
+    pub rest_density: Scalar,
+    pub speed_of_sound: Scalar,
+    pub gamma: i32,
+}
+
+impl Tait {
+    pub fn new(rest_density: Scalar, speed_of_sound: Scalar, gamma: i32) -> Self {
+        Self {
+            rest_density,
+            speed_of_sound,
+            gamma,
+        }
+    }
+}
+
+impl EquationOfState for Tait {
+    #[inline]
+    fn pressure(&self, density: Scalar) -> Scalar {
+        let b = self.rest_density * self.speed_of_sound.powi(2) / self.gamma as Scalar;
+        b * ((density / self.rest_density).powi(self.gamma) - 1.)
+    }
+}
+
+// Smoothed-particle hydrodynamics
+// Computes density & pressure of each particle then integrates pressure and viscosity forces in velocities
+pub struct Sph {
+    pub smoothing_length: Length,
+    pub viscosity: Scalar,
+    pub equation_of_state: Box<dyn EquationOfState>,
+    pub density_kernel: Box<dyn Kernel>,
+    pub pressure_kernel: Box<dyn Kernel>,
+
+    // Per particle state (indexed like particles)
+    pub densities: Vec<Scalar>,
+    pub pressures: Vec<Scalar>,
+
+    grid: NeighbourGrid,
+    accelerations: Vec<Acceleration>,
+}
+
+impl Sph {
+    pub fn new(
+        rect: Rect,
+        smoothing_length: Length,
+        viscosity: Scalar,
+        equation_of_state: Box<dyn EquationOfState>,
+        density_kernel: Box<dyn Kernel>,
+        pressure_kernel: Box<dyn Kernel>,
+    ) -> Self {
+        Self {
+            smoothing_length,
+            viscosity,
+            equation_of_state,
+            density_kernel,
+            pressure_kernel,
+            densities: Vec::new(),
+            pressures: Vec::new(),
+            grid: NeighbourGrid::new(rect, smoothing_length),
+            accelerations: Vec::new(),
+        }
+    }
+
+    // Müller et al. defaults: poly6 for densities, spiky for pressure gradients
+    pub fn new_default(
+        rect: Rect,
+        smoothing_length: Length,
+        viscosity: Scalar,
+        equation_of_state: Box<dyn EquationOfState>,
+    ) -> Self {
+        Self::new(
+            rect,
+            smoothing_length,
+            viscosity,
+            equation_of_state,
+            Box::new(Poly6Kernel),
+            Box::new(SpikyKernel),
+        )
+    }
+
+    // Minimum image interactions across the edges of the periodic rect
+    pub fn with_periodic(mut self, periodic: PeriodicBoundary) -> Self {
+        self.grid = self.grid.with_periodic(periodic);
+        self
+    }
+
+    fn compute_densities(&mut self, particles: &Particles) {
+        let _span = tracy_client::span!("SPH densities");
+
+        let h = self.smoothing_length;
+        let grid = &self.grid;
+        let kernel = &self.density_kernel;
+        let equation_of_state = &self.equation_of_state;
+
+        self.densities.resize(particles.len(), 0.);
+        self.pressures.resize(particles.len(), 0.);
+
+        self.densities
+            .par_iter_mut()
+            .zip(self.pressures.par_iter_mut())
+            .zip(particles.positions.par_iter())
+            .for_each(|((density, pressure), position)| {
+                // Includes the particle itself
+                *density = 0.;
+                grid.for_each_neighbour(
+                    &particles.positions,
+                    *position,
+                    h,
+                    |j, _, distance_squared| {
+                        *density += particles.masses[j] * kernel.value(distance_squared.sqrt(), h);
+                    },
+                );
+                *pressure = equation_of_state.pressure(*density);
+            });
+    }
+
+    fn compute_accelerations(&mut self, particles: &Particles) {
+        let _span = tracy_client::span!("SPH forces");
+
+        let h = self.smoothing_length;
+        let viscosity = self.viscosity;
+        let grid = &self.grid;
+        let kernel = &self.pressure_kernel;
+        let densities = &self.densities;
+        let pressures = &self.pressures;
+
+        self.accelerations.resize(particles.len(), Vector2::zeros());
+        self.accelerations
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, acceleration)| {
+                *acceleration = Vector2::zeros();
+
+                let density = densities[i];
+                let pressure_term = pressures[i] / (density * density);
+                let velocity = particles.velocities[i];
+
+                grid.for_each_neighbour(
+                    &particles.positions,
+                    particles.positions[i],
+                    h,
+                    |j, distance_v, distance_squared| {
+                        if j == i || distance_squared == 0. {
+                            return;
+                        }
+
+                        let distance = distance_squared.sqrt();
+                        let gradient = distance_v * (kernel.derivative(distance, h) / distance);
+                        let mass = particles.masses[j];
+                        let other_density = densities[j];
+
+                        // Symmetric pressure gradient
+                        let other_pressure_term = pressures[j] / (other_density * other_density);
+                        *acceleration -= mass * (pressure_term + other_pressure_term) * gradient;
+
+                        // Viscosity (Morris et al. formulation, kernel independent)
+                        let velocity_diff = velocity - particles.velocities[j];
+                        let coef = 2. * viscosity * mass * distance_v.dot(&gradient)
+                            / (density * other_density * (distance_squared + 0.01 * h * h));
+                        *acceleration += coef * velocity_diff;
+                    },
+                );
+            });
+    }
+}
+
+impl System for Sph {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        self.grid.insert_particles(&particles.positions);
+
+        self.compute_densities(particles);
+        self.compute_accelerations(particles);
+
+        particles
+            .velocities
+            .par_iter_mut()
+            .zip(self.accelerations.par_iter())
+            .for_each(|(velocity, acceleration)| {
+                *velocity += acceleration * dt;
+            });
+    }
+
+    // The smoothing length is fixed by the neighbour grid
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("viscosity", &mut self.viscosity);
+    }
+}
@@ -0,0 +1,389 @@
+// Ordered systems of a simulation, found by handle or name so events & input callbacks don't
+// depend on their position. Disabled systems stay in place but are not updated.
+//
+// Each step, systems run in list order except that a system waits for the ones it runs after.
+// A system with a period is only updated every n steps or once enough simulated time passed, with
+// the time elapsed since its last update. When concurrent is set, consecutive systems which can
+// run on other threads and touch disjoint particle columns are updated together, which gives the
+// same result as updating them in order.
+use rayon::prelude::*;
+
+use super::{
+    particles::{Columns, Particles},
+    systems::System,
+    types::Time,
+};
+
+// Stays valid when other systems are added or removed, not reused after a removal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemHandle(u64);
+
+// Where a system is inserted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    First,
+    Last,
+    Before(SystemHandle),
+    After(SystemHandle),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Steps(usize), // Steps(1) is every step
+    Time(Time),   // Simulated time
+}
+
+struct Entry {
+    handle: SystemHandle,
+    name: Option<String>,
+    enabled: bool,
+    system: Box<dyn System>,
+    period: Period,
+    after: Vec<SystemHandle>,
+    // Since the last update
+    steps: usize,
+    elapsed: Time,
+}
+
+impl Entry {
+    // Time to update with when due
+    fn tick(&mut self, dt: Time) -> Option<Time> {
+        self.steps += 1;
+        self.elapsed += dt;
+        let due = match self.period {
+            Period::Steps(steps) => self.steps >= steps,
+            Period::Time(time) => self.elapsed >= time,
+        };
+        if !due {
+            return None;
+        }
+
+        let elapsed = self.elapsed;
+        self.steps = 0;
+        self.elapsed = 0.;
+        Some(elapsed)
+    }
+
+    fn update(&mut self, i: usize, particles: &mut Particles, dt: Time) {
+        let span = tracy_client::span!("System");
+        match &self.name {
+            Some(name) => span.emit_text(&format!("[{}] {} {}", i, name, self.system.type_name())),
+            None => span.emit_text(&format!("[{}] {}", i, self.system.type_name())),
+        }
+        self.system.update(particles, dt);
+    }
+}
+
+pub struct SystemList {
+    pub concurrent: bool,
+    entries: Vec<Entry>,
+    next_handle: u64,
+    order: Option<Vec<usize>>, // Update order, None when it has to be computed again
+}
+
+impl SystemList {
+    pub fn new() -> Self {
+        Self {
+            concurrent: false,
+            entries: Vec::new(),
+            next_handle: 0,
+            order: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn index(&self, handle: SystemHandle) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.handle == handle)
+    }
+
+    fn entry(&self, handle: SystemHandle) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.handle == handle)
+    }
+
+    fn entry_mut(&mut self, handle: SystemHandle) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|entry| entry.handle == handle)
+    }
+
+    // None when placed relative to a missing system, panics if the name is already used
+    pub fn insert(
+        &mut self,
+        placement: Placement,
+        name: Option<&str>,
+        system: Box<dyn System>,
+    ) -> Option<SystemHandle> {
+        let index = match placement {
+            Placement::First => 0,
+            Placement::Last => self.entries.len(),
+            Placement::Before(handle) => self.index(handle)?,
+            Placement::After(handle) => self.index(handle)? + 1,
+        };
+        if let Some(name) = name {
+            assert!(
+                self.handle(name).is_none(),
+                "System name {} already used",
+                name
+            );
+        }
+
+        let handle = SystemHandle(self.next_handle);
+        self.next_handle += 1;
+        self.entries.insert(
+            index,
+            Entry {
+                handle,
+                name: name.map(str::to_string),
+                enabled: true,
+                system,
+                period: Period::Steps(1),
+                after: Vec::new(),
+                steps: 0,
+                elapsed: 0.,
+            },
+        );
+        self.order = None;
+        Some(handle)
+    }
+
+    pub fn add(&mut self, system: Box<dyn System>) -> SystemHandle {
+        self.insert(Placement::Last, None, system).unwrap()
+    }
+
+    pub fn add_named(&mut self, name: &str, system: Box<dyn System>) -> SystemHandle {
+        self.insert(Placement::Last, Some(name), system).unwrap()
+    }
+
+    pub fn handle(&self, name: &str) -> Option<SystemHandle> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name.as_deref() == Some(name))
+            .map(|entry| entry.handle)
+    }
+
+    pub fn contains(&self, handle: SystemHandle) -> bool {
+        self.index(handle).is_some()
+    }
+
+    pub fn name(&self, handle: SystemHandle) -> Option<&str> {
+        self.entry(handle)?.name.as_deref()
+    }
+
+    pub fn get_mut(&mut self, handle: SystemHandle) -> Option<&mut Box<dyn System>> {
+        self.entry_mut(handle).map(|entry| &mut entry.system)
+    }
+
+    pub fn is_enabled(&self, handle: SystemHandle) -> Option<bool> {
+        Some(self.entry(handle)?.enabled)
+    }
+
+    // False when the system is missing, its period starts again when enabled
+    pub fn set_enabled(&mut self, handle: SystemHandle, enabled: bool) -> bool {
+        let Some(entry) = self.entry_mut(handle) else {
+            return false;
+        };
+        if enabled && !entry.enabled {
+            entry.steps = 0;
+            entry.elapsed = 0.;
+        }
+        entry.enabled = enabled;
+        true
+    }
+
+    pub fn period(&self, handle: SystemHandle) -> Option<Period> {
+        Some(self.entry(handle)?.period)
+    }
+
+    // False when the system is missing
+    pub fn set_period(&mut self, handle: SystemHandle, period: Period) -> bool {
+        match period {
+            Period::Steps(steps) => assert!(steps > 0, "System period must be at least 1 step"),
+            Period::Time(time) => assert!(time > 0., "System period must be strictly positive"),
+        }
+        match self.entry_mut(handle) {
+            Some(entry) => {
+                entry.period = period;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The system is updated after the dependency when both run in the same step. False when one
+    // is missing or the dependency already runs after the system.
+    pub fn run_after(&mut self, handle: SystemHandle, dependency: SystemHandle) -> bool {
+        if !self.contains(dependency) || self.depends_on(dependency, handle) {
+            return false;
+        }
+        let Some(entry) = self.entry_mut(handle) else {
+            return false;
+        };
+        if !entry.after.contains(&dependency) {
+            entry.after.push(dependency);
+        }
+        self.order = None;
+        true
+    }
+
+    // Directly or through other systems, a system depends on itself
+    fn depends_on(&self, handle: SystemHandle, dependency: SystemHandle) -> bool {
+        handle == dependency
+            || self.entry(handle).is_some_and(|entry| {
+                entry
+                    .after
+                    .iter()
+                    .any(|after| self.depends_on(*after, dependency))
+            })
+    }
+
+    // Keeps the handle, name, enabled flag & schedule, gives back the previous system. The new
+    // system is dropped when the handle is missing.
+    pub fn replace(
+        &mut self,
+        handle: SystemHandle,
+        system: Box<dyn System>,
+    ) -> Option<Box<dyn System>> {
+        let entry = self.entry_mut(handle)?;
+        Some(std::mem::replace(&mut entry.system, system))
+    }
+
+    // The systems which ran after it don't wait anymore
+    pub fn remove(&mut self, handle: SystemHandle) -> Option<Box<dyn System>> {
+        let index = self.index(handle)?;
+        let entry = self.entries.remove(index);
+        for other in self.entries.iter_mut() {
+            other.after.retain(|after| *after != handle);
+        }
+        self.order = None;
+        Some(entry.system)
+    }
+
+    // In list order, disabled ones included
+    pub fn handles(&self) -> impl Iterator<Item = SystemHandle> + '_ {
+        self.entries.iter().map(|entry| entry.handle)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn System>> {
+        self.entries.iter_mut().map(|entry| &mut entry.system)
+    }
+
+    // Positions in update order: the first system in list order whose dependencies all ran, there
+    // is always one as dependency cycles are refused
+    fn compute_order(&self) -> Vec<usize> {
+        let mut done = vec![false; self.entries.len()];
+        let mut order = Vec::with_capacity(self.entries.len());
+        while order.len() < self.entries.len() {
+            let next = (0..self.entries.len())
+                .find(|&i| {
+                    !done[i]
+                        && self.entries[i]
+                            .after
+                            .iter()
+                            .all(|after| self.index(*after).is_none_or(|j| done[j]))
+                })
+                .unwrap();
+            done[next] = true;
+            order.push(next);
+        }
+        order
+    }
+
+    pub fn update(&mut self, particles: &mut Particles, dt: Time) {
+        let order = match self.order.take() {
+            Some(order) => order,
+            None => self.compute_order(),
+        };
+
+        // Positions & times of the systems due this step
+        let mut due = Vec::new();
+        for &i in order.iter() {
+            let entry = &mut self.entries[i];
+            if !entry.enabled {
+                continue;
+            }
+            if let Some(elapsed) = entry.tick(dt) {
+                due.push((i, elapsed));
+            }
+        }
+        self.order = Some(order);
+
+        let mut start = 0;
+        while start < due.len() {
+            let end = if self.concurrent {
+                self.concurrent_end(&due, start)
+            } else {
+                start + 1
+            };
+
+            if end - start == 1 {
+                let (i, elapsed) = due[start];
+                self.entries[i].update(i, particles, elapsed);
+            } else {
+                self.update_concurrent(&due[start..end], particles);
+            }
+            start = end;
+        }
+    }
+
+    // End of the systems from start which can be updated together, at least start + 1
+    fn concurrent_end(&mut self, due: &[(usize, Time)], start: usize) -> usize {
+        let mut columns = Columns::NONE;
+        let mut end = start;
+        for &(i, _) in due[start..].iter() {
+            let system = &mut self.entries[i].system;
+            let system_columns = system.columns();
+            if system.concurrent().is_none() || columns.intersects(system_columns) {
+                break;
+            }
+            columns = columns | system_columns;
+            end += 1;
+        }
+        end.max(start + 1)
+    }
+
+    // Each system gets its own columns of the particles
+    fn update_concurrent(&mut self, due: &[(usize, Time)], particles: &mut Particles) {
+        let _span = tracy_client::span!("Concurrent systems");
+
+        let mut updates = self
+            .entries
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let &(_, elapsed) = due.iter().find(|(j, _)| *j == i)?;
+                let columns = entry.system.columns();
+                let system = entry.system.concurrent()?;
+                Some((system, columns, particles.take_columns(columns), elapsed))
+            })
+            .collect::<Vec<_>>();
+
+        updates
+            .par_iter_mut()
+            .for_each(|(system, _, particles, elapsed)| system.update(particles, *elapsed));
+
+        for (_, columns, taken, _) in updates {
+            particles.restore_columns(taken, columns);
+        }
+    }
+}
+
+impl Default for SystemList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Vec<Box<dyn System>>> for SystemList {
+    fn from(systems: Vec<Box<dyn System>>) -> Self {
+        let mut list = Self::new();
+        for system in systems {
+            list.add(system);
+        }
+        list
+    }
+}
@@ -2,18 +2,34 @@ use nalgebra::Vector2;
 use rayon::prelude::*;
 
 use super::{
-    areas::Area,
+    areas::{Area, Rect},
     color::Color,
     forces::Force,
     integrator::Integrator,
-    particles::{ParticleFactory, Particles},
-    types::{Force as TypeForce, Scalar, Time},
+    particles::{Columns, ParticleFactory, Particles},
+    periodic::PeriodicBoundary,
+    tunables::Tunables,
+    types::{Force as TypeForce, Position, Scalar, Time, Velocity},
 };
 use crate::utils::smooth_rate::SmoothRate;
 
 pub trait System {
     fn update(&mut self, particles: &mut Particles, dt: Time);
 
+    // Named parameters changed while running
+    fn tunables(&mut self, _tunables: &mut Tunables) {}
+
+    // Particle columns read or written by update. When the system list runs systems
+    // concurrently, update only gets these columns, the others are empty.
+    fn columns(&self) -> Columns {
+        Columns::ALL
+    }
+
+    // Some(self) for systems which can be updated on another thread
+    fn concurrent(&mut self) -> Option<&mut (dyn System + Send)> {
+        None
+    }
+
     fn type_name(&self) -> &'static str {
         std::any::type_name::<Self>()
     }
@@ -80,13 +96,71 @@ pub struct Wall {
     pub restitution: Scalar,
 }
 
+// Maximum number of bounces resolved per particle & step before clamping
+const WALL_MAX_BOUNCES: usize = 4;
+
+impl Wall {
+    // Fraction of the path from -> to where it crosses [min, max] and the crossed boundary
+    #[inline]
+    fn time_of_impact(
+        from: Scalar,
+        to: Scalar,
+        min: Scalar,
+        max: Scalar,
+    ) -> Option<(Scalar, Scalar)> {
+        if to < min && from >= min {
+            Some(((min - from) / (to - from), min))
+        } else if to > max && from <= max {
+            Some(((max - from) / (to - from), max))
+        } else {
+            None
+        }
+    }
+
+    // Swept collision: the path travelled during the last step (position - velocity * dt)
+    // is bounced on the walls at the exact point of impact, the remaining motion is reflected
+    #[inline]
+    fn sweep(&self, position: &mut Position, velocity: &mut Velocity, dt: Time) {
+        let mut from = *position - *velocity * dt;
+        let mut to = *position;
+
+        for _ in 0..WALL_MAX_BOUNCES {
+            let x_impact = Self::time_of_impact(from.x, to.x, self.x_min, self.x_max);
+            let y_impact = Self::time_of_impact(from.y, to.y, self.y_min, self.y_max);
+
+            // Earliest impact (axis, fraction, boundary)
+            let (axis, t, boundary) = match (x_impact, y_impact) {
+                (Some((tx, bx)), Some((ty, _))) if tx <= ty => (0, tx, bx),
+                (_, Some((ty, by))) => (1, ty, by),
+                (Some((tx, bx)), None) => (0, tx, bx),
+                (None, None) => break,
+            };
+
+            let mut hit = from + (to - from) * t;
+            hit[axis] = boundary;
+
+            let mut remaining = to - hit;
+            remaining[axis] = -remaining[axis] * self.restitution;
+            velocity[axis] = -velocity[axis] * self.restitution;
+
+            from = hit;
+            to = hit + remaining;
+        }
+
+        *position = to;
+    }
+}
+
 impl System for Wall {
-    fn update(&mut self, particles: &mut Particles, _dt: Time) {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
         particles
             .positions
             .par_iter_mut()
             .zip(particles.velocities.par_iter_mut())
             .for_each(|(position, velocity)| {
+                self.sweep(position, velocity, dt);
+
+                // Particles which started outside or are out of bounces are clamped
                 if position.x < self.x_min {
                     position.x = self.x_min;
                     velocity.x = -velocity.x * self.restitution;
@@ -104,6 +178,18 @@ impl System for Wall {
                 }
             });
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("restitution", &mut self.restitution);
+    }
+
+    fn columns(&self) -> Columns {
+        Columns::POSITIONS | Columns::VELOCITIES
+    }
+
+    fn concurrent(&mut self) -> Option<&mut (dyn System + Send)> {
+        Some(self)
+    }
 }
 
 pub struct Loop {
@@ -113,22 +199,32 @@ pub struct Loop {
     pub y_max: Scalar,
 }
 
+impl Loop {
+    // Periodic boundary to use in forces & neighbour queries for the same space
+    pub fn boundary(&self) -> PeriodicBoundary {
+        PeriodicBoundary::new(Rect::new(
+            Vector2::new(self.x_min, self.y_min),
+            Vector2::new(self.x_max - self.x_min, self.y_max - self.y_min),
+        ))
+    }
+}
+
 impl System for Loop {
     fn update(&mut self, particles: &mut Particles, _dt: Time) {
+        let boundary = self.boundary();
         particles.positions.par_iter_mut().for_each(|position| {
-            if position.x < self.x_min {
-                position.x = self.x_max;
-            } else if position.x > self.x_max {
-                position.x = self.x_min;
-            }
-
-            if position.y < self.y_min {
-                position.y = self.y_max;
-            } else if position.y > self.y_max {
-                position.y = self.y_min;
-            }
+            // Keep the offset past the edge so distances stay consistent
+            *position = boundary.wrap(*position);
         });
     }
+
+    fn columns(&self) -> Columns {
+        Columns::POSITIONS
+    }
+
+    fn concurrent(&mut self) -> Option<&mut (dyn System + Send)> {
+        Some(self)
+    }
 }
 
 pub struct Void {
@@ -175,6 +271,7 @@ impl System for Physics {
             let span = tracy_client::span!("Force");
             span.emit_text(&format!("[{}] {}", i, force.type_name()));
 
+            force.update(dt);
             force.apply(particles, &mut self.forces_buffer);
         }
 
@@ -189,6 +286,14 @@ impl System for Physics {
         self.integrator
             .integrate_vec(&self.forces_buffer, &mut particles.velocities, dt);
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        for (i, force) in self.forces.iter_mut().enumerate() {
+            tunables.nested(&format!("forces.{}", i), |tunables| {
+                force.tunables(tunables)
+            });
+        }
+    }
 }
 
 pub struct VelocityIntegrator {
@@ -220,4 +325,16 @@ impl System for ColorWheel {
             *color = Color::from_hsva(h, s, v, a);
         });
     }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("speed", &mut self.speed);
+    }
+
+    fn columns(&self) -> Columns {
+        Columns::COLORS
+    }
+
+    fn concurrent(&mut self) -> Option<&mut (dyn System + Send)> {
+        Some(self)
+    }
 }
@@ -0,0 +1,172 @@
+// Systems holding the particles at a target temperature (units where the Boltzmann constant is 1,
+// as the Maxwell-Boltzmann distributions). Velocity rescaling & Berendsen scale the velocities
+// relative to the center of mass, which keeps the momentum and any bulk motion. Langevin adds a
+// friction towards a bath velocity (as UniformDrag, but at a rate independent of the mass) and
+// random kicks making up for the energy it takes.
+use nalgebra::Vector2;
+use rand::RngCore;
+use rand_pcg::Pcg64Mcg;
+use rayon::prelude::*;
+
+use super::{
+    diagnostics::{center_of_mass_velocity, kinetic_temperature},
+    distributions::standard_normal,
+    particles::{Columns, Particles},
+    random::{chunk_rng, CHUNK_SIZE},
+    systems::System,
+    tunables::Tunables,
+    types::{Scalar, Temperature, Time, Velocity},
+};
+
+// Velocities relative to the center of mass multiplied by factor
+fn scale_temperature(particles: &mut Particles, factor: Scalar) {
+    let center = center_of_mass_velocity(particles);
+    particles.velocities.par_iter_mut().for_each(|velocity| {
+        *velocity = center + (*velocity - center) * factor;
+    });
+}
+
+fn check_temperature(temperature: Temperature) {
+    assert!(temperature >= 0., "Temperature must be positive");
+}
+
+// Sets the temperature to the target each update, the crudest thermostat
+pub struct VelocityRescaling {
+    pub temperature: Temperature,
+}
+
+impl VelocityRescaling {
+    pub fn new(temperature: Temperature) -> Self {
+        check_temperature(temperature);
+        Self { temperature }
+    }
+}
+
+impl System for VelocityRescaling {
+    fn update(&mut self, particles: &mut Particles, _dt: Time) {
+        let temperature = kinetic_temperature(particles);
+        if temperature > 0. {
+            scale_temperature(particles, (self.temperature / temperature).sqrt());
+        }
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("temperature", &mut self.temperature);
+    }
+
+    fn columns(&self) -> Columns {
+        Columns::VELOCITIES | Columns::MASSES
+    }
+
+    fn concurrent(&mut self) -> Option<&mut (dyn System + Send)> {
+        Some(self)
+    }
+}
+
+// Relaxes the temperature towards the target with time constant tau, rescales it completely when
+// dt >= tau
+pub struct Berendsen {
+    pub temperature: Temperature,
+    pub tau: Time,
+}
+
+impl Berendsen {
+    pub fn new(temperature: Temperature, tau: Time) -> Self {
+        check_temperature(temperature);
+        assert!(tau > 0., "Berendsen tau must be strictly positive");
+        Self { temperature, tau }
+    }
+}
+
+impl System for Berendsen {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        let temperature = kinetic_temperature(particles);
+        if temperature > 0. {
+            let rate = (dt / self.tau).min(1.);
+            let squared = 1. + rate * (self.temperature / temperature - 1.);
+            scale_temperature(particles, squared.sqrt());
+        }
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("temperature", &mut self.temperature);
+        tunables.scalar("tau", &mut self.tau);
+    }
+
+    fn columns(&self) -> Columns {
+        Columns::VELOCITIES | Columns::MASSES
+    }
+
+    fn concurrent(&mut self) -> Option<&mut (dyn System + Send)> {
+        Some(self)
+    }
+}
+
+// Velocities relative to the bath decay at rate gamma (per unit time, whatever the mass) and get
+// gaussian kicks of variance T / m (1 - e^(-2 gamma dt)) per component, the exact solution of the
+// Ornstein-Uhlenbeck process over dt. Particles without mass are left alone. The noise only
+// depends on the rng, not on the thread count.
+pub struct Langevin {
+    pub temperature: Temperature,
+    pub gamma: Scalar,
+    pub velocity: Velocity, // Of the bath
+    rng: Pcg64Mcg,
+}
+
+impl Langevin {
+    pub fn new(rng: Pcg64Mcg, temperature: Temperature, gamma: Scalar) -> Self {
+        check_temperature(temperature);
+        assert!(gamma > 0., "Langevin gamma must be strictly positive");
+        Self {
+            temperature,
+            gamma,
+            velocity: Velocity::zeros(),
+            rng,
+        }
+    }
+
+    pub fn with_velocity(mut self, velocity: Velocity) -> Self {
+        self.velocity = velocity;
+        self
+    }
+}
+
+impl System for Langevin {
+    fn update(&mut self, particles: &mut Particles, dt: Time) {
+        let decay = (-self.gamma * dt).exp();
+        let variance = self.temperature * (1. - decay * decay);
+        let bath = self.velocity;
+        let seed = ((self.rng.next_u64() as u128) << 64) | self.rng.next_u64() as u128;
+
+        particles
+            .velocities
+            .par_chunks_mut(CHUNK_SIZE)
+            .zip(particles.masses.par_chunks(CHUNK_SIZE))
+            .enumerate()
+            .for_each(|(chunk, (velocities, masses))| {
+                let mut rng = chunk_rng(seed, chunk);
+                for (velocity, mass) in velocities.iter_mut().zip(masses.iter()) {
+                    if *mass <= 0. {
+                        continue;
+                    }
+                    let noise = Vector2::new(standard_normal(&mut rng), standard_normal(&mut rng));
+                    *velocity =
+                        bath + (*velocity - bath) * decay + noise * (variance / mass).sqrt();
+                }
+            });
+    }
+
+    fn tunables(&mut self, tunables: &mut Tunables) {
+        tunables.scalar("temperature", &mut self.temperature);
+        tunables.scalar("gamma", &mut self.gamma);
+        tunables.vector("velocity", &mut self.velocity);
+    }
+
+    fn columns(&self) -> Columns {
+        Columns::VELOCITIES | Columns::MASSES
+    }
+
+    fn concurrent(&mut self) -> Option<&mut (dyn System + Send)> {
+        Some(self)
+    }
+}
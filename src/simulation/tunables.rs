@@ -0,0 +1,208 @@
+// Named parameters of systems & forces changed while the simulation runs, without touching the
+// particles. Names are dotted paths from the system list, e.g. "systems.1.forces.0.theta" for the
+// Barnes-Hut theta of a quadtree force in the second system. Named systems are found by name
+// instead, e.g. "systems.thermostat.tau", so their tunables follow them when the list changes.
+use log::{info, warn};
+use nalgebra::Vector2;
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use super::{
+    simulation::{Simulation, SimulationRunner},
+    system_list::SystemList,
+    types::Scalar,
+};
+use crate::utils::file_watcher::FileWatcher;
+
+// Visits the tunables, setting the ones with a value
+pub struct Tunables {
+    prefix: String,
+    values: BTreeMap<String, Scalar>, // Removed once set
+    visited: Vec<(String, Scalar)>,   // Values after the visit
+    changes: Vec<(String, Scalar, Scalar)>,
+}
+
+impl Tunables {
+    pub fn new(values: BTreeMap<String, Scalar>) -> Self {
+        Self {
+            prefix: String::new(),
+            values,
+            visited: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    // Only lists the tunables
+    pub fn new_empty() -> Self {
+        Self::new(BTreeMap::new())
+    }
+
+    fn path(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.prefix, name)
+        }
+    }
+
+    // True when a different value was set
+    pub fn scalar(&mut self, name: &str, value: &mut Scalar) -> bool {
+        let path = self.path(name);
+        let changed = match self.values.remove(&path) {
+            Some(new) if new != *value => {
+                self.changes.push((path.clone(), *value, new));
+                *value = new;
+                true
+            }
+            _ => false,
+        };
+        self.visited.push((path, *value));
+        changed
+    }
+
+    // As name.x & name.y
+    pub fn vector(&mut self, name: &str, value: &mut Vector2<Scalar>) -> bool {
+        self.nested(name, |tunables| {
+            let x = tunables.scalar("x", &mut value.x);
+            let y = tunables.scalar("y", &mut value.y);
+            x || y
+        })
+    }
+
+    // Tunables of a part, prefixed with its name
+    pub fn nested<T>(&mut self, name: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        let len = self.prefix.len();
+        self.prefix = self.path(name);
+        let result = f(self);
+        self.prefix.truncate(len);
+        result
+    }
+
+    // By name, or by position in the list for unnamed systems, disabled systems included
+    pub fn systems(&mut self, systems: &mut SystemList) {
+        let handles = systems.handles().collect::<Vec<_>>();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let name = match systems.name(handle) {
+                Some(name) => format!("systems.{}", name),
+                None => format!("systems.{}", i),
+            };
+            let system = systems.get_mut(handle).unwrap();
+            self.nested(&name, |tunables| system.tunables(tunables));
+        }
+    }
+
+    // Names given a value but not visited
+    pub fn unused(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+
+    pub fn visited(&self) -> &[(String, Scalar)] {
+        &self.visited
+    }
+
+    // Name, previous & new value
+    pub fn changes(&self) -> &[(String, Scalar, Scalar)] {
+        &self.changes
+    }
+}
+
+// Numbers of a TOML file, nested tables give the prefixes: `[systems.1.forces.0]` then
+// `theta = 1.2` or `gravity.coef = 0.03`
+pub fn read_tunables<P: AsRef<Path>>(path: P) -> io::Result<BTreeMap<String, Scalar>> {
+    let path = path.as_ref();
+    let invalid_data = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), message),
+        )
+    };
+
+    let source = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    let table: toml::Table = toml::from_str(&source).map_err(|e| invalid_data(e.to_string()))?;
+
+    let mut values = BTreeMap::new();
+    flatten(&table, "", &mut values).map_err(invalid_data)?;
+    Ok(values)
+}
+
+fn flatten(
+    table: &toml::Table,
+    prefix: &str,
+    values: &mut BTreeMap<String, Scalar>,
+) -> Result<(), String> {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::Float(value) => {
+                values.insert(path, *value);
+            }
+            toml::Value::Integer(value) => {
+                values.insert(path, *value as Scalar);
+            }
+            toml::Value::Table(table) => flatten(table, &path, values)?,
+            other => {
+                return Err(format!(
+                    "{}: expected a number, got {}",
+                    path,
+                    other.type_str()
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+// Applies a tunables file before the first step, then each time the file changes. Values which
+// are not in the file anymore are kept.
+pub struct TunedSimulationRunner {
+    pub runner: Box<dyn SimulationRunner>,
+    pub watcher: FileWatcher,
+    loaded: bool,
+}
+
+impl TunedSimulationRunner {
+    pub fn new<P: AsRef<Path>>(runner: Box<dyn SimulationRunner>, path: P) -> Self {
+        Self {
+            runner,
+            watcher: FileWatcher::new(path),
+            loaded: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.watcher.path
+    }
+
+    fn apply(&self, systems: &mut SystemList) {
+        let values = match read_tunables(self.path()) {
+            Ok(values) => values,
+            Err(e) => {
+                warn!("{} (keeping the current values)", e);
+                return;
+            }
+        };
+
+        let mut tunables = Tunables::new(values);
+        tunables.systems(systems);
+        for (name, previous, value) in tunables.changes() {
+            info!("{}: {} -> {}", name, previous, value);
+        }
+        for name in tunables.unused() {
+            warn!("{}: no tunable named {}", self.path().display(), name);
+        }
+    }
+}
+
+impl SimulationRunner for TunedSimulationRunner {
+    fn step(&mut self, sim: &mut Simulation) {
+        if !self.loaded || self.watcher.changed() {
+            self.loaded = true;
+            self.apply(&mut sim.systems);
+        }
+        self.runner.step(sim);
+    }
+}
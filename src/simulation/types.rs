@@ -7,6 +7,7 @@ pub type Time = Scalar;
 pub type Energy = Scalar;
 pub type Temperature = Scalar;
 pub type Length = Scalar;
+pub type Angle = Scalar; // Radians
 
 pub type Position = Vector2<Scalar>;
 pub type Velocity = Vector2<Scalar>;
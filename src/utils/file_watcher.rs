@@ -0,0 +1,52 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use super::timer::Timer;
+
+// Polls the modification time of a file, at most once per interval
+pub struct FileWatcher {
+    pub path: PathBuf,
+    pub interval: Duration,
+    modified: Option<SystemTime>,
+    timer: Timer,
+}
+
+impl FileWatcher {
+    // Only the changes made after its creation are reported
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        Self {
+            modified: modification_time(&path),
+            path,
+            interval: Duration::from_millis(500),
+            timer: Timer::new_now(),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // True once per modification, a missing file is not a change
+    pub fn changed(&mut self) -> bool {
+        if self.timer.elapsed() < self.interval {
+            return false;
+        }
+        self.timer.reset();
+
+        let modified = modification_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -1,3 +1,4 @@
+pub mod file_watcher;
 pub mod smooth_rate;
 pub mod sorted_vec;
 pub mod timer;